@@ -0,0 +1,215 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use itertools::Itertools;
+use crate::models::{DBState, FieldKind, FieldValue, ItemId};
+
+/// Custom user field the `--assignee` filter looks at.
+pub const ASSIGNEE_FIELD: &str = "assignee";
+
+/// Limits the calendar to some stories, and to the sprints those stories are in.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct CalendarFilter {
+    /// User name in the `assignee` field, with or without the leading `@`.
+    pub assignee: Option<String>,
+    pub epic_id: Option<ItemId>
+}
+
+/// Writes an iCalendar file with an all-day event for every story due date and
+/// every sprint. Event UIDs only depend on the item id, so importing a newer export
+/// updates the events instead of adding copies.
+pub fn ical_export(db_state: &DBState, filter: &CalendarFilter, now: DateTime<Utc>) -> Result<String> {
+    let assignee = match &filter.assignee {
+        Some(assignee) => {
+            let definition = db_state.field_definition(ASSIGNEE_FIELD)
+                .filter(|definition| definition.kind == FieldKind::User && definition.target.applies_to_stories())
+                .ok_or_else(|| anyhow!("there is no '{}' user field for stories", ASSIGNEE_FIELD))?;
+            Some(definition.parse_value(assignee)?)
+        }
+        None => None
+    };
+    if let Some(epic_id) = filter.epic_id {
+        db_state.epics.get(&epic_id).ok_or_else(|| anyhow!("no epic {}", db_state.item_key(epic_id)))?;
+    }
+
+    let stories = db_state.stories
+        .iter()
+        .filter(|(id, _)| filter.epic_id.is_none() || db_state.epic_id_of_story(**id) == filter.epic_id)
+        .filter(|(_, story)| assignee.is_none() || story.fields.get(ASSIGNEE_FIELD) == assignee.as_ref())
+        .sorted_by_key(|(id, _)| **id)
+        .collect::<Vec<_>>();
+    let filtered = filter.assignee.is_some() || filter.epic_id.is_some();
+    let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        "PRODID:-//cli-project-jira//EN".to_owned(),
+        "CALSCALE:GREGORIAN".to_owned(),
+    ];
+
+    for (id, sprint) in db_state.sprints.iter().sorted_by_key(|(id, _)| **id) {
+        if filtered && !stories.iter().any(|(_, story)| story.sprint_id == Some(*id)) {
+            continue;
+        }
+        lines.extend(all_day_event(
+            &format!("sprint-{}", id),
+            &stamp,
+            sprint.start,
+            sprint.end,
+            &format!("Sprint: {}", sprint.name),
+            &format!("{} ({})", sprint.goal, sprint.state)
+        ));
+    }
+
+    for (id, story) in stories {
+        let Some(due_date) = story.due_date else {
+            continue;
+        };
+        let epic = db_state.epic_id_of_story(*id)
+            .and_then(|epic_id| db_state.epics.get(&epic_id))
+            .map(|epic| format!(" in epic \"{}\"", epic.name))
+            .unwrap_or_default();
+        let mut description = format!("{} {}{}, {}", story.issue_type, db_state.item_key(*id), epic, story.status);
+        if let Some(FieldValue::User(assignee)) = story.fields.get(ASSIGNEE_FIELD) {
+            description.push_str(&format!(", assigned to @{}", assignee));
+        }
+        lines.extend(all_day_event(&format!("story-{}", id), &stamp, due_date, due_date, &format!("Due: {}", story.name), &description));
+    }
+
+    lines.push("END:VCALENDAR".to_owned());
+    Ok(lines.into_iter().map(|line| fold(&line) + "\r\n").collect())
+}
+
+/// An event from `start` to `end`, both included.
+fn all_day_event(uid: &str, stamp: &str, start: NaiveDate, end: NaiveDate, summary: &str, description: &str) -> Vec<String> {
+    let end = end.checked_add_days(Days::new(1)).unwrap_or(end);
+    vec![
+        "BEGIN:VEVENT".to_owned(),
+        format!("UID:{}@cli-project-jira", uid),
+        format!("DTSTAMP:{}", stamp),
+        format!("DTSTART;VALUE=DATE:{}", start.format("%Y%m%d")),
+        format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")),
+        format!("SUMMARY:{}", escape(summary)),
+        format!("DESCRIPTION:{}", escape(description)),
+        "END:VEVENT".to_owned(),
+    ]
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Splits lines longer than 75 bytes, continuing them on lines starting with a space.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use crate::db::{test_utils::MockDb, ProjectsDatabase};
+    use crate::models::{Epic, FieldDefinition, FieldTarget, Sprint, Story};
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    fn database() -> ProjectsDatabase {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        db.create_field_definition(FieldDefinition::new(ASSIGNEE_FIELD.to_owned(), FieldKind::User, FieldTarget::Stories)).unwrap();
+        let auth = db.create_epic(Epic::new("Auth".to_owned(), "".to_owned())).unwrap();
+        let login = db.create_story(Story::new("Login, SSO".to_owned(), "".to_owned()), auth).unwrap();
+        db.update_story_due_date(login, Some(date(5, 1))).unwrap();
+        db.set_story_field(login, ASSIGNEE_FIELD, "@ann").unwrap();
+        let billing = db.create_epic(Epic::new("Billing".to_owned(), "".to_owned())).unwrap();
+        let invoices = db.create_story(Story::new("Invoices".to_owned(), "".to_owned()), billing).unwrap();
+        db.update_story_due_date(invoices, Some(date(6, 1))).unwrap();
+        db.create_story(Story::new("Undated".to_owned(), "".to_owned()), billing).unwrap();
+        let sprint_id = db.create_sprint(Sprint::new("Sprint 1".to_owned(), "Ship login".to_owned(), date(4, 22), date(5, 3))).unwrap();
+        db.assign_story_to_sprint(login, Some(sprint_id)).unwrap();
+        db
+    }
+
+    #[test]
+    fn ical_export_should_write_events_with_stable_uids() {
+        let db_state = database().read_db().unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 4, 1, 8, 30, 0).unwrap();
+
+        let calendar = ical_export(&db_state, &CalendarFilter::default(), now).unwrap();
+
+        assert_eq!(calendar, [
+            "BEGIN:VCALENDAR",
+            "VERSION:2.0",
+            "PRODID:-//cli-project-jira//EN",
+            "CALSCALE:GREGORIAN",
+            "BEGIN:VEVENT",
+            "UID:sprint-1@cli-project-jira",
+            "DTSTAMP:20240401T083000Z",
+            "DTSTART;VALUE=DATE:20240422",
+            "DTEND;VALUE=DATE:20240504",
+            "SUMMARY:Sprint: Sprint 1",
+            "DESCRIPTION:Ship login (PLANNED)",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:story-2@cli-project-jira",
+            "DTSTAMP:20240401T083000Z",
+            "DTSTART;VALUE=DATE:20240501",
+            "DTEND;VALUE=DATE:20240502",
+            "SUMMARY:Due: Login\\, SSO",
+            "DESCRIPTION:STORY 2 in epic \"Auth\"\\, OPEN\\, assigned to @ann",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:story-4@cli-project-jira",
+            "DTSTAMP:20240401T083000Z",
+            "DTSTART;VALUE=DATE:20240601",
+            "DTEND;VALUE=DATE:20240602",
+            "SUMMARY:Due: Invoices",
+            "DESCRIPTION:STORY 4 in epic \"Billing\"\\, OPEN",
+            "END:VEVENT",
+            "END:VCALENDAR",
+            "",
+        ].join("\r\n"));
+    }
+
+    #[test]
+    fn ical_export_should_filter_by_assignee_and_epic() {
+        let db_state = database().read_db().unwrap();
+        let now = Utc::now();
+
+        let by_assignee = ical_export(&db_state, &CalendarFilter { assignee: Some("ann".to_owned()), epic_id: None }, now).unwrap();
+        assert_eq!(by_assignee.matches("BEGIN:VEVENT").count(), 2);
+        assert_eq!(by_assignee.contains("UID:story-2@"), true);
+
+        let by_epic = ical_export(&db_state, &CalendarFilter { assignee: None, epic_id: Some(3) }, now).unwrap();
+        assert_eq!(by_epic.matches("BEGIN:VEVENT").count(), 1);
+        assert_eq!(by_epic.contains("UID:story-4@"), true);
+
+        assert_eq!(ical_export(&db_state, &CalendarFilter { assignee: None, epic_id: Some(2) }, now).is_err(), true);
+        assert_eq!(ical_export(&DBState::default(), &CalendarFilter { assignee: Some("ann".to_owned()), epic_id: None }, now).is_err(), true);
+    }
+
+    #[test]
+    fn fold_should_split_long_lines() {
+        let line = format!("SUMMARY:{}", "x".repeat(100));
+
+        let folded = fold(&line);
+
+        assert_eq!(folded.split("\r\n").map(str::len).collect::<Vec<_>>(), vec![75, 34]);
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+}
@@ -0,0 +1,441 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use crate::calendar::{ical_export, CalendarFilter};
+use crate::config::Config;
+use crate::dashboard::html_dashboard;
+use crate::csv::{export_csv, import_csv, CsvColumn};
+use crate::db::{convert_database, ProjectsDatabase};
+use crate::jira::{import_jira, JiraMapping};
+use crate::merge::{Conflict, Merge, Side};
+use crate::models::DBState;
+use crate::report::markdown_report;
+use crate::search::{search_stories, StoryFilter};
+use crate::ui::theme::{colors_enabled, Theme};
+
+/// Runs a non-interactive command, e.g. `stories --type bug --status open login`,
+/// `export csv --columns epic_name,story_name`, `import jira issues.json --dry-run`
+/// `convert db.json db.yaml`, `sync origin`, `merge base.json ours.json theirs.json`
+/// or `key-prefix PROJ`.
+/// Output is only colored when stdout is a terminal and `NO_COLOR` isn't set.
+pub fn run_command(db: &ProjectsDatabase, config: &Config, args: &[String]) -> Result<()> {
+    let (command, args) = args
+        .split_first()
+        .ok_or_else(|| anyhow!("no command given"))?;
+
+    match command.as_str() {
+        "stories" => {
+            let db_state = db.read_db()?;
+            let theme = colors_enabled().then(|| Theme::named(config.theme));
+            print!("{}", list_stories(&db_state, theme.as_ref(), args)?);
+            Ok(())
+        }
+        "export" => {
+            let db_state = db.read_db()?;
+            let output = match args.split_first() {
+                Some((format, args)) if format == "csv" => export_csv_command(&db_state, args)?,
+                Some((format, [])) if format == "markdown" => markdown_report(&db_state),
+                Some((format, [])) if format == "html" => html_dashboard(&db_state),
+                Some((format, args)) if format == "ical" => export_ical_command(&db_state, args)?,
+                _ => return Err(anyhow!("export requires a format: csv, markdown, html or ical"))
+            };
+            print!("{}", output);
+            Ok(())
+        }
+        "import" => {
+            let output = match args.split_first() {
+                Some((format, args)) if format == "csv" => import_csv_command(db, args)?,
+                Some((format, args)) if format == "jira" => import_jira_command(db, args)?,
+                _ => return Err(anyhow!("import requires a format: csv or jira"))
+            };
+            print!("{}", output);
+            Ok(())
+        }
+        "convert" => match args {
+            [from, to] => {
+                convert_database(from, to)?;
+                println!("converted '{}' to '{}'", from, to);
+                Ok(())
+            }
+            _ => Err(anyhow!("usage: convert FROM TO, formats follow the extensions .json, .yaml, .yml and .toml"))
+        },
+        "merge" => {
+            print!("{}", merge_command(args, &mut io::stdin().lock(), &mut io::stdout())?);
+            Ok(())
+        }
+        "sync" => {
+            let remote = match args {
+                [] => "origin",
+                [remote] => remote.as_str(),
+                _ => return Err(anyhow!("usage: sync [REMOTE]"))
+            };
+            println!("{}", db.database.sync(remote)?);
+            Ok(())
+        }
+        "key-prefix" => match args {
+            [] => {
+                db.set_key_prefix(None)?;
+                println!("items are shown by number");
+                Ok(())
+            }
+            [prefix] => {
+                db.set_key_prefix(Some(prefix.to_uppercase()))?;
+                println!("items are shown as {}-<number>", prefix.to_uppercase());
+                Ok(())
+            }
+            _ => Err(anyhow!("usage: key-prefix [PREFIX]"))
+        },
+        command => Err(anyhow!("unknown command '{}'", command))
+    }
+}
+
+/// Merges `THEIRS` into `OURS`, or into the `--output` file, asking on `output`
+/// which side to keep for every conflict.
+fn merge_command(args: &[String], input: &mut impl BufRead, output: &mut impl Write) -> Result<String> {
+    let mut paths = vec![];
+    let mut target = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output" | "-o" => target = Some(args.next().ok_or_else(|| anyhow!("--output requires a file"))?),
+            _ => paths.push(arg)
+        }
+    }
+    let [base, ours, theirs] = paths.as_slice() else {
+        return Err(anyhow!("usage: merge BASE OURS THEIRS [--output FILE]"));
+    };
+    let read = |path: &str| ProjectsDatabase::new(path.to_owned()).read_db().with_context(|| anyhow!("couldn't read '{}'", path));
+
+    let merge = Merge::new(&read(base)?, &read(ours)?, &read(theirs)?)?;
+    let mut report = merge.renumbered.iter().map(|renumbered| format!("{}\n", renumbered)).collect::<String>();
+    let total = merge.conflicts.len();
+    let mut number = 0;
+    let state = merge.resolve(|conflict| {
+        number += 1;
+        ask_side(conflict, number, total, input, output)
+    })?;
+
+    let target = target.unwrap_or(ours);
+    ProjectsDatabase::new(target.to_owned()).database.write_db(&state)?;
+    report.push_str(&format!("merged into '{}', {} conflicts resolved\n", target, total));
+    Ok(report)
+}
+
+fn ask_side(conflict: &Conflict, number: usize, total: usize, input: &mut impl BufRead, output: &mut impl Write) -> Result<Side> {
+    let show = |value: &Option<serde_json::Value>| value.as_ref().map_or("(none)".to_owned(), ToString::to_string);
+    writeln!(output, "conflict {} of {}: {}", number, total, conflict)?;
+    writeln!(output, "  base:   {}", show(&conflict.base))?;
+    writeln!(output, "  ours:   {}", show(&conflict.ours))?;
+    writeln!(output, "  theirs: {}", show(&conflict.theirs))?;
+    loop {
+        write!(output, "keep [o] ours | [t] theirs: ")?;
+        output.flush()?;
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 {
+            return Err(anyhow!("merge aborted, nothing was saved"));
+        }
+        match answer.trim() {
+            "o" | "ours" => return Ok(Side::Ours),
+            "t" | "theirs" => return Ok(Side::Theirs),
+            _ => continue
+        }
+    }
+}
+
+fn list_stories(db_state: &DBState, theme: Option<&Theme>, args: &[String]) -> Result<String> {
+    let filter = parse_story_filter(db_state, args)?;
+    let mut output = String::new();
+
+    for hit in search_stories(db_state, &filter) {
+        output.push_str(&format!(
+            "{}\t[{}]\t{}\t{}",
+            db_state.item_key(hit.story_id),
+            db_state.issue_type_icon(&hit.story.issue_type),
+            theme.map_or_else(|| hit.story.status.to_string(), |theme| theme.status(&hit.story.status).paint(&hit.story.status.to_string())),
+            hit.story.name
+        ));
+        for (name, value) in &hit.story.fields {
+            output.push_str(&format!("\t{}={}", name, value));
+        }
+        output.push('\n');
+    }
+
+    Ok(output)
+}
+
+fn export_csv_command(db_state: &DBState, args: &[String]) -> Result<String> {
+    let columns = match args {
+        [] => CsvColumn::all(db_state),
+        [flag, list] if flag == "--columns" || flag == "-c" => CsvColumn::parse_list(list)?,
+        _ => return Err(anyhow!("usage: export csv [--columns name,...]"))
+    };
+    Ok(export_csv(db_state, &columns))
+}
+
+/// `export ical [--assignee NAME] [--epic KEY]`
+fn export_ical_command(db_state: &DBState, args: &[String]) -> Result<String> {
+    let mut filter = CalendarFilter::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--assignee" | "-a" => {
+                let assignee = args.next().ok_or_else(|| anyhow!("--assignee requires a user name"))?;
+                filter.assignee = Some(assignee.to_owned());
+            }
+            "--epic" | "-e" => {
+                let epic_key = args.next().ok_or_else(|| anyhow!("--epic requires an epic key"))?;
+                filter.epic_id = Some(db_state.find_item(epic_key)?);
+            }
+            arg => return Err(anyhow!("unexpected argument '{}'", arg))
+        }
+    }
+
+    ical_export(db_state, &filter, Utc::now())
+}
+
+/// `import csv FILE [--dry-run] [--map HEADER=COLUMN]...`
+fn import_csv_command(db: &ProjectsDatabase, args: &[String]) -> Result<String> {
+    let mut file_path = None;
+    let mut dry_run = false;
+    let mut mapping = HashMap::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" | "-n" => dry_run = true,
+            "--map" | "-m" => {
+                let value = args.next().ok_or_else(|| anyhow!("--map requires a header=column pair"))?;
+                let (header, column) = value
+                    .rsplit_once('=')
+                    .ok_or_else(|| anyhow!("--map requires a header=column pair"))?;
+                mapping.insert(header.trim().to_owned(), CsvColumn::parse(column)?);
+            }
+            path if file_path.is_none() => file_path = Some(path.to_owned()),
+            arg => return Err(anyhow!("unexpected argument '{}'", arg))
+        }
+    }
+
+    let file_path = file_path.ok_or_else(|| anyhow!("import csv requires a file"))?;
+    let contents = fs::read_to_string(&file_path).with_context(|| anyhow!("couldn't read '{}'", file_path))?;
+    Ok(import_csv(db, &contents, &mapping, dry_run)?.to_string())
+}
+
+/// `import jira FILE [--dry-run] [--mapping FILE]`
+fn import_jira_command(db: &ProjectsDatabase, args: &[String]) -> Result<String> {
+    let mut file_path = None;
+    let mut dry_run = false;
+    let mut mapping = JiraMapping::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" | "-n" => dry_run = true,
+            "--mapping" | "-m" => {
+                let mapping_path = args.next().ok_or_else(|| anyhow!("--mapping requires a file"))?;
+                mapping = JiraMapping::load(mapping_path)?;
+            }
+            path if file_path.is_none() => file_path = Some(path.to_owned()),
+            arg => return Err(anyhow!("unexpected argument '{}'", arg))
+        }
+    }
+
+    let file_path = file_path.ok_or_else(|| anyhow!("import jira requires a file"))?;
+    let contents = fs::read_to_string(&file_path).with_context(|| anyhow!("couldn't read '{}'", file_path))?;
+    Ok(import_jira(db, &contents, &mapping, dry_run)?.to_string())
+}
+
+fn parse_story_filter(db_state: &DBState, args: &[String]) -> Result<StoryFilter> {
+    let mut query = vec![];
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--type" | "-t" => {
+                let value = args.next().ok_or_else(|| anyhow!("--type requires a value"))?;
+                query.push(format!("type:{}", value));
+            }
+            "--status" | "-s" => {
+                let value = args.next().ok_or_else(|| anyhow!("--status requires a value"))?;
+                query.push(format!("status:{}", value));
+            }
+            "--field" | "-f" => {
+                let value = args.next().ok_or_else(|| anyhow!("--field requires a name=value pair"))?;
+                let (name, value) = value
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("--field requires a name=value pair"))?;
+                query.push(format!("{}:{}", name, value));
+            }
+            arg => query.push(arg.to_owned())
+        }
+    }
+
+    StoryFilter::parse(&query.join(" "), db_state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_utils::MockDb;
+    use crate::models::{Epic, FieldDefinition, FieldKind, FieldTarget, IssueType, Status, Story};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn list_stories_should_filter_by_type() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        db.create_story(Story::new("story".to_owned(), "".to_owned()), epic_id).unwrap();
+        db.create_story(Story::new("bug".to_owned(), "".to_owned()).with_issue_type(IssueType::new("Bug")), epic_id).unwrap();
+
+        let db_state = db.read_db().unwrap();
+
+        let output = list_stories(&db_state, None, &args(&["--type", "bug"])).unwrap();
+        assert_eq!(output, "3\t[B]\tOPEN\tbug\n".to_owned());
+
+        let output = list_stories(&db_state, None, &args(&[])).unwrap();
+        assert_eq!(output.lines().count(), 2);
+
+        assert_eq!(list_stories(&db_state, None, &args(&["--type"])).is_err(), true);
+
+        let output = list_stories(&db_state, Some(&Theme::dark()), &args(&["--type", "bug"])).unwrap();
+        assert_eq!(output.starts_with("3\t[B]\t\u{1b}["), true);
+        assert_eq!(output.contains("OPEN"), true);
+    }
+
+    #[test]
+    fn list_stories_should_include_and_filter_custom_fields() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let story_id = db.create_story(Story::new("story".to_owned(), "".to_owned()), epic_id).unwrap();
+        db.create_story(Story::new("other".to_owned(), "".to_owned()), epic_id).unwrap();
+        db.create_field_definition(FieldDefinition::new("points".to_owned(), FieldKind::Number, FieldTarget::Stories)).unwrap();
+        db.set_story_field(story_id, "points", "3").unwrap();
+
+        let db_state = db.read_db().unwrap();
+
+        let output = list_stories(&db_state, None, &args(&["--field", "points=3"])).unwrap();
+        assert_eq!(output, "2\t[S]\tOPEN\tstory\tpoints=3\n".to_owned());
+    }
+
+    #[test]
+    fn export_csv_command_should_accept_a_column_list() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        let epic_id = db.create_epic(Epic::new("Auth".to_owned(), "".to_owned())).unwrap();
+        db.create_story(Story::new("Login".to_owned(), "".to_owned()), epic_id).unwrap();
+        let db_state = db.read_db().unwrap();
+
+        let output = export_csv_command(&db_state, &args(&["--columns", "story_id,story_name"])).unwrap();
+        assert_eq!(output, "story_id,story_name\n2,Login\n");
+        assert_eq!(export_csv_command(&db_state, &args(&[])).unwrap().lines().count(), 2);
+        assert_eq!(export_csv_command(&db_state, &args(&["--columns"])).is_err(), true);
+    }
+
+    #[test]
+    fn export_ical_command_should_read_filters() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let story_id = db.create_story(Story::new("Login".to_owned(), "".to_owned()), epic_id).unwrap();
+        db.update_story_due_date(story_id, chrono::NaiveDate::from_ymd_opt(2024, 5, 1)).unwrap();
+        let db_state = db.read_db().unwrap();
+
+        let output = export_ical_command(&db_state, &args(&["--epic", "1"])).unwrap();
+        assert_eq!(output.contains("UID:story-2@cli-project-jira"), true);
+        assert_eq!(export_ical_command(&db_state, &args(&["--epic", "x"])).is_err(), true);
+        assert_eq!(export_ical_command(&db_state, &args(&["--assignee", "ann"])).is_err(), true);
+    }
+
+    #[test]
+    fn import_csv_command_should_read_mapping_and_dry_run_flags() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        let tmpfile = tempfile::NamedTempFile::new().unwrap();
+        fs::write(tmpfile.path(), "Epic,Summary\nAuth,Login\n").unwrap();
+        let path = tmpfile.path().to_str().unwrap();
+
+        let output = import_csv_command(&db, &args(&[path, "--map", "Epic=epic_name", "-m", "Summary=story_name", "--dry-run"])).unwrap();
+        assert_eq!(output, "dry run, nothing was saved\nepics: 1 created, 0 updated\nstories: 1 created, 0 updated\n");
+        assert_eq!(db.read_db().unwrap().epics.len(), 0);
+
+        let output = import_csv_command(&db, &args(&[path, "--map", "Epic=epic_name"])).unwrap();
+        assert_eq!(output, "epics: 1 created, 0 updated\nstories: 0 created, 0 updated\nignored columns: Summary\n");
+        assert_eq!(import_csv_command(&db, &args(&[path, "--map", "Epic"])).is_err(), true);
+        assert_eq!(import_csv_command(&db, &args(&[])).is_err(), true);
+    }
+
+    #[test]
+    fn import_jira_command_should_read_the_mapping_file() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        let export = tempfile::NamedTempFile::new().unwrap();
+        fs::write(export.path(), r#"[{ "key": "P-1", "fields": { "summary": "Auth", "issuetype": { "name": "Epic" }, "status": { "name": "QA" } } }]"#).unwrap();
+        let mapping = tempfile::NamedTempFile::new().unwrap();
+        fs::write(mapping.path(), r#"{ "statuses": { "QA": "Resolved" } }"#).unwrap();
+
+        let output = import_jira_command(&db, &args(&[
+            export.path().to_str().unwrap(),
+            "--mapping",
+            mapping.path().to_str().unwrap()
+        ])).unwrap();
+
+        assert_eq!(output, "imported 1 epics and 0 stories\n");
+        assert_eq!(db.read_db().unwrap().epics[&1].status, Status::Resolved);
+        assert_eq!(import_jira_command(&db, &args(&["--mapping"])).is_err(), true);
+    }
+
+    #[test]
+    fn merge_command_should_ask_about_conflicts_and_write_ours() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_owned();
+        let db = ProjectsDatabase::new(path("base.json"));
+        db.database.write_db(&DBState::default()).unwrap();
+        let epic_id = db.create_epic(Epic::new("Auth".to_owned(), "".to_owned())).unwrap();
+        fs::copy(path("base.json"), path("ours.json")).unwrap();
+        fs::copy(path("base.json"), path("theirs.json")).unwrap();
+        ProjectsDatabase::new(path("ours.json")).update_epic_details(epic_id, "Sign in".to_owned(), "".to_owned()).unwrap();
+        let theirs = ProjectsDatabase::new(path("theirs.json"));
+        theirs.update_epic_details(epic_id, "Log in".to_owned(), "".to_owned()).unwrap();
+        theirs.create_epic(Epic::new("Billing".to_owned(), "".to_owned())).unwrap();
+
+        let mut prompts = vec![];
+        let report = merge_command(
+            &args(&[&path("base.json"), &path("ours.json"), &path("theirs.json")]),
+            &mut "x\nt\n".as_bytes(),
+            &mut prompts
+        ).unwrap();
+
+        assert_eq!(String::from_utf8(prompts).unwrap(), [
+            "conflict 1 of 1: epics.1.name",
+            "  base:   \"Auth\"",
+            "  ours:   \"Sign in\"",
+            "  theirs: \"Log in\"",
+            "keep [o] ours | [t] theirs: keep [o] ours | [t] theirs: ",
+        ].join("\n"));
+        assert_eq!(report, format!("merged into '{}', 1 conflicts resolved\n", path("ours.json")));
+        let merged = ProjectsDatabase::new(path("ours.json")).read_db().unwrap();
+        assert_eq!(merged.epics[&1].name, "Log in");
+        assert_eq!(merged.epics[&2].name, "Billing");
+    }
+
+    #[test]
+    fn merge_command_should_stop_without_answers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db.json").to_str().unwrap().to_owned();
+        let db = ProjectsDatabase::new(path.clone());
+        db.database.write_db(&DBState::default()).unwrap();
+        db.create_epic(Epic::new("Auth".to_owned(), "".to_owned())).unwrap();
+        let ours = dir.path().join("ours.json").to_str().unwrap().to_owned();
+        let theirs = dir.path().join("theirs.json").to_str().unwrap().to_owned();
+        fs::copy(&path, &ours).unwrap();
+        fs::copy(&path, &theirs).unwrap();
+        ProjectsDatabase::new(ours.clone()).update_epic_status(1, Status::Closed).unwrap();
+        ProjectsDatabase::new(theirs.clone()).update_epic_status(1, Status::Resolved).unwrap();
+
+        let result = merge_command(&args(&[&path, &ours, &theirs, "-o", "merged.json"]), &mut "".as_bytes(), &mut vec![]);
+
+        assert_eq!(result.unwrap_err().to_string(), "merge aborted, nothing was saved");
+        assert_eq!(merge_command(&args(&[&path, &ours]), &mut "".as_bytes(), &mut vec![]).is_err(), true);
+    }
+}
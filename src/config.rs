@@ -0,0 +1,60 @@
+use std::fs;
+use std::io::ErrorKind;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use crate::ui::theme::ThemeName;
+
+/// User settings read from a JSON file next to the database. Every setting is
+/// optional, so an empty or missing file gives the defaults.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+    pub theme: ThemeName,
+    /// Rows per page on list pages; unset fits the page to the terminal height.
+    pub page_size: Option<usize>,
+    /// Commits every change in a git repository around the database, see `GitDatabase`.
+    pub git: bool
+}
+
+impl Config {
+    pub fn load(file_path: &str) -> Result<Config> {
+        let contents = match fs::read_to_string(file_path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Config::default()),
+            Err(error) => return Err(error).with_context(|| anyhow!("couldn't read config file '{}'", file_path))
+        };
+
+        serde_json::from_str(&contents).with_context(|| anyhow!("invalid config file '{}'", file_path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use super::*;
+
+    #[test]
+    fn load_should_return_defaults_for_missing_file() {
+        assert_eq!(Config::load("INVALID_PATH").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn load_should_parse_json_file() {
+        let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+        write!(tmpfile, r#"{{ "theme": "high-contrast", "page_size": 25, "git": true }}"#).unwrap();
+
+        let config = Config::load(tmpfile.path().to_str().unwrap()).unwrap();
+
+        assert_eq!(config.theme, ThemeName::HighContrast);
+        assert_eq!(config.page_size, Some(25));
+        assert_eq!(config.git, true);
+    }
+
+    #[test]
+    fn load_should_fail_with_invalid_json() {
+        let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+        write!(tmpfile, r#"{{ "theme": "purple" }}"#).unwrap();
+
+        assert_eq!(Config::load(tmpfile.path().to_str().unwrap()).is_err(), true);
+    }
+}
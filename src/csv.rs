@@ -0,0 +1,723 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use itertools::Itertools;
+use crate::db::ProjectsDatabase;
+use crate::models::{DBState, Epic, FieldValue, IssueType, ItemId, Priority, SprintState, Status, Story};
+
+/// A column of the CSV export and import. Rows hold one story each, repeating the
+/// columns of its epic; epics without stories get a row of their own.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum CsvColumn {
+    EpicId,
+    EpicName,
+    EpicDescription,
+    EpicStatus,
+    EpicField(String),
+    StoryId,
+    StoryName,
+    StoryDescription,
+    StoryStatus,
+    StoryType,
+    StoryPoints,
+    StoryPriority,
+    StoryDueDate,
+    StorySprint,
+    StoryField(String),
+}
+
+impl CsvColumn {
+    /// Every column, including the custom fields defined in `db_state`, in export order.
+    pub fn all(db_state: &DBState) -> Vec<CsvColumn> {
+        let mut columns = vec![CsvColumn::EpicId, CsvColumn::EpicName, CsvColumn::EpicDescription, CsvColumn::EpicStatus];
+        columns.extend(db_state.epic_fields().into_iter().map(|definition| CsvColumn::EpicField(definition.name)));
+        columns.extend([
+            CsvColumn::StoryId,
+            CsvColumn::StoryName,
+            CsvColumn::StoryDescription,
+            CsvColumn::StoryStatus,
+            CsvColumn::StoryType,
+            CsvColumn::StoryPoints,
+            CsvColumn::StoryPriority,
+            CsvColumn::StoryDueDate,
+            CsvColumn::StorySprint,
+        ]);
+        columns.extend(db_state.story_fields().into_iter().map(|definition| CsvColumn::StoryField(definition.name)));
+        columns
+    }
+
+    /// Header of the column, e.g. `story_status` or `epic_field:team`.
+    pub fn name(&self) -> String {
+        match self {
+            CsvColumn::EpicId => "epic_id".to_owned(),
+            CsvColumn::EpicName => "epic_name".to_owned(),
+            CsvColumn::EpicDescription => "epic_description".to_owned(),
+            CsvColumn::EpicStatus => "epic_status".to_owned(),
+            CsvColumn::EpicField(name) => format!("epic_field:{}", name),
+            CsvColumn::StoryId => "story_id".to_owned(),
+            CsvColumn::StoryName => "story_name".to_owned(),
+            CsvColumn::StoryDescription => "story_description".to_owned(),
+            CsvColumn::StoryStatus => "story_status".to_owned(),
+            CsvColumn::StoryType => "story_type".to_owned(),
+            CsvColumn::StoryPoints => "story_points".to_owned(),
+            CsvColumn::StoryPriority => "story_priority".to_owned(),
+            CsvColumn::StoryDueDate => "story_due_date".to_owned(),
+            CsvColumn::StorySprint => "story_sprint".to_owned(),
+            CsvColumn::StoryField(name) => format!("story_field:{}", name),
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<CsvColumn> {
+        let name = name.trim();
+        if let Some(field) = name.strip_prefix("epic_field:") {
+            return Ok(CsvColumn::EpicField(field.to_owned()));
+        }
+        if let Some(field) = name.strip_prefix("story_field:") {
+            return Ok(CsvColumn::StoryField(field.to_owned()));
+        }
+        match name {
+            "epic_id" => Ok(CsvColumn::EpicId),
+            "epic_name" => Ok(CsvColumn::EpicName),
+            "epic_description" => Ok(CsvColumn::EpicDescription),
+            "epic_status" => Ok(CsvColumn::EpicStatus),
+            "story_id" => Ok(CsvColumn::StoryId),
+            "story_name" => Ok(CsvColumn::StoryName),
+            "story_description" => Ok(CsvColumn::StoryDescription),
+            "story_status" => Ok(CsvColumn::StoryStatus),
+            "story_type" => Ok(CsvColumn::StoryType),
+            "story_points" => Ok(CsvColumn::StoryPoints),
+            "story_priority" => Ok(CsvColumn::StoryPriority),
+            "story_due_date" => Ok(CsvColumn::StoryDueDate),
+            "story_sprint" => Ok(CsvColumn::StorySprint),
+            _ => Err(anyhow!("unknown column '{}'", name))
+        }
+    }
+
+    /// Columns given as a comma separated list, e.g. `epic_name,story_name,story_status`.
+    pub fn parse_list(list: &str) -> Result<Vec<CsvColumn>> {
+        list.split(',').map(CsvColumn::parse).collect()
+    }
+
+    fn is_epic_column(&self) -> bool {
+        matches!(
+            self,
+            CsvColumn::EpicId | CsvColumn::EpicName | CsvColumn::EpicDescription | CsvColumn::EpicStatus | CsvColumn::EpicField(_)
+        )
+    }
+
+    fn epic_value(&self, db_state: &DBState, epic_id: ItemId, epic: &Epic) -> String {
+        match self {
+            CsvColumn::EpicId => db_state.item_key(epic_id),
+            CsvColumn::EpicName => epic.name.clone(),
+            CsvColumn::EpicDescription => epic.description.clone(),
+            CsvColumn::EpicStatus => epic.status.to_string(),
+            CsvColumn::EpicField(name) => epic.fields.get(name).map(|value| value.to_string()).unwrap_or_default(),
+            _ => String::new()
+        }
+    }
+
+    fn story_value(&self, db_state: &DBState, story_id: ItemId, story: &Story) -> String {
+        match self {
+            CsvColumn::StoryId => db_state.item_key(story_id),
+            CsvColumn::StoryName => story.name.clone(),
+            CsvColumn::StoryDescription => story.description.clone(),
+            CsvColumn::StoryStatus => story.status.to_string(),
+            CsvColumn::StoryType => story.issue_type.to_string(),
+            CsvColumn::StoryPoints => story.points.map(|points| points.to_string()).unwrap_or_default(),
+            CsvColumn::StoryPriority => story.priority.to_string(),
+            CsvColumn::StoryDueDate => story.due_date.map(|date| date.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+            CsvColumn::StorySprint => story.sprint_id.map(|sprint_id| sprint_id.to_string()).unwrap_or_default(),
+            CsvColumn::StoryField(name) => story.fields.get(name).map(|value| value.to_string()).unwrap_or_default(),
+            _ => String::new()
+        }
+    }
+}
+
+/// Writes epics and their stories, ordered by id, with a header row of `columns`.
+/// Epics and stories are written with their keys, see `DBState::item_key`.
+pub fn export_csv(db_state: &DBState, columns: &[CsvColumn]) -> String {
+    let mut rows = vec![columns.iter().map(|column| column.name()).collect::<Vec<_>>()];
+
+    for (epic_id, epic) in db_state.epics.iter().sorted_by_key(|(id, _)| **id) {
+        let epic_cells = |story: Option<(ItemId, &Story)>| {
+            columns
+                .iter()
+                .map(|column| match story {
+                    _ if column.is_epic_column() => column.epic_value(db_state, *epic_id, epic),
+                    Some((story_id, story)) => column.story_value(db_state, story_id, story),
+                    None => String::new()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let stories = epic.stories
+            .iter()
+            .sorted()
+            .filter_map(|story_id| db_state.stories.get(story_id).map(|story| (*story_id, story)))
+            .collect::<Vec<_>>();
+        if stories.is_empty() {
+            rows.push(epic_cells(None));
+        }
+        rows.extend(stories.into_iter().map(|story| epic_cells(Some(story))));
+    }
+
+    rows.into_iter()
+        .map(|row| row.iter().map(|cell| quote_cell(cell)).join(","))
+        .map(|line| line + "\n")
+        .collect()
+}
+
+fn quote_cell(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_owned()
+    }
+}
+
+/// A row of a CSV file and the line it starts on.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CsvRecord {
+    pub line: usize,
+    pub cells: Vec<String>
+}
+
+/// Splits CSV text into records. Quoted cells may hold commas, line breaks and
+/// doubled quotes; blank lines are skipped.
+pub fn parse_csv(contents: &str) -> Result<Vec<CsvRecord>> {
+    let mut records = vec![];
+    let mut cells = vec![];
+    let mut cell = String::new();
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut quoted_since = None;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted_since.is_some() {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    cell.push('"');
+                }
+                '"' => quoted_since = None,
+                c => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    cell.push(c);
+                }
+            }
+            continue;
+        }
+
+        match c {
+            '"' if cell.is_empty() => quoted_since = Some(line),
+            ',' => cells.push(std::mem::take(&mut cell)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                cells.push(std::mem::take(&mut cell));
+                records.push(CsvRecord { line: record_line, cells: std::mem::take(&mut cells) });
+                line += 1;
+                record_line = line;
+            }
+            c => cell.push(c)
+        }
+    }
+
+    if let Some(quoted_since) = quoted_since {
+        return Err(anyhow!("line {}: quoted value isn't closed", quoted_since));
+    }
+    if !cell.is_empty() || !cells.is_empty() {
+        cells.push(cell);
+        records.push(CsvRecord { line: record_line, cells });
+    }
+    records.retain(|record| record.cells.iter().any(|cell| !cell.trim().is_empty()));
+    Ok(records)
+}
+
+/// Why a row of an import was skipped.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RowError {
+    pub line: usize,
+    pub message: String
+}
+
+/// What an import did, or would do on a dry run.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub created_epics: BTreeSet<ItemId>,
+    pub updated_epics: BTreeSet<ItemId>,
+    pub created_stories: BTreeSet<ItemId>,
+    pub updated_stories: BTreeSet<ItemId>,
+    /// Headers that neither match a column name nor are mapped to one.
+    pub ignored_columns: Vec<String>,
+    pub errors: Vec<RowError>
+}
+
+impl Display for ImportReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.dry_run {
+            writeln!(f, "dry run, nothing was saved")?;
+        }
+        writeln!(f, "epics: {} created, {} updated", self.created_epics.len(), self.updated_epics.len())?;
+        writeln!(f, "stories: {} created, {} updated", self.created_stories.len(), self.updated_stories.len())?;
+        if !self.ignored_columns.is_empty() {
+            writeln!(f, "ignored columns: {}", self.ignored_columns.join(", "))?;
+        }
+        for error in &self.errors {
+            writeln!(f, "line {}: {}", error.line, error.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Creates and updates epics and stories from CSV text whose first row is a header.
+///
+/// Headers are read as column names unless `mapping` maps them to a column, e.g.
+/// `"Summary"` to `CsvColumn::StoryName`. A row updates the epic in `epic_id`, or
+/// else the first epic called `epic_name`, creating it when there is none; the same
+/// goes for its story with `story_id` and `story_name`. Empty cells leave values as
+/// they are. Rows with invalid values are skipped and listed in the report. With
+/// `dry_run` nothing is written, otherwise all changes are written at once.
+pub fn import_csv(db: &ProjectsDatabase, contents: &str, mapping: &HashMap<String, CsvColumn>, dry_run: bool) -> Result<ImportReport> {
+    db.batch(dry_run, |db| import_rows(db, contents, mapping)).map(|report| ImportReport { dry_run, ..report })
+}
+
+fn import_rows(db: &ProjectsDatabase, contents: &str, mapping: &HashMap<String, CsvColumn>) -> Result<ImportReport> {
+    let mut records = parse_csv(contents)?.into_iter();
+    let header = records.next().ok_or_else(|| anyhow!("the CSV file is empty"))?;
+    let mut report = ImportReport::default();
+    let columns = header_columns(db, &header.cells, mapping, &mut report)?;
+
+    for record in records {
+        let values = columns
+            .iter()
+            .zip(&record.cells)
+            .filter_map(|(column, cell)| Some((column.clone()?, cell.trim())))
+            .filter(|(_, cell)| !cell.is_empty())
+            .collect::<HashMap<_, _>>();
+        if let Err(error) = import_row(db, &values, &mut report) {
+            report.errors.push(RowError { line: record.line, message: error.to_string() });
+        }
+    }
+    Ok(report)
+}
+
+fn header_columns(
+    db: &ProjectsDatabase,
+    headers: &[String],
+    mapping: &HashMap<String, CsvColumn>,
+    report: &mut ImportReport
+) -> Result<Vec<Option<CsvColumn>>> {
+    if let Some(header) = mapping.keys().sorted().find(|header| !headers.iter().any(|name| name.trim() == header.as_str())) {
+        return Err(anyhow!("no column '{}' in the CSV file", header));
+    }
+
+    let db_state = db.read_db()?;
+    let mut columns = vec![];
+    for header in headers.iter().map(|header| header.trim()) {
+        let column = match mapping.get(header) {
+            Some(column) => Some(column.clone()),
+            None => CsvColumn::parse(header).ok()
+        };
+        match &column {
+            None => report.ignored_columns.push(header.to_owned()),
+            Some(column) if columns.contains(&Some(column.clone())) => {
+                return Err(anyhow!("more than one column is read as '{}'", column.name()));
+            }
+            Some(CsvColumn::EpicField(name)) if !db_state.epic_fields().iter().any(|definition| &definition.name == name) => {
+                return Err(anyhow!("epic field '{}' not found", name));
+            }
+            Some(CsvColumn::StoryField(name)) if !db_state.story_fields().iter().any(|definition| &definition.name == name) => {
+                return Err(anyhow!("story field '{}' not found", name));
+            }
+            _ => {}
+        }
+        columns.push(column);
+    }
+    Ok(columns)
+}
+
+/// Values of a row, checked before anything is written so a bad row changes nothing.
+#[derive(Default)]
+struct ItemValues {
+    name: Option<String>,
+    description: Option<String>,
+    status: Option<Status>,
+    issue_type: Option<IssueType>,
+    points: Option<u32>,
+    priority: Option<Priority>,
+    due_date: Option<NaiveDate>,
+    sprint_id: Option<u32>,
+    fields: Vec<(String, FieldValue)>
+}
+
+fn import_row(db: &ProjectsDatabase, values: &HashMap<CsvColumn, &str>, report: &mut ImportReport) -> Result<()> {
+    let db_state = db.read_db()?;
+    let mut epic = ItemValues::default();
+    let mut story = ItemValues::default();
+    let mut epic_id = None;
+    let mut story_id = None;
+
+    for (column, value) in values.iter().sorted_by_key(|(column, _)| column.name()) {
+        let value = value.to_string();
+        match column {
+            CsvColumn::EpicId => epic_id = Some(db_state.find_item(&value)?),
+            CsvColumn::EpicName => epic.name = Some(value),
+            CsvColumn::EpicDescription => epic.description = Some(value),
+            CsvColumn::EpicStatus => epic.status = Some(value.parse()?),
+            CsvColumn::EpicField(name) => {
+                let definition = db_state.field_definition(name).ok_or_else(|| anyhow!("epic field '{}' not found", name))?;
+                epic.fields.push((name.clone(), definition.parse_value(&value)?));
+            }
+            CsvColumn::StoryId => story_id = Some(db_state.find_item(&value)?),
+            CsvColumn::StoryName => story.name = Some(value),
+            CsvColumn::StoryDescription => story.description = Some(value),
+            CsvColumn::StoryStatus => story.status = Some(value.parse()?),
+            CsvColumn::StoryType => story.issue_type = Some(db_state.issue_type(&value)?),
+            CsvColumn::StoryPoints => story.points = Some(value.parse().map_err(|_| anyhow!("'{}' is not a number of points", value))?),
+            CsvColumn::StoryPriority => story.priority = Some(value.parse()?),
+            CsvColumn::StoryDueDate => {
+                let due_date = NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+                    .map_err(|_| anyhow!("'{}' is not a date in YYYY-MM-DD format", value))?;
+                story.due_date = Some(due_date);
+            }
+            CsvColumn::StorySprint => {
+                let sprint_id = parse_id(&value, "story_sprint")?;
+                match db_state.sprints.get(&sprint_id) {
+                    None => return Err(anyhow!("no sprint with id {}", sprint_id)),
+                    Some(sprint) if sprint.state == SprintState::Closed => return Err(anyhow!("sprint {} is closed", sprint_id)),
+                    Some(_) => story.sprint_id = Some(sprint_id)
+                }
+            }
+            CsvColumn::StoryField(name) => {
+                let definition = db_state.field_definition(name).ok_or_else(|| anyhow!("story field '{}' not found", name))?;
+                story.fields.push((name.clone(), definition.parse_value(&value)?));
+            }
+        }
+    }
+
+    // Without an epic_id the epic_name says which epic the row belongs to.
+    let mut epic_id = match (epic_id, &epic.name) {
+        (Some(epic_id), _) if !db_state.epics.contains_key(&epic_id) => return Err(anyhow!("{} is not an epic", db_state.item_key(epic_id))),
+        (Some(epic_id), _) => Some(epic_id),
+        (None, Some(name)) => db_state.epics
+            .iter()
+            .filter(|(_, epic)| &epic.name == name)
+            .map(|(id, _)| *id)
+            .min(),
+        (None, None) => None
+    };
+
+    if let Some(story_id) = story_id {
+        let story_key = db_state.item_key(story_id);
+        let story_epic_id = db_state.epic_id_of_story(story_id).ok_or_else(|| anyhow!("{} is not a story", story_key))?;
+        if (epic_id.is_some() || epic.name.is_some()) && epic_id != Some(story_epic_id) {
+            return Err(anyhow!("story {} belongs to epic {}", story_key, db_state.item_key(story_epic_id)));
+        }
+        if story.issue_type.as_ref().is_some_and(|issue_type| issue_type != &db_state.stories[&story_id].issue_type) {
+            return Err(anyhow!("the type of story {} can't be changed", story_key));
+        }
+        epic_id = Some(story_epic_id);
+    } else if story.name.is_none() && values.keys().any(|column| !column.is_epic_column()) {
+        return Err(anyhow!("story values need a story_id or story_name"));
+    }
+
+    let epic_id = match epic_id {
+        Some(epic_id) => epic_id,
+        None => {
+            let name = epic.name.clone().ok_or_else(|| anyhow!("the row needs an epic_id, epic_name or story_id"))?;
+            let epic_id = db.create_epic(Epic::new(name, epic.description.take().unwrap_or_default()))?;
+            report.created_epics.insert(epic_id);
+            epic_id
+        }
+    };
+    if update_epic(db, epic_id, epic)? && !report.created_epics.contains(&epic_id) {
+        report.updated_epics.insert(epic_id);
+    }
+
+    // Without a story_id the story_name says which story of the epic the row is.
+    let existing_story = story.name.as_ref().and_then(|name| {
+        db_state.epics.get(&epic_id)?.stories
+            .iter()
+            .filter(|id| db_state.stories.get(id).is_some_and(|story| &story.name == name))
+            .min()
+            .copied()
+    });
+    if let (None, Some(existing_id)) = (story_id, existing_story) {
+        if story.issue_type.as_ref().is_some_and(|issue_type| issue_type != &db_state.stories[&existing_id].issue_type) {
+            return Err(anyhow!("the type of story {} can't be changed", db_state.item_key(existing_id)));
+        }
+    }
+
+    let story_id = match (story_id.or(existing_story), story.name.clone()) {
+        (Some(story_id), _) => story_id,
+        (None, Some(name)) => {
+            let new_story = Story::new(name, story.description.take().unwrap_or_default())
+                .with_issue_type(story.issue_type.take().unwrap_or_default());
+            let story_id = db.create_story(new_story, epic_id)?;
+            report.created_stories.insert(story_id);
+            story_id
+        }
+        (None, None) => return Ok(())
+    };
+    if update_story(db, story_id, story)? && !report.created_stories.contains(&story_id) {
+        report.updated_stories.insert(story_id);
+    }
+    Ok(())
+}
+
+fn parse_id(value: &str, column: &str) -> Result<u32> {
+    value.parse().map_err(|_| anyhow!("'{}' is not a valid {}", value, column))
+}
+
+/// Writes the values that differ from the stored epic; `true` when there were any.
+fn update_epic(db: &ProjectsDatabase, epic_id: ItemId, values: ItemValues) -> Result<bool> {
+    let mut db_state = db.read_db()?;
+    let epic = db_state.epics.remove(&epic_id).ok_or_else(|| anyhow!("no epic {}", db_state.item_key(epic_id)))?;
+    let mut changed = false;
+
+    let name = values.name.unwrap_or_else(|| epic.name.clone());
+    let description = values.description.unwrap_or_else(|| epic.description.clone());
+    if name != epic.name || description != epic.description {
+        db.update_epic_details(epic_id, name, description)?;
+        changed = true;
+    }
+    if let Some(status) = values.status.filter(|status| *status != epic.status) {
+        db.update_epic_status(epic_id, status)?;
+        changed = true;
+    }
+    for (name, value) in values.fields {
+        if epic.fields.get(&name) != Some(&value) {
+            db.set_epic_field(epic_id, &name, &value.to_string())?;
+            changed = true;
+        }
+    }
+    Ok(changed)
+}
+
+/// Writes the values that differ from the stored story; `true` when there were any.
+fn update_story(db: &ProjectsDatabase, story_id: ItemId, values: ItemValues) -> Result<bool> {
+    let mut db_state = db.read_db()?;
+    let story = db_state.stories.remove(&story_id).ok_or_else(|| anyhow!("no story {}", db_state.item_key(story_id)))?;
+    let mut changed = false;
+
+    let name = values.name.unwrap_or_else(|| story.name.clone());
+    let description = values.description.unwrap_or_else(|| story.description.clone());
+    if name != story.name || description != story.description {
+        db.update_story_details(story_id, name, description)?;
+        changed = true;
+    }
+    if let Some(status) = values.status.filter(|status| *status != story.status) {
+        db.update_story_status(story_id, status)?;
+        changed = true;
+    }
+    if values.points.is_some() && values.points != story.points {
+        db.update_story_points(story_id, values.points)?;
+        changed = true;
+    }
+    if let Some(priority) = values.priority.filter(|priority| *priority != story.priority) {
+        db.update_story_priority(story_id, priority)?;
+        changed = true;
+    }
+    if values.due_date.is_some() && values.due_date != story.due_date {
+        db.update_story_due_date(story_id, values.due_date)?;
+        changed = true;
+    }
+    if values.sprint_id.is_some() && values.sprint_id != story.sprint_id {
+        db.assign_story_to_sprint(story_id, values.sprint_id)?;
+        changed = true;
+    }
+    for (name, value) in values.fields {
+        if story.fields.get(&name) != Some(&value) {
+            db.set_story_field(story_id, &name, &value.to_string())?;
+            changed = true;
+        }
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_utils::MockDb;
+    use crate::models::{item_id, FieldDefinition, FieldKind, FieldTarget, Sprint};
+
+    fn database() -> ProjectsDatabase {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        db.create_field_definition(FieldDefinition::new("team".to_owned(), FieldKind::Text, FieldTarget::Epics)).unwrap();
+        let epic_id = db.create_epic(Epic::new("Auth".to_owned(), "Login, logout".to_owned())).unwrap();
+        db.set_epic_field(epic_id, "team", "core").unwrap();
+        let story_id = db.create_story(Story::new("Login".to_owned(), "Say \"hi\"".to_owned()), epic_id).unwrap();
+        db.update_story_points(story_id, Some(3)).unwrap();
+        db.create_epic(Epic::new("Empty".to_owned(), "".to_owned())).unwrap();
+        db
+    }
+
+    #[test]
+    fn export_csv_should_write_one_row_per_story() {
+        let db_state = database().read_db().unwrap();
+
+        assert_eq!(
+            export_csv(&db_state, &CsvColumn::all(&db_state)),
+            "epic_id,epic_name,epic_description,epic_status,epic_field:team,story_id,story_name,story_description,story_status,story_type,story_points,story_priority,story_due_date,story_sprint\n\
+             1,Auth,\"Login, logout\",OPEN,core,2,Login,\"Say \"\"hi\"\"\",OPEN,STORY,3,MEDIUM,,\n\
+             3,Empty,,OPEN,,,,,,,,,,\n"
+        );
+
+        let columns = CsvColumn::parse_list("story_name,epic_name").unwrap();
+        assert_eq!(export_csv(&db_state, &columns), "story_name,epic_name\nLogin,Auth\n,Empty\n");
+        assert_eq!(CsvColumn::parse_list("story_name,owner").is_err(), true);
+    }
+
+    #[test]
+    fn export_and_import_should_use_item_keys() {
+        let db = database();
+        db.set_key_prefix(Some("PROJ".to_owned())).unwrap();
+        let mut db_state = db.read_db().unwrap();
+        let story_id = item_id(7, 3);
+        db_state.stories.insert(story_id, Story::new("Logout".to_owned(), "".to_owned()));
+        db_state.epics.get_mut(&1).unwrap().stories.push(story_id);
+        db.database.write_db(&db_state).unwrap();
+
+        let columns = CsvColumn::parse_list("epic_id,story_id").unwrap();
+        assert_eq!(export_csv(&db_state, &columns), "epic_id,story_id
+PROJ-1,PROJ-2
+PROJ-1,PROJ-3.7
+PROJ-3,
+");
+
+        let report = import_csv(&db, "epic_id,story_id,story_status
+PROJ-1,PROJ-3.7,closed
+,PROJ-9,closed
+", &HashMap::new(), false).unwrap();
+        assert_eq!(report.updated_stories, BTreeSet::from([story_id]));
+        assert_eq!(report.errors, vec![RowError { line: 3, message: "no epic or story with key PROJ-9".to_owned() }]);
+        assert_eq!(db.read_db().unwrap().stories[&story_id].status, Status::Closed);
+    }
+
+    #[test]
+    fn parse_csv_should_handle_quotes_and_line_breaks() {
+        let records = parse_csv("a,\"b, \"\"c\"\"\"\r\n\n\"multi\nline\",d\ne,").unwrap();
+
+        assert_eq!(records, vec![
+            CsvRecord { line: 1, cells: vec!["a".to_owned(), "b, \"c\"".to_owned()] },
+            CsvRecord { line: 3, cells: vec!["multi\nline".to_owned(), "d".to_owned()] },
+            CsvRecord { line: 5, cells: vec!["e".to_owned(), "".to_owned()] },
+        ]);
+        assert_eq!(parse_csv("a,\"b\nc").unwrap_err().to_string(), "line 1: quoted value isn't closed");
+    }
+
+    #[test]
+    fn import_csv_should_round_trip_an_export() {
+        let db = database();
+        let db_state = db.read_db().unwrap();
+        let contents = export_csv(&db_state, &CsvColumn::all(&db_state));
+
+        let report = import_csv(&db, &contents, &HashMap::new(), false).unwrap();
+
+        assert_eq!(report, ImportReport::default());
+        assert_eq!(db.read_db().unwrap(), db_state);
+    }
+
+    #[test]
+    fn import_csv_should_create_and_update_items() {
+        let db = database();
+        db.create_sprint(Sprint::new("".to_owned(), "".to_owned(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 1, 14).unwrap())).unwrap();
+        let contents = "Epic,story_id,Summary,story_status,story_priority,story_type,story_sprint,Owner\n\
+                        Auth,2,,in progress,high,,1,ann\n\
+                        Billing,,Invoices,,,bug,,bob\n\
+                        Billing,,Refunds,,lowest,,,\n";
+        let mapping = HashMap::from([
+            ("Epic".to_owned(), CsvColumn::EpicName),
+            ("Summary".to_owned(), CsvColumn::StoryName),
+        ]);
+
+        let report = import_csv(&db, contents, &mapping, false).unwrap();
+
+        assert_eq!(report.created_epics, BTreeSet::from([4]));
+        assert_eq!(report.created_stories, BTreeSet::from([5, 6]));
+        assert_eq!(report.updated_epics, BTreeSet::new());
+        assert_eq!(report.updated_stories, BTreeSet::from([2]));
+        assert_eq!(report.ignored_columns, vec!["Owner".to_owned()]);
+        assert_eq!(report.errors, vec![]);
+
+        let db_state = db.read_db().unwrap();
+        let login = &db_state.stories[&2];
+        assert_eq!((login.status.clone(), login.priority, login.sprint_id), (Status::InProgress, Priority::High, Some(1)));
+        assert_eq!(db_state.epics[&4].stories, vec![5, 6]);
+        assert_eq!(db_state.stories[&5].issue_type, IssueType::new("Bug"));
+        assert_eq!(db_state.stories[&6].priority, Priority::Lowest);
+    }
+
+    #[test]
+    fn import_csv_should_match_stories_by_name_on_reimport() {
+        let db = database();
+        let contents = "epic_name,story_name,story_points
+                        Auth,Login,5
+                        Auth,Logout,1
+                        Billing,Login,2
+";
+
+        let first = import_csv(&db, contents, &HashMap::new(), false).unwrap();
+        let before = db.read_db().unwrap();
+        let second = import_csv(&db, contents, &HashMap::new(), false).unwrap();
+
+        assert_eq!((first.created_stories.len(), first.updated_stories, first.created_epics.len()), (2, BTreeSet::from([2]), 1));
+        assert_eq!(second, ImportReport::default());
+        assert_eq!(db.read_db().unwrap(), before);
+        assert_eq!(before.stories.len(), 3);
+        assert_eq!(before.stories[&2].points, Some(5));
+    }
+
+    #[test]
+    fn import_csv_should_report_bad_rows_and_keep_going() {
+        let db = database();
+        let contents = "epic_name,story_name,story_status,story_points,story_id\n\
+                        Auth,First,doing,,\n\
+                        Auth,Second,,many,\n\
+                        ,Orphan,,,\n\
+                        Billing,,,,2\n\
+                        Auth,Third,closed,5,\n";
+
+        let report = import_csv(&db, contents, &HashMap::new(), false).unwrap();
+
+        assert_eq!(report.errors, vec![
+            RowError { line: 2, message: "unknown status 'doing'".to_owned() },
+            RowError { line: 3, message: "'many' is not a number of points".to_owned() },
+            RowError { line: 4, message: "the row needs an epic_id, epic_name or story_id".to_owned() },
+            RowError { line: 5, message: "story 2 belongs to epic 1".to_owned() },
+        ]);
+        assert_eq!(report.created_stories, BTreeSet::from([4]));
+        assert_eq!(report.created_epics, BTreeSet::new());
+        assert_eq!(db.read_db().unwrap().stories.len(), 2);
+    }
+
+    #[test]
+    fn import_csv_should_only_preview_on_dry_run() {
+        let db = database();
+        let before = db.read_db().unwrap();
+
+        let report = import_csv(&db, "epic_name,story_name\nBilling,Invoices\n", &HashMap::new(), true).unwrap();
+
+        assert_eq!(report.dry_run, true);
+        assert_eq!(report.created_epics.len(), 1);
+        assert_eq!(report.created_stories.len(), 1);
+        assert_eq!(report.to_string(), "dry run, nothing was saved\nepics: 1 created, 0 updated\nstories: 1 created, 0 updated\n");
+        assert_eq!(db.read_db().unwrap(), before);
+    }
+
+    #[test]
+    fn import_csv_should_reject_bad_headers() {
+        let db = database();
+
+        let missing = HashMap::from([("Title".to_owned(), CsvColumn::StoryName)]);
+        assert_eq!(import_csv(&db, "epic_name\nAuth\n", &missing, false).unwrap_err().to_string(), "no column 'Title' in the CSV file");
+        assert_eq!(import_csv(&db, "epic_name,epic_name\n", &HashMap::new(), false).unwrap_err().to_string(), "more than one column is read as 'epic_name'");
+        assert_eq!(import_csv(&db, "story_field:team\n", &HashMap::new(), false).unwrap_err().to_string(), "story field 'team' not found");
+        assert_eq!(import_csv(&db, "", &HashMap::new(), false).is_err(), true);
+    }
+}
@@ -0,0 +1,188 @@
+use itertools::Itertools;
+use crate::models::{DBState, Status};
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border-bottom: 1px solid #ddd; padding: 4px 8px; text-align: left; }
+.filters { margin: 1em 0; }
+.filters input, .filters select { margin-right: 1em; padding: 4px; }
+section.epic { margin-top: 2em; }
+";
+
+const SCRIPT: &str = "
+const text = document.getElementById('filter');
+const status = document.getElementById('status');
+function applyFilters() {
+  const query = text.value.trim().toLowerCase();
+  const wanted = status.value;
+  document.querySelectorAll('tr.story').forEach(row => {
+    row.hidden = !row.dataset.search.includes(query) || (wanted !== '' && row.dataset.status !== wanted);
+  });
+  document.querySelectorAll('section.epic').forEach(section => {
+    const filtering = query !== '' || wanted !== '';
+    section.hidden = filtering && section.querySelector('tr.story:not([hidden])') === null;
+  });
+}
+text.addEventListener('input', applyFilters);
+status.addEventListener('change', applyFilters);
+";
+
+const BAR_WIDTH: usize = 200;
+
+/// Renders a single HTML page with an overview of the epics and their progress,
+/// a chart of how stories are spread over the statuses and a table of stories per
+/// epic that can be filtered by text and status. Styles, script and charts are
+/// inline, so the file can be published as it is.
+pub fn html_dashboard(db_state: &DBState) -> String {
+    let epics = db_state.epics.iter().sorted_by_key(|(id, _)| **id).collect::<Vec<_>>();
+    let mut html = String::new();
+
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>Project dashboard</title>\n");
+    html.push_str(&format!("<style>{}</style>\n</head>\n<body>\n<h1>Project dashboard</h1>\n", STYLE));
+
+    html.push_str("<h2>Status distribution</h2>\n");
+    let statuses = Status::all()
+        .into_iter()
+        .map(|status| {
+            let count = db_state.stories.values().filter(|story| story.status == status).count();
+            (status, count)
+        })
+        .collect::<Vec<_>>();
+    html.push_str(&status_chart(&statuses));
+
+    html.push_str("<h2>Epics</h2>\n<table>\n<tr><th>id</th><th>epic</th><th>status</th><th>progress</th><th>stories</th></tr>\n");
+    for (id, epic) in &epics {
+        let stories = epic.stories.iter().filter_map(|id| db_state.stories.get(id)).collect::<Vec<_>>();
+        let done = stories.iter().filter(|story| story.status.is_done()).count();
+        html.push_str(&format!(
+            "<tr><td>{}</td><td><a href=\"#epic-{}\">{}</a></td><td>{}</td><td>{}</td><td>{} of {} done</td></tr>\n",
+            escape(&db_state.item_key(**id)), id, escape(&epic.name), epic.status, progress_bar(done, stories.len()), done, stories.len()
+        ));
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<div class=\"filters\">\n<input id=\"filter\" type=\"search\" placeholder=\"Filter stories\">\n<select id=\"status\">\n<option value=\"\">All statuses</option>\n");
+    for status in Status::all() {
+        html.push_str(&format!("<option value=\"{}\">{}</option>\n", status_class(&status), status));
+    }
+    html.push_str("</select>\n</div>\n");
+
+    for (id, epic) in &epics {
+        html.push_str(&format!("<section class=\"epic\" id=\"epic-{}\">\n<h2>Epic {}: {}</h2>\n", id, escape(&db_state.item_key(**id)), escape(&epic.name)));
+        if !epic.description.trim().is_empty() {
+            html.push_str(&format!("<p>{}</p>\n", escape(epic.description.trim())));
+        }
+        html.push_str("<table>\n<tr><th>id</th><th>type</th><th>story</th><th>status</th><th>priority</th><th>points</th><th>due</th></tr>\n");
+        for story_id in epic.stories.iter().sorted() {
+            let Some(story) = db_state.stories.get(story_id) else {
+                continue;
+            };
+            let search = format!("{} {} {}", db_state.item_key(*story_id), story.name, story.issue_type).to_lowercase();
+            html.push_str(&format!(
+                "<tr class=\"story\" data-status=\"{}\" data-search=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                status_class(&story.status),
+                escape(&search),
+                escape(&db_state.item_key(*story_id)),
+                story.issue_type,
+                escape(&story.name),
+                story.status,
+                story.priority,
+                story.points.map(|points| points.to_string()).unwrap_or_default(),
+                story.due_date.map(|date| date.format("%Y-%m-%d").to_string()).unwrap_or_default()
+            ));
+        }
+        html.push_str("</table>\n</section>\n");
+    }
+
+    html.push_str(&format!("<script>{}</script>\n</body>\n</html>\n", SCRIPT));
+    html
+}
+
+/// Horizontal bars, one per status, scaled to the most common one.
+fn status_chart(statuses: &[(Status, usize)]) -> String {
+    let max = statuses.iter().map(|(_, count)| *count).max().unwrap_or(0).max(1);
+    let row_height = 24;
+    let mut svg = format!(
+        "<svg width=\"{}\" height=\"{}\" role=\"img\" aria-label=\"Stories per status\">\n",
+        BAR_WIDTH + 160,
+        statuses.len() * row_height
+    );
+    for (idx, (status, count)) in statuses.iter().enumerate() {
+        let y = idx * row_height;
+        svg.push_str(&format!(
+            "<text x=\"0\" y=\"{}\" font-size=\"12\">{}</text><rect x=\"100\" y=\"{}\" width=\"{}\" height=\"16\" fill=\"{}\"/><text x=\"{}\" y=\"{}\" font-size=\"12\">{}</text>\n",
+            y + 13,
+            status,
+            y + 2,
+            BAR_WIDTH * count / max,
+            status_color(status),
+            100 + BAR_WIDTH * count / max + 6,
+            y + 13,
+            count
+        ));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn progress_bar(done: usize, total: usize) -> String {
+    let width = (BAR_WIDTH * done).checked_div(total).unwrap_or(0);
+    format!(
+        "<svg width=\"{}\" height=\"12\" role=\"img\" aria-label=\"{} of {} done\"><rect width=\"{}\" height=\"12\" fill=\"#e0e0e0\"/><rect width=\"{}\" height=\"12\" fill=\"{}\"/></svg>",
+        BAR_WIDTH, done, total, BAR_WIDTH, width, status_color(&Status::Resolved)
+    )
+}
+
+fn status_class(status: &Status) -> String {
+    status.to_string().to_lowercase().replace(' ', "-")
+}
+
+fn status_color(status: &Status) -> &'static str {
+    match status {
+        Status::Open => "#9e9e9e",
+        Status::InProgress => "#1e88e5",
+        Status::Resolved => "#43a047",
+        Status::Closed => "#2e7d32"
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{test_utils::MockDb, ProjectsDatabase};
+    use crate::models::{Epic, Story};
+
+    #[test]
+    fn html_dashboard_should_render_epics_charts_and_filters() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        let epic_id = db.create_epic(Epic::new("Auth & <SSO>".to_owned(), "".to_owned())).unwrap();
+        let first = db.create_story(Story::new("Login \"fast\"".to_owned(), "".to_owned()), epic_id).unwrap();
+        db.create_story(Story::new("Logout".to_owned(), "".to_owned()), epic_id).unwrap();
+        db.update_story_status(first, Status::Closed).unwrap();
+
+        let html = html_dashboard(&db.read_db().unwrap());
+
+        assert_eq!(html.contains("<a href=\"#epic-1\">Auth &amp; &lt;SSO&gt;</a>"), true);
+        assert_eq!(html.contains("aria-label=\"1 of 2 done\"><rect width=\"200\" height=\"12\" fill=\"#e0e0e0\"/><rect width=\"100\""), true);
+        assert_eq!(html.contains("<text x=\"0\" y=\"13\" font-size=\"12\">OPEN</text><rect x=\"100\" y=\"2\" width=\"200\""), true);
+        assert_eq!(html.contains("<tr class=\"story\" data-status=\"closed\" data-search=\"2 login &quot;fast&quot; story\">"), true);
+        assert_eq!(html.contains("<option value=\"in-progress\">IN PROGRESS</option>"), true);
+        assert_eq!(html.contains("<script>"), true);
+        assert_eq!(html.contains("src=") || html.contains("<link"), false);
+        assert_eq!(html_dashboard(&db.read_db().unwrap()), html);
+    }
+
+    #[test]
+    fn progress_bar_should_handle_epics_without_stories() {
+        assert_eq!(progress_bar(0, 0).contains("<rect width=\"0\""), true);
+    }
+}
@@ -1,54 +1,108 @@
+use std::cell::{Cell, RefCell};
 use std::fs;
 use std::fs::File;
-use std::io::{Read, Write};
-
-use anyhow::{anyhow, Result};
-
-use crate::models::{DBState, Epic, Status, Story};
+use std::io::{ErrorKind, Read, Write};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+
+use chrono::{NaiveDate, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::config::Config;
+use crate::git::GitDatabase;
+use crate::models::{item_id, DBState, Epic, FieldDefinition, FieldKind, ItemId, Priority, Sprint, SprintState, Status, Story};
 
 pub struct ProjectsDatabase {
     pub database: Box<dyn Database>
 }
 
 impl ProjectsDatabase {
+    /// Opens the database file in the format its extension says, see `StorageFormat`.
     pub fn new(file_path: String) -> Self {
-        ProjectsDatabase {
-            database: Box::new(JSONFileDatabase {
-                file_path,
-            })
-        }
+        let database: Box<dyn Database> = match StorageFormat::from_path(&file_path) {
+            StorageFormat::Json => Box::new(JSONFileDatabase { file_path }),
+            StorageFormat::Yaml => Box::new(YamlFileDatabase { file_path }),
+            StorageFormat::Toml => Box::new(TomlFileDatabase { file_path }),
+            StorageFormat::Directory => Box::new(DirectoryDatabase { dir_path: file_path }),
+        };
+        ProjectsDatabase { database }
+    }
+
+    /// Like `new`, but keeps the history in git when the config asks for it, and
+    /// creates items in the replica this copy claims, see `ReplicaDatabase`.
+    pub fn open(file_path: String, config: &Config) -> Result<Self> {
+        let replica_path = replica_path(&file_path);
+        let database: Box<dyn Database> = if config.git {
+            Box::new(GitDatabase::open(&file_path)?)
+        } else {
+            ProjectsDatabase::new(file_path).database
+        };
+        Ok(ProjectsDatabase { database: Box::new(ReplicaDatabase::claimed(database, replica_path)) })
     }
 
     pub fn read_db(&self) -> Result<DBState> {
         self.database.read_db()
     }
 
-    pub fn create_epic(&self, epic: Epic) -> Result<u32> {
+    /// Runs `changes` on an in-memory copy of the database and, unless `dry_run`,
+    /// writes the result back once, so an import becomes a single git commit.
+    pub fn batch<T>(&self, dry_run: bool, changes: impl FnOnce(&ProjectsDatabase) -> Result<T>) -> Result<T> {
+        let inner = Box::new(InMemoryDatabase::new(self.read_db()?));
+        let copy = ProjectsDatabase { database: Box::new(ReplicaDatabase::fixed(inner, self.database.replica()?)) };
+        let result = changes(&copy)?;
+        if !dry_run {
+            self.database.write_db(&copy.read_db()?)?;
+        }
+        Ok(result)
+    }
+
+    pub fn create_epic(&self, mut epic: Epic) -> Result<ItemId> {
         let mut state = self.read_db()?;
-        let current_id = state.last_item_id + 1;
-        state.last_item_id = current_id;
+        let current_id = self.next_item_id(&mut state)?;
 
+        epic.updated_at = Some(Utc::now());
         state.epics.insert(current_id, epic);
         self.database.write_db(&state)?;
         Ok(current_id)
     }
 
-    pub fn create_story(&self, story: Story, epic_id: u32) -> Result<u32> {
+    pub fn create_story(&self, mut story: Story, epic_id: ItemId) -> Result<ItemId> {
         let mut state = self.read_db()?;
-        let current_id = state.last_item_id + 1;
+        let current_id = self.next_item_id(&mut state)?;
 
-        state.last_item_id = current_id;
+        story.updated_at = Some(Utc::now());
         state.stories.insert(current_id, story);
-        state.epics
-            .get_mut(&epic_id)
-            .ok_or_else(|| anyhow!("Epic not found!"))?
+        epic_mut(&mut state, epic_id)?
             .stories
             .push(current_id);
         self.database.write_db(&state)?;
         Ok(current_id)
     }
 
-    pub fn delete_epic(&self, epic_id: u32) -> Result<()> {
+    /// Hands out the next sequence number, in this copy's replica.
+    fn next_item_id(&self, state: &mut DBState) -> Result<ItemId> {
+        state.last_item_id += 1;
+        Ok(item_id(self.database.replica()?, state.last_item_id))
+    }
+
+    /// Sets the project key items are shown with, e.g. `PROJ` for `PROJ-43`.
+    pub fn set_key_prefix(&self, prefix: Option<String>) -> Result<()> {
+        let mut state = self.read_db()?;
+        if let Some(prefix) = &prefix {
+            if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_alphanumeric()) || prefix.chars().all(|c| c.is_ascii_digit()) {
+                return Err(anyhow!("key prefix '{}' should be letters and digits, like PROJ", prefix));
+            }
+        }
+        state.key_prefix = prefix;
+        self.database.write_db(&state)
+    }
+
+    pub fn delete_epic(&self, epic_id: ItemId) -> Result<()> {
         let mut state = self.read_db()?;
         for id in &state.epics
             .get(&epic_id)
@@ -61,12 +115,9 @@ impl ProjectsDatabase {
         Ok(())
     }
 
-    pub fn delete_story(&self, epic_id: u32, story_id: u32) -> Result<()> {
+    pub fn delete_story(&self, epic_id: ItemId, story_id: ItemId) -> Result<()> {
         let mut state = self.read_db()?;
-        let epic = state
-            .epics
-            .get_mut(&epic_id)
-            .ok_or_else(|| anyhow!("Epic with such id not found!"))?;
+        let epic = epic_mut(&mut state, epic_id)?;
         let story_idx = epic
             .stories
             .iter()
@@ -81,34 +132,248 @@ impl ProjectsDatabase {
         Ok(())
     }
 
-    pub fn update_epic_status(&self, epic_id: u32, status: Status) -> Result<()> {
+    pub fn update_epic_details(&self, epic_id: ItemId, name: String, description: String) -> Result<()> {
         let mut state = self.read_db()?;
-        state
-            .epics
-            .get_mut(&epic_id)
-            .ok_or_else(|| anyhow!("Epic with such id not found!"))?
-            .status = status;
+        let epic = epic_mut(&mut state, epic_id)?;
+        epic.name = name;
+        epic.description = description;
+
+        self.database.write_db(&state)?;
+        Ok(())
+    }
+
+    pub fn update_epic_status(&self, epic_id: ItemId, status: Status) -> Result<()> {
+        let mut state = self.read_db()?;
+        epic_mut(&mut state, epic_id)?.status = status;
+
+        self.database.write_db(&state)?;
+        Ok(())
+    }
+
+    pub fn update_story_details(&self, story_id: ItemId, name: String, description: String) -> Result<()> {
+        let mut state = self.read_db()?;
+        let story = story_mut(&mut state, story_id)?;
+        story.name = name;
+        story.description = description;
+
+        self.database.write_db(&state)?;
+        Ok(())
+    }
+
+    pub fn update_story_status(&self, story_id: ItemId, status: Status) -> Result<()> {
+        let mut state = self.read_db()?;
+        story_mut(&mut state, story_id)?.status = status;
+
+        self.database.write_db(&state)?;
+        Ok(())
+    }
+
+    pub fn create_field_definition(&self, definition: FieldDefinition) -> Result<()> {
+        let mut state = self.read_db()?;
+        if definition.name.trim().is_empty() {
+            return Err(anyhow!("Field name must not be empty!"));
+        }
+        if state.field_definition(&definition.name).is_some() {
+            return Err(anyhow!("Field '{}' already exists!", definition.name));
+        }
+        if let FieldKind::SingleSelect { options } | FieldKind::MultiSelect { options } = &definition.kind {
+            if options.is_empty() {
+                return Err(anyhow!("Field '{}' needs at least one option!", definition.name));
+            }
+        }
 
+        state.custom_fields.push(definition);
         self.database.write_db(&state)?;
         Ok(())
     }
 
-    pub fn update_story_status(&self, story_id: u32, status: Status) -> Result<()> {
+    /// Validates and stores a custom field value on an epic. Empty input clears the field.
+    pub fn set_epic_field(&self, epic_id: ItemId, field_name: &str, input: &str) -> Result<()> {
         let mut state = self.read_db()?;
+        let definition = state
+            .field_definition(field_name)
+            .filter(|definition| definition.target.applies_to_epics())
+            .ok_or_else(|| anyhow!("Epic field '{}' not found!", field_name))?
+            .clone();
+        let epic = epic_mut(&mut state, epic_id)?;
+
+        if input.trim().is_empty() {
+            epic.fields.remove(&definition.name);
+        } else {
+            epic.fields.insert(definition.name.clone(), definition.parse_value(input)?);
+        }
+
+        self.database.write_db(&state)?;
+        Ok(())
+    }
+
+    /// Validates and stores a custom field value on a story. Empty input clears the field.
+    pub fn set_story_field(&self, story_id: ItemId, field_name: &str, input: &str) -> Result<()> {
+        let mut state = self.read_db()?;
+        let story = state.stories.get(&story_id).ok_or_else(|| anyhow!("Story with such id not found!"))?;
+        let definition = state
+            .fields_of_story(story)
+            .into_iter()
+            .find(|definition| definition.name.eq_ignore_ascii_case(field_name))
+            .ok_or_else(|| anyhow!("Story field '{}' not found!", field_name))?;
+        let story = story_mut(&mut state, story_id)?;
+
+        if input.trim().is_empty() {
+            story.fields.remove(&definition.name);
+        } else {
+            story.fields.insert(definition.name.clone(), definition.parse_value(input)?);
+        }
+
+        self.database.write_db(&state)?;
+        Ok(())
+    }
+
+    pub fn update_story_points(&self, story_id: ItemId, points: Option<u32>) -> Result<()> {
+        let mut state = self.read_db()?;
+        story_mut(&mut state, story_id)?.points = points;
+
+        self.database.write_db(&state)?;
+        Ok(())
+    }
+
+    pub fn update_story_priority(&self, story_id: ItemId, priority: Priority) -> Result<()> {
+        let mut state = self.read_db()?;
+        story_mut(&mut state, story_id)?.priority = priority;
+
+        self.database.write_db(&state)?;
+        Ok(())
+    }
+
+    pub fn update_story_due_date(&self, story_id: ItemId, due_date: Option<NaiveDate>) -> Result<()> {
+        let mut state = self.read_db()?;
+        story_mut(&mut state, story_id)?.due_date = due_date;
+
+        self.database.write_db(&state)?;
+        Ok(())
+    }
+
+    pub fn create_sprint(&self, sprint: Sprint) -> Result<u32> {
+        let mut state = self.read_db()?;
+        if sprint.end < sprint.start {
+            return Err(anyhow!("Sprint can't end before it starts!"));
+        }
+
+        let current_id = state.last_sprint_id + 1;
+        state.last_sprint_id = current_id;
+
+        state.sprints.insert(current_id, sprint);
+        self.database.write_db(&state)?;
+        Ok(current_id)
+    }
+
+    pub fn start_sprint(&self, sprint_id: u32) -> Result<()> {
+        let mut state = self.read_db()?;
+        if state.sprints.values().any(|sprint| sprint.state == SprintState::Active) {
+            return Err(anyhow!("Another sprint is already active!"));
+        }
+
+        let sprint = state
+            .sprints
+            .get_mut(&sprint_id)
+            .ok_or_else(|| anyhow!("Sprint with such id not found!"))?;
+        if sprint.state != SprintState::Planned {
+            return Err(anyhow!("Only planned sprints can be started!"));
+        }
+        sprint.state = SprintState::Active;
+
+        self.database.write_db(&state)?;
+        Ok(())
+    }
+
+    /// Closes the sprint and moves its incomplete stories to `rollover_sprint_id`,
+    /// or to the backlog when it is `None`.
+    pub fn close_sprint(&self, sprint_id: u32, rollover_sprint_id: Option<u32>) -> Result<()> {
+        let mut state = self.read_db()?;
+        if let Some(rollover_sprint_id) = rollover_sprint_id {
+            let rollover_sprint = state
+                .sprints
+                .get(&rollover_sprint_id)
+                .ok_or_else(|| anyhow!("Sprint with such id not found!"))?;
+            if rollover_sprint_id == sprint_id || rollover_sprint.state == SprintState::Closed {
+                return Err(anyhow!("Stories can't be moved to a closed sprint!"));
+            }
+        }
+
+        let sprint = state
+            .sprints
+            .get_mut(&sprint_id)
+            .ok_or_else(|| anyhow!("Sprint with such id not found!"))?;
+        if sprint.state == SprintState::Closed {
+            return Err(anyhow!("Sprint is already closed!"));
+        }
+        sprint.state = SprintState::Closed;
+
         state
             .stories
-            .get_mut(&story_id)
-            .ok_or_else(|| anyhow!("Story with such id not found!"))?
-            .status = status;
+            .values_mut()
+            .filter(|story| story.sprint_id == Some(sprint_id) && !story.status.is_done())
+            .for_each(|story| {
+                story.sprint_id = rollover_sprint_id;
+                story.updated_at = Some(Utc::now());
+            });
+
+        self.database.write_db(&state)?;
+        Ok(())
+    }
+
+    /// Assigns a story to a sprint, or moves it back to the backlog when `sprint_id` is `None`.
+    pub fn assign_story_to_sprint(&self, story_id: ItemId, sprint_id: Option<u32>) -> Result<()> {
+        let mut state = self.read_db()?;
+        if let Some(sprint_id) = sprint_id {
+            let sprint = state
+                .sprints
+                .get(&sprint_id)
+                .ok_or_else(|| anyhow!("Sprint with such id not found!"))?;
+            if sprint.state == SprintState::Closed {
+                return Err(anyhow!("Stories can't be added to a closed sprint!"));
+            }
+        }
+
+        story_mut(&mut state, story_id)?.sprint_id = sprint_id;
 
         self.database.write_db(&state)?;
         Ok(())
     }
 }
 
+/// Looks up an epic for modification and stamps its `updated_at`.
+fn epic_mut(state: &mut DBState, epic_id: ItemId) -> Result<&mut Epic> {
+    let epic = state
+        .epics
+        .get_mut(&epic_id)
+        .ok_or_else(|| anyhow!("Epic with such id not found!"))?;
+    epic.updated_at = Some(Utc::now());
+    Ok(epic)
+}
+
+/// Looks up a story for modification and stamps its `updated_at`.
+fn story_mut(state: &mut DBState, story_id: ItemId) -> Result<&mut Story> {
+    let story = state
+        .stories
+        .get_mut(&story_id)
+        .ok_or_else(|| anyhow!("Story with such id not found!"))?;
+    story.updated_at = Some(Utc::now());
+    Ok(story)
+}
+
 pub trait Database {
     fn read_db(&self) -> Result<DBState>;
     fn write_db(&self, db_state: &DBState) -> Result<()>;
+
+    /// Replica number put in the ids of new items, 0 when the database has no copies.
+    fn replica(&self) -> Result<u32> {
+        Ok(0)
+    }
+
+    /// Exchanges changes with `remote`, for databases kept in git.
+    fn sync(&self, _remote: &str) -> Result<String> {
+        Err(anyhow!("the database isn't kept in git, set \"git\": true in the config"))
+    }
 }
 
 struct JSONFileDatabase {
@@ -133,9 +398,320 @@ impl Database for JSONFileDatabase {
     }
 }
 
+/// A copy of the database that creates items in its own replica, see `ItemId`. The
+/// replica is claimed on the first write, recorded in `DBState.replicas` and kept
+/// in a file next to the database, which a copy of the database doesn't come with.
+struct ReplicaDatabase {
+    inner: Box<dyn Database>,
+    /// Where the claimed replica is kept; `None` when it is fixed.
+    replica_path: Option<String>,
+    replica: Cell<Option<u32>>
+}
+
+impl ReplicaDatabase {
+    fn claimed(inner: Box<dyn Database>, replica_path: String) -> Self {
+        ReplicaDatabase { inner, replica_path: Some(replica_path), replica: Cell::new(None) }
+    }
+
+    fn fixed(inner: Box<dyn Database>, replica: u32) -> Self {
+        ReplicaDatabase { inner, replica_path: None, replica: Cell::new(Some(replica)) }
+    }
+}
+
+impl Database for ReplicaDatabase {
+    fn read_db(&self) -> Result<DBState> {
+        self.inner.read_db()
+    }
+
+    fn write_db(&self, db_state: &DBState) -> Result<()> {
+        let mut db_state = db_state.clone();
+        db_state.replicas.insert(self.replica()?);
+        self.inner.write_db(&db_state)
+    }
+
+    fn replica(&self) -> Result<u32> {
+        if let Some(replica) = self.replica.get() {
+            return Ok(replica);
+        }
+        let replica_path = self.replica_path.as_deref().ok_or_else(|| anyhow!("no replica"))?;
+        let replica = match fs::read_to_string(replica_path) {
+            Ok(replica) => replica.trim().parse().with_context(|| anyhow!("invalid replica file '{}'", replica_path))?,
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                let replica = new_replica(&self.inner.read_db().map(|state| state.replicas).unwrap_or_default());
+                fs::write(replica_path, replica.to_string()).with_context(|| anyhow!("couldn't write '{}'", replica_path))?;
+                replica
+            }
+            Err(error) => return Err(error).with_context(|| anyhow!("couldn't read '{}'", replica_path))
+        };
+        self.replica.set(Some(replica));
+        Ok(replica)
+    }
+
+    fn sync(&self, remote: &str) -> Result<String> {
+        self.inner.sync(remote)
+    }
+}
+
+/// The file a copy of the database at `file_path` keeps its replica in.
+pub fn replica_path(file_path: &str) -> String {
+    format!("{}.replica", file_path.trim_end_matches(['/', '\\']))
+}
+
+/// Replica 0 for the first copy, so existing ids stay as they are, otherwise a
+/// random one no other copy claimed. At most five digits, since the replica is
+/// part of keys like `PROJ-43.20517`.
+fn new_replica(claimed: &BTreeSet<u32>) -> u32 {
+    if claimed.is_empty() {
+        return 0;
+    }
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos()));
+    let mut replica = (hasher.finish() % 99_999) as u32 + 1;
+    while claimed.contains(&replica) {
+        replica = replica % 99_999 + 1;
+    }
+    replica
+}
+
+/// Formats a database file can be stored in.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StorageFormat {
+    Json,
+    Yaml,
+    Toml,
+    /// One file per item, see `DirectoryDatabase`.
+    Directory,
+}
+
+impl StorageFormat {
+    /// Existing directories and paths ending in `/` use one file per item, `.yaml`/`.yml`
+    /// files are YAML and `.toml` files TOML; anything else is JSON.
+    pub fn from_path(file_path: &str) -> StorageFormat {
+        if file_path.ends_with('/') || Path::new(file_path).is_dir() {
+            return StorageFormat::Directory;
+        }
+        let extension = Path::new(file_path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+        match extension.as_deref() {
+            Some("yaml" | "yml") => StorageFormat::Yaml,
+            Some("toml") => StorageFormat::Toml,
+            _ => StorageFormat::Json
+        }
+    }
+}
+
+/// Copies the database in `from` to a new file or directory `to`, converting between
+/// the formats their paths say.
+pub fn convert_database(from: &str, to: &str) -> Result<()> {
+    if Path::new(to).exists() {
+        return Err(anyhow!("'{}' already exists", to));
+    }
+    let state = ProjectsDatabase::new(from.to_owned()).read_db().with_context(|| anyhow!("couldn't read '{}'", from))?;
+    ProjectsDatabase::new(to.to_owned()).database.write_db(&state)
+}
+
+/// Pretty YAML. Items are ordered by id, so only changed items show up in diffs.
+struct YamlFileDatabase {
+    file_path: String
+}
+
+impl Database for YamlFileDatabase {
+    fn read_db(&self) -> Result<DBState> {
+        let data = fs::read_to_string(&self.file_path).with_context(|| anyhow!("couldn't read '{}'", self.file_path))?;
+        serde_yaml::from_str(&data).with_context(|| anyhow!("invalid YAML in '{}'", self.file_path))
+    }
+
+    fn write_db(&self, db_state: &DBState) -> Result<()> {
+        let data = serde_yaml::to_string(db_state)?;
+        fs::write(&self.file_path, data).with_context(|| anyhow!("couldn't write '{}'", self.file_path))
+    }
+}
+
+/// Pretty TOML. TOML tables only have string keys and no null, so the state goes
+/// through a JSON value with ids as keys like `"12"` and unset values left out;
+/// tables are then sorted by those keys as text.
+struct TomlFileDatabase {
+    file_path: String
+}
+
+impl Database for TomlFileDatabase {
+    fn read_db(&self) -> Result<DBState> {
+        let data = fs::read_to_string(&self.file_path).with_context(|| anyhow!("couldn't read '{}'", self.file_path))?;
+        from_toml(&data).with_context(|| anyhow!("invalid TOML in '{}'", self.file_path))
+    }
+
+    fn write_db(&self, db_state: &DBState) -> Result<()> {
+        fs::write(&self.file_path, to_toml(db_state)?).with_context(|| anyhow!("couldn't write '{}'", self.file_path))
+    }
+}
+
+fn to_toml<T: Serialize>(value: &T) -> Result<String> {
+    let value = without_nulls(serde_json::to_value(value)?);
+    Ok(toml::to_string_pretty(&value)?)
+}
+
+fn from_toml<T: DeserializeOwned>(data: &str) -> Result<T> {
+    let value: serde_json::Value = toml::from_str(data)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+fn without_nulls(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => map
+            .into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| (key, without_nulls(value)))
+            .collect(),
+        serde_json::Value::Array(values) => values.into_iter().map(without_nulls).collect(),
+        value => value
+    }
+}
+
+/// One file per item, so edits to different items merge cleanly in git:
+///
+/// - `index.toml` holds the counters, issue types, custom fields and sprints,
+/// - `epics/<id>.toml` holds an epic,
+/// - `stories/<id>.md` holds a story, its description as Markdown after YAML front matter.
+///
+/// Only files whose content changed are rewritten, and files of deleted items are removed.
+struct DirectoryDatabase {
+    dir_path: String
+}
+
+const INDEX_FILE: &str = "index.toml";
+const EPICS_DIR: &str = "epics";
+const STORIES_DIR: &str = "stories";
+
+impl Database for DirectoryDatabase {
+    fn read_db(&self) -> Result<DBState> {
+        let dir = Path::new(&self.dir_path);
+        let mut state: DBState = read_item_file(&dir.join(INDEX_FILE), from_toml)?;
+        for (id, path) in item_files(&dir.join(EPICS_DIR), "toml")? {
+            state.epics.insert(id, read_item_file(&path, from_toml)?);
+        }
+        for (id, path) in item_files(&dir.join(STORIES_DIR), "md")? {
+            state.stories.insert(id, read_item_file(&path, story_from_markdown)?);
+        }
+        Ok(state)
+    }
+
+    fn write_db(&self, db_state: &DBState) -> Result<()> {
+        let dir = Path::new(&self.dir_path);
+        let index = DBState { epics: BTreeMap::new(), stories: BTreeMap::new(), ..db_state.clone() };
+        write_if_changed(&dir.join(INDEX_FILE), &to_toml(&index)?)?;
+
+        let epics = db_state.epics
+            .iter()
+            .map(|(id, epic)| Ok((*id, to_toml(epic)?)))
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        write_item_files(&dir.join(EPICS_DIR), "toml", &epics)?;
+
+        let stories = db_state.stories
+            .iter()
+            .map(|(id, story)| Ok((*id, story_to_markdown(story)?)))
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        write_item_files(&dir.join(STORIES_DIR), "md", &stories)
+    }
+}
+
+fn read_item_file<T>(path: &Path, parse: fn(&str) -> Result<T>) -> Result<T> {
+    let data = fs::read_to_string(path).with_context(|| anyhow!("couldn't read '{}'", path.display()))?;
+    parse(&data).with_context(|| anyhow!("invalid item in '{}'", path.display()))
+}
+
+/// The `<id>.<extension>` files in `dir` by id; a missing directory has none.
+fn item_files(dir: &Path, extension: &str) -> Result<BTreeMap<ItemId, PathBuf>> {
+    if !dir.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let mut files = BTreeMap::new();
+    for entry in fs::read_dir(dir).with_context(|| anyhow!("couldn't read '{}'", dir.display()))? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(extension) {
+            continue;
+        }
+        let id = path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse().ok())
+            .ok_or_else(|| anyhow!("'{}' isn't named after an item id", path.display()))?;
+        files.insert(id, path);
+    }
+    Ok(files)
+}
+
+fn write_item_files(dir: &Path, extension: &str, items: &BTreeMap<ItemId, String>) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| anyhow!("couldn't create '{}'", dir.display()))?;
+    for (id, path) in item_files(dir, extension)? {
+        if !items.contains_key(&id) {
+            fs::remove_file(&path).with_context(|| anyhow!("couldn't remove '{}'", path.display()))?;
+        }
+    }
+    for (id, data) in items {
+        write_if_changed(&dir.join(format!("{}.{}", id, extension)), data)?;
+    }
+    Ok(())
+}
+
+fn write_if_changed(path: &Path, data: &str) -> Result<()> {
+    if fs::read_to_string(path).is_ok_and(|current| current == data) {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| anyhow!("couldn't create '{}'", parent.display()))?;
+    }
+    fs::write(path, data).with_context(|| anyhow!("couldn't write '{}'", path.display()))
+}
+
+const FRONT_MATTER: &str = "---\n";
+
+fn story_to_markdown(story: &Story) -> Result<String> {
+    let mut front_matter = serde_yaml::to_value(story)?;
+    if let serde_yaml::Value::Mapping(fields) = &mut front_matter {
+        fields.shift_remove("description");
+    }
+    Ok(format!("{}{}{}{}", FRONT_MATTER, serde_yaml::to_string(&front_matter)?, FRONT_MATTER, story.description))
+}
+
+fn story_from_markdown(data: &str) -> Result<Story> {
+    let (front_matter, description) = data
+        .strip_prefix(FRONT_MATTER)
+        .and_then(|rest| {
+            let end = rest.find(&format!("\n{}", FRONT_MATTER))?;
+            Some((&rest[..=end], &rest[end + 1 + FRONT_MATTER.len()..]))
+        })
+        .ok_or_else(|| anyhow!("missing front matter between '---' lines"))?;
+    let mut story = serde_yaml::from_str::<serde_yaml::Value>(front_matter)?;
+    if let serde_yaml::Value::Mapping(fields) = &mut story {
+        fields.insert("description".into(), description.into());
+    }
+    Ok(serde_yaml::from_value(story)?)
+}
+
+/// Keeps the state in memory only, e.g. to preview changes on a copy of the database.
+pub struct InMemoryDatabase {
+    state: RefCell<DBState>
+}
+
+impl InMemoryDatabase {
+    pub fn new(state: DBState) -> Self {
+        InMemoryDatabase { state: RefCell::new(state) }
+    }
+}
+
+impl Database for InMemoryDatabase {
+    fn read_db(&self) -> Result<DBState> {
+        Ok(self.state.borrow().clone())
+    }
+
+    fn write_db(&self, db_state: &DBState) -> Result<()> {
+        *self.state.borrow_mut() = db_state.clone();
+        Ok(())
+    }
+}
+
 pub mod test_utils {
-    use std::cell::RefCell;
-    use std::collections::HashMap;
     use super::*;
 
     pub struct MockDb {
@@ -145,11 +721,7 @@ pub mod test_utils {
     impl MockDb {
         pub fn new() -> Self {
             Self {
-                last_written_state: RefCell::new(DBState {
-                    last_item_id: 0,
-                    epics: HashMap::new(),
-                    stories: HashMap::new()
-                })
+                last_written_state: RefCell::new(DBState::default())
             }
         }
     }
@@ -172,6 +744,7 @@ pub mod test_utils {
 mod tests {
     use super::*;
     use super::test_utils::MockDb;
+    use crate::models::{item_replica, item_sequence, FieldKind, FieldTarget, FieldValue, IssueType};
 
     #[test]
     fn create_epic_should_work() {
@@ -186,11 +759,13 @@ mod tests {
         let id = result.unwrap();
         let db_state = db.read_db().unwrap();
 
-        let expected_id = 1;
+        let expected_id: ItemId = 1;
 
         assert_eq!(id, expected_id);
-        assert_eq!(db_state.last_item_id, expected_id);
-        assert_eq!(db_state.epics.get(&id), Some(&epic));
+        assert_eq!(u64::from(db_state.last_item_id), expected_id);
+        let saved_epic = db_state.epics.get(&id).unwrap();
+        assert_eq!(saved_epic.updated_at.is_some(), true);
+        assert_eq!(saved_epic, &Epic { updated_at: saved_epic.updated_at, ..epic });
     }
 
     #[test]
@@ -222,12 +797,14 @@ mod tests {
         let id = result.unwrap();
         let db_state = db.read_db().unwrap();
 
-        let expected_id = 2;
+        let expected_id: ItemId = 2;
 
         assert_eq!(id, expected_id);
-        assert_eq!(db_state.last_item_id, expected_id);
+        assert_eq!(u64::from(db_state.last_item_id), expected_id);
         assert_eq!(db_state.epics.get(&epic_id).unwrap().stories.contains(&id), true);
-        assert_eq!(db_state.stories.get(&id), Some(&story));
+        let saved_story = db_state.stories.get(&id).unwrap();
+        assert_eq!(saved_story.updated_at.is_some(), true);
+        assert_eq!(saved_story, &Story { updated_at: saved_story.updated_at, ..story });
     }
 
     #[test]
@@ -368,6 +945,23 @@ mod tests {
         assert_eq!(db_state.epics.get(&epic_id).unwrap().status, Status::Closed);
     }
 
+    #[test]
+    fn update_details_should_rename_epics_and_stories() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
+
+        assert_eq!(db.update_epic_details(epic_id, "Auth".to_owned(), "Login and logout".to_owned()).is_ok(), true);
+        assert_eq!(db.update_story_details(story_id, "Login".to_owned(), "With password".to_owned()).is_ok(), true);
+        assert_eq!(db.update_story_details(999, "".to_owned(), "".to_owned()).is_err(), true);
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.epics[&epic_id].name, "Auth");
+        assert_eq!(db_state.epics[&epic_id].description, "Login and logout");
+        assert_eq!(db_state.stories[&story_id].name, "Login");
+        assert_eq!(db_state.stories[&story_id].description, "With password");
+    }
+
     #[test]
     fn update_story_status_should_error_if_invalid_story_id() {
         let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
@@ -401,10 +995,224 @@ mod tests {
         assert_eq!(db_state.stories.get(&story_id).unwrap().status, Status::Closed);
     }
 
+    #[test]
+    fn create_field_definition_should_error_on_duplicate_name() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+
+        let result = db.create_field_definition(FieldDefinition::new("Points".to_owned(), FieldKind::Number, FieldTarget::Stories));
+        assert_eq!(result.is_ok(), true);
+
+        let result = db.create_field_definition(FieldDefinition::new("points".to_owned(), FieldKind::Text, FieldTarget::Epics));
+        assert_eq!(result.is_err(), true);
+
+        assert_eq!(db.read_db().unwrap().custom_fields.len(), 1);
+    }
+
+    #[test]
+    fn create_field_definition_should_require_options_for_select_fields() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+
+        let result = db.create_field_definition(FieldDefinition::new("team".to_owned(), FieldKind::SingleSelect { options: vec![] }, FieldTarget::Stories));
+        assert_eq!(result.unwrap_err().to_string(), "Field 'team' needs at least one option!");
+        let result = db.create_field_definition(FieldDefinition::new("labels".to_owned(), FieldKind::MultiSelect { options: vec![] }, FieldTarget::Stories));
+        assert_eq!(result.is_err(), true);
+        let result = db.create_field_definition(FieldDefinition::new("team".to_owned(), FieldKind::SingleSelect { options: vec!["core".to_owned()] }, FieldTarget::Stories));
+        assert_eq!(result.is_ok(), true);
+
+        assert_eq!(db.read_db().unwrap().custom_fields.len(), 1);
+    }
+
+    #[test]
+    fn set_story_field_should_validate_input() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
+
+        db.create_field_definition(FieldDefinition::new("points".to_owned(), FieldKind::Number, FieldTarget::Stories)).unwrap();
+        db.create_field_definition(FieldDefinition::new("labels".to_owned(), FieldKind::MultiSelect {
+            options: vec!["backend".to_owned(), "ui".to_owned()]
+        }, FieldTarget::Both)).unwrap();
+        db.create_field_definition(FieldDefinition::new("owner".to_owned(), FieldKind::User, FieldTarget::Epics)).unwrap();
+
+        assert_eq!(db.set_story_field(story_id, "points", "five").is_err(), true);
+        assert_eq!(db.set_story_field(story_id, "labels", "backend, mobile").is_err(), true);
+        assert_eq!(db.set_story_field(story_id, "owner", "@alice").is_err(), true);
+        assert_eq!(db.set_story_field(999, "points", "5").is_err(), true);
+
+        assert_eq!(db.set_story_field(story_id, "points", "5").is_ok(), true);
+        assert_eq!(db.set_story_field(story_id, "Labels", "UI,backend").is_ok(), true);
+        assert_eq!(db.set_epic_field(epic_id, "owner", "@alice").is_ok(), true);
+
+        let db_state = db.read_db().unwrap();
+        let story = db_state.stories.get(&story_id).unwrap();
+        assert_eq!(story.fields.get("points"), Some(&FieldValue::Number(5.0)));
+        assert_eq!(story.fields.get("labels"), Some(&FieldValue::MultiSelect(vec!["ui".to_owned(), "backend".to_owned()])));
+        assert_eq!(db_state.epics.get(&epic_id).unwrap().fields.get("owner"), Some(&FieldValue::User("alice".to_owned())));
+
+        assert_eq!(db.set_story_field(story_id, "points", "").is_ok(), true);
+        assert_eq!(db.read_db().unwrap().stories.get(&story_id).unwrap().fields.contains_key("points"), false);
+    }
+
+    #[test]
+    fn set_story_field_should_accept_the_fields_of_the_story_type() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
+        let bug_id = db.create_story(Story::new("".to_owned(), "".to_owned()).with_issue_type(IssueType::new("Bug")), epic_id).unwrap();
+
+        assert_eq!(db.set_story_field(story_id, "severity", "high").is_err(), true);
+        assert_eq!(db.set_story_field(bug_id, "severity", "blocker").is_err(), true);
+        assert_eq!(db.set_story_field(bug_id, "severity", "high").is_ok(), true);
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.stories[&bug_id].fields.get("Severity"), Some(&FieldValue::SingleSelect("HIGH".to_owned())));
+    }
+
+    #[test]
+    fn update_story_priority_and_due_date_should_work() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
+        let due_date = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+
+        assert_eq!(db.update_story_priority(999, Priority::High).is_err(), true);
+        assert_eq!(db.update_story_due_date(999, Some(due_date)).is_err(), true);
+        assert_eq!(db.update_story_priority(story_id, Priority::High).is_ok(), true);
+        assert_eq!(db.update_story_due_date(story_id, Some(due_date)).is_ok(), true);
+
+        let story = db.read_db().unwrap().stories.remove(&story_id).unwrap();
+        assert_eq!(story.priority, Priority::High);
+        assert_eq!(story.due_date, Some(due_date));
+        assert_eq!(story.is_overdue(NaiveDate::from_ymd_opt(2024, 2, 1).unwrap()), true);
+        assert_eq!(story.is_overdue(due_date), false);
+
+        assert_eq!(db.update_story_due_date(story_id, None).is_ok(), true);
+        assert_eq!(db.read_db().unwrap().stories.get(&story_id).unwrap().due_date, None);
+    }
+
+    fn sprint(start_day: u32) -> Sprint {
+        let start = chrono::NaiveDate::from_ymd_opt(2024, 1, start_day).unwrap();
+        Sprint::new("".to_owned(), "".to_owned(), start, start + chrono::Duration::days(13))
+    }
+
+    #[test]
+    fn create_sprint_should_error_if_end_before_start() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        let mut invalid_sprint = sprint(1);
+        invalid_sprint.end = invalid_sprint.start - chrono::Duration::days(1);
+
+        assert_eq!(db.create_sprint(invalid_sprint).is_err(), true);
+        assert_eq!(db.create_sprint(sprint(1)).unwrap(), 1);
+        assert_eq!(db.read_db().unwrap().last_item_id, 0);
+    }
+
+    #[test]
+    fn start_sprint_should_allow_only_one_active_sprint() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        let first_sprint_id = db.create_sprint(sprint(1)).unwrap();
+        let second_sprint_id = db.create_sprint(sprint(15)).unwrap();
+
+        assert_eq!(db.start_sprint(first_sprint_id).is_ok(), true);
+        assert_eq!(db.start_sprint(first_sprint_id).is_err(), true);
+        assert_eq!(db.start_sprint(second_sprint_id).is_err(), true);
+        assert_eq!(db.start_sprint(999).is_err(), true);
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.sprints.get(&first_sprint_id).unwrap().state, SprintState::Active);
+    }
+
+    #[test]
+    fn close_sprint_should_roll_over_incomplete_stories() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let done_story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
+        let open_story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
+        let sprint_id = db.create_sprint(sprint(1)).unwrap();
+        let next_sprint_id = db.create_sprint(sprint(15)).unwrap();
+
+        db.assign_story_to_sprint(done_story_id, Some(sprint_id)).unwrap();
+        db.assign_story_to_sprint(open_story_id, Some(sprint_id)).unwrap();
+        db.update_story_status(done_story_id, Status::Resolved).unwrap();
+
+        assert_eq!(db.close_sprint(sprint_id, Some(sprint_id)).is_err(), true);
+        assert_eq!(db.close_sprint(sprint_id, Some(next_sprint_id)).is_ok(), true);
+        assert_eq!(db.close_sprint(sprint_id, None).is_err(), true);
+        assert_eq!(db.assign_story_to_sprint(open_story_id, Some(sprint_id)).is_err(), true);
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.sprints.get(&sprint_id).unwrap().state, SprintState::Closed);
+        assert_eq!(db_state.stories.get(&done_story_id).unwrap().sprint_id, Some(sprint_id));
+        assert_eq!(db_state.stories.get(&open_story_id).unwrap().sprint_id, Some(next_sprint_id));
+
+        assert_eq!(db.close_sprint(next_sprint_id, None).is_ok(), true);
+        assert_eq!(db.read_db().unwrap().stories.get(&open_story_id).unwrap().sprint_id, None);
+    }
+
+    #[test]
+    fn replicas_should_hand_out_different_ids() {
+        let first = ProjectsDatabase { database: Box::new(ReplicaDatabase::fixed(Box::new(MockDb::new()), 1)) };
+        let second = ProjectsDatabase { database: Box::new(ReplicaDatabase::fixed(Box::new(MockDb::new()), 2)) };
+
+        let first_epic = first.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let second_epic = second.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let story_id = second.create_story(Story::new("".to_owned(), "".to_owned()), second_epic).unwrap();
+
+        assert_eq!((first_epic, second_epic, story_id), (item_id(1, 1), item_id(2, 1), item_id(2, 2)));
+        assert_eq!(second.read_db().unwrap().last_item_id, 2);
+    }
+
+    #[test]
+    fn copies_of_the_database_file_should_claim_their_own_replicas() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_owned();
+        ProjectsDatabase::new(path("db.json")).database.write_db(&DBState::default()).unwrap();
+        let original = ProjectsDatabase::open(path("db.json"), &Config::default()).unwrap();
+        original.create_epic(Epic::new("Auth".to_owned(), "".to_owned())).unwrap();
+        fs::copy(path("db.json"), path("copy.json")).unwrap();
+        let copy = ProjectsDatabase::open(path("copy.json"), &Config::default()).unwrap();
+
+        let first = original.create_epic(Epic::new("Billing".to_owned(), "".to_owned())).unwrap();
+        let second = copy.create_epic(Epic::new("Search".to_owned(), "".to_owned())).unwrap();
+
+        let replica = item_replica(second);
+        assert_eq!((first, item_sequence(second)), (2, 2));
+        assert_eq!(replica > 0, true);
+        assert_eq!(copy.read_db().unwrap().replicas, BTreeSet::from([0, replica]));
+        assert_eq!(fs::read_to_string(path("copy.json.replica")).unwrap(), replica.to_string());
+        assert_eq!(ProjectsDatabase::open(path("copy.json"), &Config::default()).unwrap().database.replica().unwrap(), replica);
+    }
+
+    #[test]
+    fn item_keys_should_show_the_prefix_and_find_items() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        assert_eq!(db.set_key_prefix(Some("P-1".to_owned())).is_err(), true);
+        db.set_key_prefix(Some("PROJ".to_owned())).unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.item_key(epic_id), "PROJ-1");
+        assert_eq!(db_state.find_item("proj-1").unwrap(), epic_id);
+        assert_eq!(db_state.find_item("1").unwrap(), epic_id);
+        assert_eq!(db_state.find_item("PROJ-2").is_err(), true);
+        assert_eq!(db_state.find_item("PROJ").is_err(), true);
+
+        let mut db_state = db_state;
+        let other_id = item_id(7, 1);
+        db_state.epics.insert(other_id, Epic::new("".to_owned(), "".to_owned()));
+        assert_eq!(db_state.item_key(epic_id), "PROJ-1");
+        assert_eq!(db_state.item_key(other_id), "PROJ-1.7");
+        assert_eq!(db_state.find_item("PROJ-1").unwrap(), epic_id);
+        assert_eq!(db_state.find_item("PROJ-1.7").unwrap(), other_id);
+        assert_eq!(db_state.find_item("PROJ-1.8").is_err(), true);
+        assert_eq!(db_state.find_item(&other_id.to_string()).unwrap(), other_id);
+    }
+
     mod database {
-        use std::collections::HashMap;
+        use std::collections::BTreeMap;
         use std::io::Write;
 
+        use chrono::NaiveDate;
+        use crate::models::{default_issue_types, FieldKind, FieldTarget, FieldValue, IssueType};
         use super::*;
 
         #[test]
@@ -444,25 +1252,53 @@ mod tests {
         }
 
         #[test]
-        fn write_db_should_work() {
+        fn read_db_should_turn_old_bug_details_into_fields() {
             let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
 
-            let file_contents = r#"{ "last_item_id": 0, "epics": {}, "stories": {} }"#;
+            let file_contents = r#"{ "last_item_id": 1, "epics": {}, "stories": { "1": { "name": "crash", "description": "", "status": "Open", "issue_type": "Bug", "bug": { "steps_to_reproduce": "1. open", "severity": "Critical" } } } }"#;
             write!(tmpfile, "{}", file_contents).unwrap();
 
             let db = JSONFileDatabase { file_path: tmpfile.path().to_str()
                 .expect("failed to convert tmpfile path to str").to_string() };
 
-            let story = Story { name: "epic 1".to_owned(), description: "epic 1".to_owned(), status: Status::Open };
-            let epic = Epic { name: "epic 1".to_owned(), description: "epic 1".to_owned(), status: Status::Open, stories: vec![2] };
+            let story = db.read_db().unwrap().stories.remove(&1).unwrap();
 
-            let mut stories = HashMap::new();
+            assert_eq!(story.issue_type, IssueType::new("Bug"));
+            assert_eq!(story.fields.get("Steps to reproduce"), Some(&FieldValue::Text("1. open".to_owned())));
+            assert_eq!(story.fields.get("Severity"), Some(&FieldValue::SingleSelect("CRITICAL".to_owned())));
+        }
+
+        fn sample_state() -> DBState {
+            let mut story = Story { name: "epic 1".to_owned(), description: "epic 1".to_owned(), status: Status::Open, issue_type: IssueType::new("Bug"), fields: BTreeMap::new(), points: Some(3), sprint_id: Some(1), priority: Priority::High, due_date: NaiveDate::from_ymd_opt(2024, 2, 1), updated_at: None };
+            story.fields.insert("Severity".to_owned(), FieldValue::SingleSelect("HIGH".to_owned()));
+            let mut epic = Epic { name: "epic 1".to_owned(), description: "epic 1".to_owned(), status: Status::Open, stories: vec![2], fields: BTreeMap::new(), updated_at: None };
+            epic.fields.insert("due".to_owned(), FieldValue::Date(NaiveDate::from_ymd_opt(2024, 1, 31).unwrap()));
+
+            let mut stories = BTreeMap::new();
             stories.insert(2, story);
 
-            let mut epics = HashMap::new();
+            let mut epics = BTreeMap::new();
             epics.insert(1, epic);
 
-            let state = DBState { last_item_id: 2, epics, stories };
+            let custom_fields = vec![FieldDefinition::new("due".to_owned(), FieldKind::Date, FieldTarget::Epics)];
+
+            let mut sprints = BTreeMap::new();
+            sprints.insert(1, Sprint::new("Sprint 1".to_owned(), "".to_owned(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 1, 14).unwrap()));
+
+            DBState { last_item_id: 2, epics, stories, issue_types: default_issue_types(), custom_fields, last_sprint_id: 1, sprints, key_prefix: Some("PROJ".to_owned()), replicas: BTreeSet::new() }
+        }
+
+        #[test]
+        fn write_db_should_work() {
+            let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+
+            let file_contents = r#"{ "last_item_id": 0, "epics": {}, "stories": {} }"#;
+            write!(tmpfile, "{}", file_contents).unwrap();
+
+            let db = JSONFileDatabase { file_path: tmpfile.path().to_str()
+                .expect("failed to convert tmpfile path to str").to_string() };
+
+            let state = sample_state();
 
             let write_result = db.write_db(&state);
             let read_result = db.read_db().unwrap();
@@ -470,5 +1306,112 @@ mod tests {
             assert_eq!(write_result.is_ok(), true);
             assert_eq!(read_result, state);
         }
+
+        #[test]
+        fn storage_format_should_follow_the_extension() {
+            assert_eq!(StorageFormat::from_path("data/db.json"), StorageFormat::Json);
+            assert_eq!(StorageFormat::from_path("data/db.YML"), StorageFormat::Yaml);
+            assert_eq!(StorageFormat::from_path("data/db.yaml"), StorageFormat::Yaml);
+            assert_eq!(StorageFormat::from_path("data/db.toml"), StorageFormat::Toml);
+            assert_eq!(StorageFormat::from_path("data/db"), StorageFormat::Json);
+            assert_eq!(StorageFormat::from_path("data/db/"), StorageFormat::Directory);
+            assert_eq!(StorageFormat::from_path(std::env::temp_dir().to_str().unwrap()), StorageFormat::Directory);
+        }
+
+        #[test]
+        fn yaml_and_toml_should_round_trip_deterministically() {
+            for suffix in [".yaml", ".toml"] {
+                let tmpfile = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+                let db = ProjectsDatabase::new(tmpfile.path().to_str().unwrap().to_owned());
+                let state = sample_state();
+
+                assert_eq!(db.database.write_db(&state).is_ok(), true);
+                let written = fs::read_to_string(tmpfile.path()).unwrap();
+                assert_eq!(db.read_db().unwrap(), state);
+
+                db.database.write_db(&db.read_db().unwrap()).unwrap();
+                assert_eq!(fs::read_to_string(tmpfile.path()).unwrap(), written);
+            }
+        }
+
+        #[test]
+        fn yaml_should_be_pretty_and_ordered_by_id() {
+            let tmpfile = tempfile::Builder::new().suffix(".yml").tempfile().unwrap();
+            let db = ProjectsDatabase::new(tmpfile.path().to_str().unwrap().to_owned());
+            let mut state = sample_state();
+            state.epics.insert(10, Epic::new("epic 10".to_owned(), "".to_owned()));
+
+            db.database.write_db(&state).unwrap();
+            let written = fs::read_to_string(tmpfile.path()).unwrap();
+
+            assert_eq!(written.starts_with("last_item_id: 2\nepics:\n  1:\n    name: epic 1\n"), true);
+            assert_eq!(written.find("  1:").unwrap() < written.find("  10:").unwrap(), true);
+        }
+
+        #[test]
+        fn directory_database_should_store_one_file_per_item() {
+            let dir = tempfile::tempdir().unwrap();
+            let db = ProjectsDatabase::new(dir.path().to_str().unwrap().to_owned());
+            let state = sample_state();
+
+            assert_eq!(db.database.write_db(&state).is_ok(), true);
+            assert_eq!(db.read_db().unwrap(), state);
+            assert_eq!(dir.path().join("index.toml").is_file(), true);
+            assert_eq!(dir.path().join("epics/1.toml").is_file(), true);
+
+            let story = fs::read_to_string(dir.path().join("stories/2.md")).unwrap();
+            assert_eq!(story.starts_with("---\nname: epic 1\nstatus: Open\n"), true);
+            assert_eq!(story.ends_with("\n---\nepic 1"), true);
+        }
+
+        #[test]
+        fn directory_database_should_only_touch_changed_items() {
+            let dir = tempfile::tempdir().unwrap();
+            let db = ProjectsDatabase::new(format!("{}/", dir.path().join("db").to_str().unwrap()));
+            let mut state = sample_state();
+            state.stories.insert(3, Story::new("notes".to_owned(), "".to_owned()));
+            db.database.write_db(&state).unwrap();
+            let epic_file = dir.path().join("db/epics/1.toml");
+            File::options().write(true).open(&epic_file).unwrap().set_modified(std::time::UNIX_EPOCH).unwrap();
+
+            state.stories.remove(&2);
+            state.stories.get_mut(&3).unwrap().description = "line 1\n---\nline 3\n".to_owned();
+            db.database.write_db(&state).unwrap();
+
+            assert_eq!(dir.path().join("db/stories/2.md").exists(), false);
+            assert_eq!(fs::metadata(&epic_file).unwrap().modified().unwrap(), std::time::UNIX_EPOCH);
+            assert_eq!(db.read_db().unwrap(), state);
+        }
+
+        #[test]
+        fn directory_database_should_report_invalid_files() {
+            let dir = tempfile::tempdir().unwrap();
+            let db = ProjectsDatabase::new(dir.path().to_str().unwrap().to_owned());
+            assert_eq!(db.read_db().is_err(), true);
+
+            db.database.write_db(&sample_state()).unwrap();
+            fs::write(dir.path().join("stories/2.md"), "name: no front matter").unwrap();
+            let error = db.read_db().unwrap_err();
+
+            assert_eq!(format!("{:#}", error).ends_with("missing front matter between '---' lines"), true);
+        }
+
+        #[test]
+        fn convert_database_should_copy_between_formats() {
+            let dir = tempfile::tempdir().unwrap();
+            let json = dir.path().join("db.json").to_str().unwrap().to_owned();
+            let toml = dir.path().join("db.toml").to_str().unwrap().to_owned();
+            let yaml = dir.path().join("db.yaml").to_str().unwrap().to_owned();
+            ProjectsDatabase::new(json.clone()).database.write_db(&sample_state()).unwrap();
+
+            let directory = format!("{}/", dir.path().join("items").to_str().unwrap());
+
+            assert_eq!(convert_database(&json, &toml).is_ok(), true);
+            assert_eq!(convert_database(&toml, &directory).is_ok(), true);
+            assert_eq!(convert_database(&directory, &yaml).is_ok(), true);
+            assert_eq!(ProjectsDatabase::new(yaml.clone()).read_db().unwrap(), sample_state());
+            assert_eq!(convert_database(&json, &yaml).unwrap_err().to_string(), format!("'{}' already exists", yaml));
+            assert_eq!(convert_database("INVALID_PATH.json", dir.path().join("new.yaml").to_str().unwrap()).is_err(), true);
+        }
     }
 }
\ No newline at end of file
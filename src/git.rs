@@ -0,0 +1,464 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use crate::db::{replica_path, Database, ProjectsDatabase, StorageFormat};
+use crate::merge::{Merge, Side};
+use crate::models::{DBState, ItemId};
+
+static WORKTREES: AtomicUsize = AtomicUsize::new(0);
+
+/// Runs git in the repository that holds the database.
+pub struct GitRepo {
+    dir: PathBuf
+}
+
+impl GitRepo {
+    /// Opens the repository `dir` is in, creating one at `dir` when there is none,
+    /// so a database inside a project is kept in the project's repository.
+    pub fn open_or_init(dir: &Path) -> Result<GitRepo> {
+        fs::create_dir_all(dir).with_context(|| anyhow!("couldn't create '{}'", dir.display()))?;
+        let repo = GitRepo { dir: dir.canonicalize()? };
+        match repo.run(&["rev-parse", "--show-toplevel"]) {
+            Ok(top_level) => Ok(GitRepo { dir: PathBuf::from(top_level).canonicalize()? }),
+            Err(_) => {
+                repo.run(&["init", "--quiet"])?;
+                Ok(repo)
+            }
+        }
+    }
+
+    pub fn run(&self, args: &[&str]) -> Result<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .output()
+            .with_context(|| anyhow!("couldn't run git"))?;
+        if !output.status.success() {
+            return Err(anyhow!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    }
+
+    fn succeeds(&self, args: &[&str]) -> Result<bool> {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .output()
+            .with_context(|| anyhow!("couldn't run git"))?
+            .status;
+        Ok(status.success())
+    }
+
+    /// Adds `pattern` to the repository's own ignore list, which isn't committed.
+    pub fn exclude(&self, pattern: &str) -> Result<()> {
+        let exclude = self.dir.join(self.run(&["rev-parse", "--git-path", "info/exclude"])?);
+        let contents = fs::read_to_string(&exclude).unwrap_or_default();
+        if contents.lines().any(|line| line == pattern) {
+            return Ok(());
+        }
+        if let Some(parent) = exclude.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let separator = if contents.is_empty() || contents.ends_with('\n') { "" } else { "\n" };
+        fs::write(&exclude, format!("{}{}{}\n", contents, separator, pattern)).with_context(|| anyhow!("couldn't write '{}'", exclude.display()))
+    }
+
+    /// Commits all changes under `path` and nothing else the user may have staged;
+    /// returns false if there were none.
+    pub fn commit(&self, path: &str, message: &str) -> Result<bool> {
+        self.run(&["add", "--all", "--", path])?;
+        if self.succeeds(&["diff", "--cached", "--quiet", "--", path])? {
+            return Ok(false);
+        }
+        self.run(&["commit", "--quiet", "--only", "--message", message, "--", path])?;
+        Ok(true)
+    }
+}
+
+/// Keeps the database in a git repository: every write is committed with a message
+/// naming the items it changed, and `sync` exchanges commits with a remote.
+pub struct GitDatabase {
+    inner: Box<dyn Database>,
+    repo: GitRepo,
+    /// The database file or directory, relative to the top of the repository.
+    path: String
+}
+
+impl GitDatabase {
+    /// The repository is the one around the directory of a directory database, or
+    /// the directory around a database file. Without one, it is created there.
+    pub fn open(file_path: &str) -> Result<GitDatabase> {
+        let (dir, name) = match StorageFormat::from_path(file_path) {
+            StorageFormat::Directory => (PathBuf::from(file_path), None),
+            _ => {
+                let file = Path::new(file_path);
+                let name = file.file_name().and_then(|name| name.to_str()).ok_or_else(|| anyhow!("'{}' isn't a file name", file_path))?;
+                let dir = file.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+                (dir.to_path_buf(), Some(name))
+            }
+        };
+        let inner = ProjectsDatabase::new(file_path.to_owned()).database;
+        let repo = GitRepo::open_or_init(&dir)?;
+        let prefix = dir.canonicalize()?.strip_prefix(&repo.dir)?.to_path_buf();
+        let path = match name {
+            Some(name) => prefix.join(name),
+            None if prefix.as_os_str().is_empty() => PathBuf::from("."),
+            None => prefix
+        };
+        let path = path.to_str().ok_or_else(|| anyhow!("'{}' isn't a valid path", file_path))?.to_owned();
+        if path != "." {
+            repo.exclude(&format!("/{}", replica_path(&path)))?;
+        }
+        Ok(GitDatabase { inner, repo, path })
+    }
+
+    /// The database as it was in commit `rev`, read from a temporary worktree. A
+    /// commit without the database counts as an empty one.
+    fn read_at(&self, rev: &str) -> Result<DBState> {
+        let worktree = std::env::temp_dir().join(format!("cli-project-jira-{}-{}", process::id(), WORKTREES.fetch_add(1, Ordering::Relaxed)));
+        let worktree_path = worktree.to_str().ok_or_else(|| anyhow!("invalid temporary path"))?;
+        self.repo.run(&["worktree", "add", "--detach", "--force", worktree_path, rev])?;
+
+        let file_path = worktree.join(&self.path);
+        let state = if file_path.exists() {
+            let file_path = file_path.to_str().ok_or_else(|| anyhow!("invalid temporary path"))?;
+            let file_path = if self.path == "." { format!("{}/", file_path) } else { file_path.to_owned() };
+            ProjectsDatabase::new(file_path).read_db()
+        } else {
+            Ok(DBState::default())
+        };
+        self.repo.run(&["worktree", "remove", "--force", worktree_path])?;
+        state.with_context(|| anyhow!("couldn't read the database in commit {}", rev))
+    }
+}
+
+impl Database for GitDatabase {
+    fn read_db(&self) -> Result<DBState> {
+        self.inner.read_db()
+    }
+
+    fn write_db(&self, db_state: &DBState) -> Result<()> {
+        let before = self.inner.read_db().unwrap_or_default();
+        self.inner.write_db(db_state)?;
+        self.repo.commit(&self.path, &describe_changes(&before, db_state)?)?;
+        Ok(())
+    }
+
+    /// Fetches the current branch from `remote`, merges it and pushes the result.
+    /// Diverged copies of the database are merged item by item; values both sides
+    /// changed are taken from the copy of the item that was updated last.
+    fn sync(&self, remote: &str) -> Result<String> {
+        self.repo.commit(&self.path, "Update database")?;
+        let branch = self.repo.run(&["rev-parse", "--abbrev-ref", "HEAD"])?;
+        let push = || self.repo.run(&["push", "--quiet", remote, &format!("HEAD:refs/heads/{}", branch)]);
+
+        if self.repo.run(&["fetch", "--quiet", remote, &branch]).is_err() {
+            push()?;
+            return Ok(format!("pushed to {}/{}", remote, branch));
+        }
+        let theirs = self.repo.run(&["rev-parse", "FETCH_HEAD"])?;
+        if self.repo.succeeds(&["merge-base", "--is-ancestor", &theirs, "HEAD"])? {
+            push()?;
+            return Ok(format!("pushed to {}/{}", remote, branch));
+        }
+        if self.repo.succeeds(&["merge-base", "--is-ancestor", "HEAD", &theirs])? {
+            self.repo.run(&["merge", "--quiet", "--ff-only", &theirs])?;
+            return Ok(format!("updated from {}/{}", remote, branch));
+        }
+
+        let base = match self.repo.run(&["merge-base", "HEAD", &theirs]) {
+            Ok(base) => self.read_at(&base)?,
+            Err(_) => DBState::default()
+        };
+        let merge = Merge::new(&base, &self.read_at("HEAD")?, &self.read_at(&theirs)?)?;
+        let mut notes = vec![];
+        let state = merge_with_recency(merge, &mut notes)?;
+
+        // Other files merge as text; the database is then replaced by the semantic merge.
+        // A conflicted merge fails but leaves MERGE_HEAD; anything else is a real error.
+        if let Err(error) = self.repo.run(&["merge", "--quiet", "--no-ff", "--no-commit", "--allow-unrelated-histories", &theirs]) {
+            if !self.repo.succeeds(&["rev-parse", "-q", "--verify", "MERGE_HEAD"])? {
+                return Err(error.context(format!("couldn't merge {}/{}", remote, branch)));
+            }
+        }
+        self.inner.write_db(&state)?;
+        self.repo.run(&["add", "--all", "--", &self.path])?;
+        let unmerged = self.repo.run(&["diff", "--name-only", "--diff-filter=U"])?;
+        if !unmerged.is_empty() {
+            self.repo.run(&["merge", "--abort"])?;
+            return Err(anyhow!("couldn't merge files outside the database:\n{}", unmerged));
+        }
+
+        let mut message = format!("Merge {}/{}", remote, branch);
+        if !notes.is_empty() {
+            message.push_str(&format!("\n\n{}", notes.join("\n")));
+        }
+        self.repo.run(&["commit", "--quiet", "--allow-empty", "--message", &message])?;
+        push()?;
+
+        let mut report = format!("merged {}/{} and pushed", remote, branch);
+        if !notes.is_empty() {
+            report.push_str(&format!(":\n{}", notes.join("\n")));
+        }
+        Ok(report)
+    }
+}
+
+fn merge_with_recency(merge: Merge, resolved: &mut Vec<String>) -> Result<DBState> {
+    resolved.extend(merge.renumbered.iter().map(|renumbered| format!("- {}", renumbered)));
+    let sides = merge.conflicts.iter().map(|conflict| merge.most_recent(conflict)).collect::<Vec<_>>();
+    for (conflict, side) in merge.conflicts.iter().zip(&sides) {
+        let side = match side {
+            Side::Ours => "ours",
+            Side::Theirs => "theirs"
+        };
+        resolved.push(format!("- {}: kept {}", conflict, side));
+    }
+    let mut sides = sides.into_iter();
+    merge.resolve(|_| sides.next().ok_or_else(|| anyhow!("unexpected conflict")))
+}
+
+/// A commit message naming the created, updated and deleted items, e.g.
+/// `Update story 7 "Login": status, points`.
+pub fn describe_changes(before: &DBState, after: &DBState) -> Result<String> {
+    let mut changes = vec![];
+    let item_key = |id: ItemId| if after.epics.contains_key(&id) || after.stories.contains_key(&id) { after.item_key(id) } else { before.item_key(id) };
+    changes.extend(item_changes("epic", &before.epics, &after.epics, item_key, |epic| &epic.name)?);
+    changes.extend(item_changes("story", &before.stories, &after.stories, item_key, |story| &story.name)?);
+    changes.extend(item_changes("sprint", &before.sprints, &after.sprints, |id| id.to_string(), |sprint| &sprint.name)?);
+    if before.custom_fields != after.custom_fields {
+        changes.push("Update custom fields".to_owned());
+    }
+    if before.issue_types != after.issue_types {
+        changes.push("Update issue types".to_owned());
+    }
+
+    Ok(match changes.as_slice() {
+        [] => "Update database".to_owned(),
+        [change] => change.clone(),
+        changes => format!("Update {} items\n\n{}", changes.len(), changes.iter().map(|change| format!("- {}", change)).collect::<Vec<_>>().join("\n"))
+    })
+}
+
+fn item_changes<K: Ord + Copy, T: Serialize>(
+    kind: &str,
+    before: &BTreeMap<K, T>,
+    after: &BTreeMap<K, T>,
+    key: impl Fn(K) -> String,
+    name: fn(&T) -> &String
+) -> Result<Vec<String>> {
+    let mut changes = vec![];
+    for (id, item) in after {
+        let Some(old) = before.get(id) else {
+            changes.push(format!("Create {} {} \"{}\"", kind, key(*id), name(item)));
+            continue;
+        };
+        let (Value::Object(old), Value::Object(new)) = (serde_json::to_value(old)?, serde_json::to_value(item)?) else {
+            continue;
+        };
+        // A story list changes along with the stories created or deleted in it.
+        let mut fields = old.keys().chain(new.keys())
+            .filter(|field| !matches!(field.as_str(), "updated_at" | "stories"))
+            .filter(|field| old.get(*field) != new.get(*field))
+            .collect::<Vec<_>>();
+        fields.sort();
+        fields.dedup();
+        if !fields.is_empty() {
+            let fields = fields.into_iter().map(String::as_str).collect::<Vec<_>>().join(", ");
+            changes.push(format!("Update {} {} \"{}\": {}", kind, key(*id), name(item), fields));
+        }
+    }
+    for (id, item) in before {
+        if !after.contains_key(id) {
+            changes.push(format!("Delete {} {} \"{}\"", kind, key(*id), name(item)));
+        }
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::models::{item_replica, Epic, Status, Story};
+
+    fn git_database(path: &Path) -> ProjectsDatabase {
+        let database = GitDatabase::open(path.to_str().unwrap()).unwrap();
+        database.repo.run(&["config", "user.name", "Test"]).unwrap();
+        database.repo.run(&["config", "user.email", "test@example.com"]).unwrap();
+        if !path.exists() {
+            database.inner.write_db(&DBState::default()).unwrap();
+        }
+        ProjectsDatabase::open(path.to_str().unwrap().to_owned(), &Config { git: true, ..Config::default() }).unwrap()
+    }
+
+    fn log(dir: &Path) -> Vec<String> {
+        let repo = GitRepo { dir: dir.to_path_buf() };
+        repo.run(&["log", "--format=%s"]).unwrap().lines().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn writes_should_be_committed_with_descriptive_messages() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = git_database(&dir.path().join("db.json"));
+
+        let epic_id = db.create_epic(Epic::new("Auth".to_owned(), "".to_owned())).unwrap();
+        let story_id = db.create_story(Story::new("Login".to_owned(), "".to_owned()), epic_id).unwrap();
+        db.update_story_status(story_id, Status::InProgress).unwrap();
+        db.delete_story(epic_id, story_id).unwrap();
+
+        assert_eq!(log(dir.path()), vec![
+            "Delete story 2 \"Login\"",
+            "Update story 2 \"Login\": status",
+            "Create story 2 \"Login\"",
+            "Create epic 1 \"Auth\"",
+        ]);
+        assert_eq!(fs::read_to_string(dir.path().join("db.json.replica")).unwrap(), "0");
+        assert_eq!(GitRepo { dir: dir.path().to_path_buf() }.run(&["status", "--porcelain"]).unwrap(), "");
+    }
+
+    #[test]
+    fn writes_should_be_committed_to_the_enclosing_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = GitRepo::open_or_init(dir.path()).unwrap();
+        let db = git_database(&dir.path().join("project/db.json"));
+
+        db.create_epic(Epic::new("Auth".to_owned(), "".to_owned())).unwrap();
+
+        assert_eq!(dir.path().join("project/.git").exists(), false);
+        assert_eq!(repo.run(&["show", "--name-only", "--format=", "HEAD"]).unwrap(), "project/db.json");
+    }
+
+    #[test]
+    fn writes_should_leave_other_staged_files_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = git_database(&dir.path().join("db.json"));
+        fs::write(dir.path().join("notes.txt"), "draft\n").unwrap();
+        let repo = GitRepo { dir: dir.path().to_path_buf() };
+        repo.run(&["add", "notes.txt"]).unwrap();
+
+        db.create_epic(Epic::new("Auth".to_owned(), "".to_owned())).unwrap();
+
+        assert_eq!(repo.run(&["show", "--name-only", "--format=", "HEAD"]).unwrap(), "db.json");
+        assert_eq!(repo.run(&["diff", "--cached", "--name-only"]).unwrap(), "notes.txt");
+    }
+
+    #[test]
+    fn imports_should_be_committed_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = git_database(&dir.path().join("db.json"));
+        let contents = "epic_name,story_name,story_points\nAuth,Login,5\nAuth,Logout,\n";
+
+        let report = crate::csv::import_csv(&db, contents, &Default::default(), false).unwrap();
+
+        let replica = db.database.replica().unwrap();
+        assert_eq!(report.created_stories.len(), 2);
+        assert_eq!(report.created_epics.iter().all(|id| item_replica(*id) == replica), true);
+        assert_eq!(log(dir.path()).len(), 1);
+        assert_eq!(log(dir.path())[0], "Update 3 items");
+    }
+
+    #[test]
+    fn describe_changes_should_list_several_changes() {
+        let mut after = DBState::default();
+        after.epics.insert(1, Epic::new("Auth".to_owned(), "".to_owned()));
+        after.epics.insert(2, Epic::new("Billing".to_owned(), "".to_owned()));
+
+        assert_eq!(describe_changes(&DBState::default(), &after).unwrap(), "Update 2 items\n\n- Create epic 1 \"Auth\"\n- Create epic 2 \"Billing\"");
+        assert_eq!(describe_changes(&after, &after).unwrap(), "Update database");
+    }
+
+    #[test]
+    fn sync_should_merge_diverged_copies_item_by_item() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = dir.path().join("remote.git");
+        GitRepo { dir: dir.path().to_path_buf() }.run(&["init", "--quiet", "--bare", remote.to_str().unwrap()]).unwrap();
+
+        let ours = git_database(&dir.path().join("ours/db.json"));
+        let epic_id = ours.create_epic(Epic::new("Auth".to_owned(), "".to_owned())).unwrap();
+        let login = ours.create_story(Story::new("Login".to_owned(), "".to_owned()), epic_id).unwrap();
+        let logout = ours.create_story(Story::new("Logout".to_owned(), "".to_owned()), epic_id).unwrap();
+        GitRepo { dir: dir.path().join("ours") }.run(&["remote", "add", "origin", remote.to_str().unwrap()]).unwrap();
+        assert_eq!(ours.database.sync("origin").unwrap().starts_with("pushed to origin/"), true);
+
+        GitRepo { dir: dir.path().to_path_buf() }.run(&["clone", "--quiet", remote.to_str().unwrap(), "theirs"]).unwrap();
+        let theirs = git_database(&dir.path().join("theirs/db.json"));
+
+        ours.update_story_status(login, Status::Closed).unwrap();
+        ours.update_story_points(logout, Some(2)).unwrap();
+        theirs.update_story_points(login, Some(3)).unwrap();
+        theirs.update_story_points(logout, Some(5)).unwrap();
+
+        assert_eq!(theirs.database.sync("origin").unwrap().starts_with("pushed to"), true);
+        let report = ours.database.sync("origin").unwrap();
+        assert_eq!(report.ends_with(&format!("and pushed:\n- stories.{}.points: kept theirs", logout)), true);
+        assert_eq!(theirs.database.sync("origin").unwrap().starts_with("updated from"), true);
+
+        let state = theirs.read_db().unwrap();
+        assert_eq!(state, ours.read_db().unwrap());
+        assert_eq!(state.stories[&login].status, Status::Closed);
+        assert_eq!(state.stories[&login].points, Some(3));
+        assert_eq!(state.stories[&logout].points, Some(5));
+        assert_eq!(log(&dir.path().join("theirs"))[0].starts_with("Merge origin/"), true);
+    }
+
+    #[test]
+    fn sync_should_refuse_clones_that_claimed_the_same_replica() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = dir.path().join("remote.git");
+        GitRepo { dir: dir.path().to_path_buf() }.run(&["init", "--quiet", "--bare", remote.to_str().unwrap()]).unwrap();
+
+        let ours = git_database(&dir.path().join("ours/db.json"));
+        let ours_repo = GitRepo { dir: dir.path().join("ours") };
+        ours_repo.commit("db.json", "Add database").unwrap();
+        ours_repo.run(&["remote", "add", "origin", remote.to_str().unwrap()]).unwrap();
+        ours.database.sync("origin").unwrap();
+        GitRepo { dir: dir.path().to_path_buf() }.run(&["clone", "--quiet", remote.to_str().unwrap(), "theirs"]).unwrap();
+        let theirs = git_database(&dir.path().join("theirs/db.json"));
+
+        ours.create_epic(Epic::new("Auth".to_owned(), "".to_owned())).unwrap();
+        theirs.create_epic(Epic::new("Billing".to_owned(), "".to_owned())).unwrap();
+        theirs.database.sync("origin").unwrap();
+
+        let error = ours.database.sync("origin").unwrap_err();
+        assert_eq!(error.to_string(), "both copies claimed replica 0, so their ids may clash");
+        assert_eq!(log(&dir.path().join("ours"))[0], "Create epic 1 \"Auth\"");
+    }
+
+    #[test]
+    fn sync_should_fail_when_git_refuses_the_merge() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = dir.path().join("remote.git");
+        GitRepo { dir: dir.path().to_path_buf() }.run(&["init", "--quiet", "--bare", remote.to_str().unwrap()]).unwrap();
+
+        let ours = git_database(&dir.path().join("ours/db.json"));
+        let ours_repo = GitRepo { dir: dir.path().join("ours") };
+        fs::write(dir.path().join("ours/notes.txt"), "first\n").unwrap();
+        ours_repo.run(&["add", "notes.txt"]).unwrap();
+        ours_repo.run(&["commit", "--quiet", "--message", "Add notes"]).unwrap();
+        ours.create_epic(Epic::new("Auth".to_owned(), "".to_owned())).unwrap();
+        ours_repo.run(&["remote", "add", "origin", remote.to_str().unwrap()]).unwrap();
+        ours.database.sync("origin").unwrap();
+
+        GitRepo { dir: dir.path().to_path_buf() }.run(&["clone", "--quiet", remote.to_str().unwrap(), "theirs"]).unwrap();
+        let theirs = git_database(&dir.path().join("theirs/db.json"));
+        fs::write(dir.path().join("theirs/notes.txt"), "theirs\n").unwrap();
+        GitRepo { dir: dir.path().join("theirs") }.run(&["commit", "--quiet", "--all", "--message", "Edit notes"]).unwrap();
+        theirs.create_epic(Epic::new("Billing".to_owned(), "".to_owned())).unwrap();
+        theirs.database.sync("origin").unwrap();
+
+        ours.create_epic(Epic::new("Search".to_owned(), "".to_owned())).unwrap();
+        fs::write(dir.path().join("ours/notes.txt"), "not committed\n").unwrap();
+
+        assert_eq!(ours.database.sync("origin").is_err(), true);
+        assert_eq!(ours_repo.succeeds(&["rev-parse", "-q", "--verify", "MERGE_HEAD"]).unwrap(), false);
+        assert_eq!(log(&dir.path().join("ours"))[0].starts_with("Create epic"), true);
+    }
+}
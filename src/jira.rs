@@ -0,0 +1,434 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDate;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::db::ProjectsDatabase;
+use crate::models::{DBState, Epic, FieldDefinition, FieldKind, FieldTarget, FieldValue, IssueType, ItemId, Priority, Status, Story};
+
+/// How a Jira export maps onto epics and stories. Read from a JSON file whose
+/// `statuses` are added to the default ones:
+///
+/// ```json
+/// { "statuses": { "QA": "InProgress" }, "orphan_epic": "Unsorted" }
+/// ```
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct JiraMapping {
+    /// Jira status names, matched ignoring case. `load` lowercases them, so a name
+    /// from the file replaces the default one however it is written.
+    pub statuses: HashMap<String, Status>,
+    /// Custom field holding the epic of classic projects; newer ones use `parent`.
+    pub epic_link_field: String,
+    pub points_field: String,
+    /// Custom field the Jira key of every imported item is kept in.
+    pub key_field: String,
+    /// Epic collecting issues that don't belong to one; they are skipped when unset.
+    pub orphan_epic: Option<String>
+}
+
+impl Default for JiraMapping {
+    fn default() -> Self {
+        let statuses = [
+            ("To Do", Status::Open),
+            ("Open", Status::Open),
+            ("Backlog", Status::Open),
+            ("Selected for Development", Status::Open),
+            ("In Progress", Status::InProgress),
+            ("In Review", Status::InProgress),
+            ("Resolved", Status::Resolved),
+            ("Done", Status::Closed),
+            ("Closed", Status::Closed),
+        ];
+        JiraMapping {
+            statuses: statuses.into_iter().map(|(name, status)| (name.to_lowercase(), status)).collect(),
+            epic_link_field: "customfield_10014".to_owned(),
+            points_field: "customfield_10016".to_owned(),
+            key_field: "jira_key".to_owned(),
+            orphan_epic: None
+        }
+    }
+}
+
+impl JiraMapping {
+    pub fn load(file_path: &str) -> Result<JiraMapping> {
+        let contents = fs::read_to_string(file_path).with_context(|| anyhow!("couldn't read mapping file '{}'", file_path))?;
+        let mapping: JiraMapping = serde_json::from_str(&contents).with_context(|| anyhow!("invalid mapping file '{}'", file_path))?;
+
+        let mut statuses = JiraMapping::default().statuses;
+        statuses.extend(mapping.statuses.into_iter().map(|(name, status)| (name.to_lowercase(), status)));
+        Ok(JiraMapping { statuses, ..mapping })
+    }
+
+    fn status(&self, name: &str) -> Option<Status> {
+        self.statuses.get(&name.to_lowercase()).cloned()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JiraExport {
+    Search { issues: Vec<JiraIssue> },
+    Issues(Vec<JiraIssue>),
+}
+
+#[derive(Deserialize)]
+struct JiraIssue {
+    key: String,
+    fields: JiraFields
+}
+
+#[derive(Deserialize)]
+struct JiraFields {
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    description: Value,
+    issuetype: JiraIssueType,
+    status: JiraName,
+    priority: Option<JiraName>,
+    parent: Option<JiraParent>,
+    duedate: Option<String>,
+    #[serde(flatten)]
+    other: HashMap<String, Value>
+}
+
+#[derive(Deserialize)]
+struct JiraName {
+    name: String
+}
+
+#[derive(Deserialize)]
+struct JiraIssueType {
+    name: String,
+    #[serde(default)]
+    subtask: bool
+}
+
+#[derive(Deserialize)]
+struct JiraParent {
+    key: String
+}
+
+/// What a Jira import did, or would do on a dry run.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct JiraImportReport {
+    pub dry_run: bool,
+    pub epics: usize,
+    pub stories: usize,
+    /// Jira keys of the issues left out, with the reason.
+    pub skipped: Vec<(String, String)>,
+    /// Values without a mapping, e.g. `status 'QA', imported as OPEN`, with the
+    /// keys of the issues they were found on.
+    pub unmapped: BTreeMap<String, Vec<String>>
+}
+
+impl Display for JiraImportReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.dry_run {
+            writeln!(f, "dry run, nothing was saved")?;
+        }
+        writeln!(f, "imported {} epics and {} stories", self.epics, self.stories)?;
+        for (key, reason) in &self.skipped {
+            writeln!(f, "skipped {}: {}", key, reason)?;
+        }
+        for (value, keys) in &self.unmapped {
+            writeln!(f, "unmapped {}: {}", value, keys.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Imports the issues of a Jira Cloud JSON export, either a search result with an
+/// `issues` list or the list itself. Epics become epics and other issues stories of
+/// the epic in their `parent` or epic link field. The Jira key of every item is
+/// stored in the `key_field` custom field, and issues whose key is already there
+/// are skipped, so running the import again only adds new issues.
+pub fn import_jira(db: &ProjectsDatabase, contents: &str, mapping: &JiraMapping, dry_run: bool) -> Result<JiraImportReport> {
+    db.batch(dry_run, |db| import_issues(db, contents, mapping)).map(|report| JiraImportReport { dry_run, ..report })
+}
+
+fn import_issues(db: &ProjectsDatabase, contents: &str, mapping: &JiraMapping) -> Result<JiraImportReport> {
+    let issues = match serde_json::from_str(contents).context("invalid Jira export")? {
+        JiraExport::Search { issues } | JiraExport::Issues(issues) => issues
+    };
+    let mut report = JiraImportReport::default();
+    let mut imported = imported_keys(db, mapping)?;
+    let db_state = db.read_db()?;
+
+    let (epics, stories): (Vec<_>, Vec<_>) = issues
+        .into_iter()
+        .sorted_by_key(|issue| key_order(&issue.key))
+        .partition(|issue| issue.fields.issuetype.name.eq_ignore_ascii_case("epic"));
+
+    for issue in epics {
+        if let Some(id) = imported.get(&issue.key) {
+            report.skipped.push((issue.key.clone(), format!("already imported as {}", db_state.item_key(*id))));
+            continue;
+        }
+        let epic = Epic {
+            status: issue_status(&issue, mapping, &mut report),
+            ..Epic::new(issue.fields.summary.clone(), description_text(&issue.fields.description))
+        };
+        let epic_id = db.create_epic(epic)?;
+        db.set_epic_field(epic_id, &mapping.key_field, &issue.key)?;
+        imported.insert(issue.key, epic_id);
+        report.epics += 1;
+    }
+
+    for issue in stories {
+        if let Some(id) = imported.get(&issue.key) {
+            report.skipped.push((issue.key.clone(), format!("already imported as {}", db_state.item_key(*id))));
+            continue;
+        }
+        if issue.fields.issuetype.subtask {
+            report.skipped.push((issue.key.clone(), "sub-tasks aren't supported".to_owned()));
+            continue;
+        }
+
+        let epic_key = issue.fields.parent
+            .as_ref()
+            .map(|parent| parent.key.clone())
+            .or_else(|| issue.fields.other.get(&mapping.epic_link_field).and_then(Value::as_str).map(str::to_owned));
+        let epic_id = match (epic_key, &mapping.orphan_epic) {
+            (Some(epic_key), _) => match imported.get(&epic_key) {
+                Some(epic_id) => *epic_id,
+                None => {
+                    report.skipped.push((issue.key.clone(), format!("epic {} isn't part of the export", epic_key)));
+                    continue;
+                }
+            },
+            (None, Some(orphan_epic)) => orphan_epic_id(db, orphan_epic)?,
+            (None, None) => {
+                report.skipped.push((issue.key.clone(), "no epic".to_owned()));
+                continue;
+            }
+        };
+
+        let story = Story {
+            status: issue_status(&issue, mapping, &mut report),
+            issue_type: issue_type(&db_state, &issue, &mut report),
+            priority: issue_priority(&issue, &mut report),
+            points: issue.fields.other.get(&mapping.points_field).and_then(Value::as_f64).map(|points| points.round() as u32),
+            due_date: issue.fields.duedate.as_deref().and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()),
+            ..Story::new(issue.fields.summary.clone(), description_text(&issue.fields.description))
+        };
+        let story_id = db.create_story(story, epic_id)?;
+        db.set_story_field(story_id, &mapping.key_field, &issue.key)?;
+        imported.insert(issue.key, story_id);
+        report.stories += 1;
+    }
+
+    Ok(report)
+}
+
+/// Ids of the items imported before, by Jira key. Creates the key field on the
+/// first import.
+fn imported_keys(db: &ProjectsDatabase, mapping: &JiraMapping) -> Result<HashMap<String, ItemId>> {
+    let db_state = db.read_db()?;
+    match db_state.field_definition(&mapping.key_field) {
+        None => {
+            db.create_field_definition(FieldDefinition::new(mapping.key_field.clone(), FieldKind::Text, FieldTarget::Both))?;
+            return Ok(HashMap::new());
+        }
+        Some(definition) if definition.kind != FieldKind::Text || definition.target != FieldTarget::Both => {
+            return Err(anyhow!("field '{}' must be a text field of epics and stories", mapping.key_field));
+        }
+        Some(_) => {}
+    }
+
+    let key = |fields: &BTreeMap<String, FieldValue>| match fields.get(&mapping.key_field) {
+        Some(FieldValue::Text(key)) => Some(key.clone()),
+        _ => None
+    };
+    let epics = db_state.epics.iter().filter_map(|(id, epic)| key(&epic.fields).map(|key| (key, *id)));
+    let stories = db_state.stories.iter().filter_map(|(id, story)| key(&story.fields).map(|key| (key, *id)));
+    Ok(epics.chain(stories).collect())
+}
+
+fn orphan_epic_id(db: &ProjectsDatabase, name: &str) -> Result<ItemId> {
+    let existing = db.read_db()?.epics
+        .into_iter()
+        .filter(|(_, epic)| epic.name == name)
+        .map(|(id, _)| id)
+        .min();
+    match existing {
+        Some(epic_id) => Ok(epic_id),
+        None => db.create_epic(Epic::new(name.to_owned(), "Imported Jira issues without an epic".to_owned()))
+    }
+}
+
+/// Orders `PROJ-9` before `PROJ-10`.
+fn key_order(key: &str) -> (String, u64) {
+    match key.rsplit_once('-') {
+        Some((project, number)) => (project.to_owned(), number.parse().unwrap_or(u64::MAX)),
+        None => (key.to_owned(), u64::MAX)
+    }
+}
+
+fn issue_status(issue: &JiraIssue, mapping: &JiraMapping, report: &mut JiraImportReport) -> Status {
+    let name = &issue.fields.status.name;
+    mapping.status(name).unwrap_or_else(|| {
+        unmapped(report, format!("status '{}', imported as {}", name, Status::Open), &issue.key);
+        Status::Open
+    })
+}
+
+fn issue_type(db_state: &DBState, issue: &JiraIssue, report: &mut JiraImportReport) -> IssueType {
+    let name = &issue.fields.issuetype.name;
+    db_state.issue_type(name).unwrap_or_else(|_| {
+        unmapped(report, format!("issue type '{}', imported as {}", name, IssueType::default()), &issue.key);
+        IssueType::default()
+    })
+}
+
+fn issue_priority(issue: &JiraIssue, report: &mut JiraImportReport) -> Priority {
+    let Some(priority) = &issue.fields.priority else {
+        return Priority::default();
+    };
+    priority.name.parse().unwrap_or_else(|_| {
+        unmapped(report, format!("priority '{}', imported as {}", priority.name, Priority::default()), &issue.key);
+        Priority::default()
+    })
+}
+
+fn unmapped(report: &mut JiraImportReport, value: String, key: &str) {
+    report.unmapped.entry(value).or_default().push(key.to_owned());
+}
+
+/// Plain text of a description, which newer exports give as an Atlassian document:
+/// a tree of nodes with the text in `text` leaves.
+fn description_text(description: &Value) -> String {
+    fn collect(node: &Value, paragraphs: &mut Vec<String>) {
+        if let Some(text) = node.get("text").and_then(Value::as_str) {
+            if let Some(paragraph) = paragraphs.last_mut() {
+                paragraph.push_str(text);
+            }
+        }
+        if let Some(content) = node.get("content").and_then(Value::as_array) {
+            if matches!(node.get("type").and_then(Value::as_str), Some("paragraph" | "heading" | "codeBlock")) {
+                paragraphs.push(String::new());
+            }
+            content.iter().for_each(|child| collect(child, paragraphs));
+        }
+    }
+
+    match description {
+        Value::String(text) => text.clone(),
+        Value::Object(_) => {
+            let mut paragraphs = vec![String::new()];
+            collect(description, &mut paragraphs);
+            paragraphs.into_iter().filter(|paragraph| !paragraph.is_empty()).join("\n")
+        }
+        _ => String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_utils::MockDb;
+
+    const EXPORT: &str = r#"{
+        "issues": [
+            { "key": "PROJ-10", "fields": {
+                "summary": "Login", "issuetype": { "name": "Story" }, "status": { "name": "in progress" },
+                "priority": { "name": "High" }, "parent": { "key": "PROJ-1" }, "customfield_10016": 3.0,
+                "duedate": "2024-05-01",
+                "description": { "type": "doc", "content": [
+                    { "type": "paragraph", "content": [{ "type": "text", "text": "Use " }, { "type": "text", "text": "SSO" }] },
+                    { "type": "paragraph", "content": [{ "type": "text", "text": "No passwords" }] }
+                ] }
+            } },
+            { "key": "PROJ-1", "fields": {
+                "summary": "Auth", "description": "Sign in", "issuetype": { "name": "Epic" }, "status": { "name": "To Do" }
+            } },
+            { "key": "PROJ-2", "fields": {
+                "summary": "Crash", "issuetype": { "name": "Bug" }, "status": { "name": "QA" },
+                "priority": { "name": "Blocker" }, "customfield_10014": "PROJ-1"
+            } },
+            { "key": "PROJ-3", "fields": {
+                "summary": "Step", "issuetype": { "name": "Sub-task", "subtask": true }, "status": { "name": "Done" },
+                "parent": { "key": "PROJ-10" }
+            } },
+            { "key": "PROJ-4", "fields": { "summary": "Loose", "issuetype": { "name": "Task" }, "status": { "name": "Done" } } },
+            { "key": "PROJ-5", "fields": {
+                "summary": "Elsewhere", "issuetype": { "name": "Improvement" }, "status": { "name": "Done" },
+                "parent": { "key": "OTHER-1" }
+            } }
+        ]
+    }"#;
+
+    #[test]
+    fn import_jira_should_map_epics_stories_and_statuses() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+
+        let report = import_jira(&db, EXPORT, &JiraMapping::default(), false).unwrap();
+
+        assert_eq!(report.to_string(), "imported 1 epics and 2 stories\n\
+            skipped PROJ-3: sub-tasks aren't supported\n\
+            skipped PROJ-4: no epic\n\
+            skipped PROJ-5: epic OTHER-1 isn't part of the export\n\
+            unmapped priority 'Blocker', imported as MEDIUM: PROJ-2\n\
+            unmapped status 'QA', imported as OPEN: PROJ-2\n");
+
+        let db_state = db.read_db().unwrap();
+        let epic = &db_state.epics[&1];
+        assert_eq!((epic.name.as_str(), epic.description.as_str(), &epic.stories), ("Auth", "Sign in", &vec![2, 3]));
+        assert_eq!(epic.fields["jira_key"], FieldValue::Text("PROJ-1".to_owned()));
+
+        let bug = &db_state.stories[&2];
+        assert_eq!((bug.issue_type.clone(), bug.status.clone(), bug.priority), (IssueType::new("Bug"), Status::Open, Priority::Medium));
+        assert_eq!(bug.fields["jira_key"], FieldValue::Text("PROJ-2".to_owned()));
+
+        let story = &db_state.stories[&3];
+        assert_eq!(story.description, "Use SSO\nNo passwords");
+        assert_eq!((story.status.clone(), story.priority, story.points), (Status::InProgress, Priority::High, Some(3)));
+        assert_eq!(story.due_date, NaiveDate::from_ymd_opt(2024, 5, 1));
+    }
+
+    #[test]
+    fn import_jira_should_skip_issues_imported_before() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        db.set_key_prefix(Some("APP".to_owned())).unwrap();
+        import_jira(&db, EXPORT, &JiraMapping::default(), false).unwrap();
+
+        let mapping = JiraMapping { orphan_epic: Some("Unsorted".to_owned()), ..JiraMapping::default() };
+        let report = import_jira(&db, EXPORT, &mapping, false).unwrap();
+
+        assert_eq!((report.epics, report.stories), (0, 1));
+        assert_eq!(report.skipped[0], ("PROJ-1".to_owned(), "already imported as APP-1".to_owned()));
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.epics[&4].name, "Unsorted");
+        assert_eq!(db_state.epics[&4].stories, vec![5]);
+    }
+
+    #[test]
+    fn import_jira_should_only_preview_on_dry_run() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+
+        let report = import_jira(&db, EXPORT, &JiraMapping::default(), true).unwrap();
+
+        assert_eq!((report.dry_run, report.epics, report.stories), (true, 1, 2));
+        assert_eq!(db.read_db().unwrap().custom_fields.len(), 0);
+        assert_eq!(import_jira(&db, r#"{ "total": 0 }"#, &JiraMapping::default(), false).is_err(), true);
+    }
+
+    #[test]
+    fn load_should_add_statuses_to_the_defaults() {
+        let tmpfile = tempfile::NamedTempFile::new().unwrap();
+        fs::write(tmpfile.path(), r#"{ "statuses": { "QA": "InProgress", "Done": "Resolved" }, "key_field": "jira" }"#).unwrap();
+
+        let mapping = JiraMapping::load(tmpfile.path().to_str().unwrap()).unwrap();
+
+        assert_eq!(mapping.status("qa"), Some(Status::InProgress));
+        assert_eq!(mapping.status("done"), Some(Status::Resolved));
+        assert_eq!(mapping.status("Closed"), Some(Status::Closed));
+        assert_eq!(mapping.key_field, "jira");
+        assert_eq!(mapping.epic_link_field, "customfield_10014");
+        assert_eq!(JiraMapping::load("INVALID_PATH").is_err(), true);
+    }
+}
@@ -0,0 +1,554 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use anyhow::{anyhow, Context, Result};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use crate::models::Action;
+use crate::ui::Page;
+
+/// What the help page says about an action.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ActionHelp {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The keymap command running the action, on every page it is bound on.
+    pub command: Option<Command>,
+    /// Input outside the keymap that runs the action, like `:id:` or `ctrl+b`.
+    pub input: Option<&'static str>,
+    /// Pages `input` works on; empty when it works everywhere.
+    pub pages: &'static [PageKind]
+}
+
+impl ActionHelp {
+    fn command(name: &'static str, description: &'static str, command: Command) -> Self {
+        ActionHelp { name, description, command: Some(command), input: None, pages: &[] }
+    }
+
+    fn input(name: &'static str, description: &'static str, input: &'static str, pages: &'static [PageKind]) -> Self {
+        ActionHelp { name, description, command: None, input: Some(input), pages }
+    }
+}
+
+impl Action {
+    pub fn help(&self) -> ActionHelp {
+        match self {
+            Action::NavigateToEpicDetail { .. } => ActionHelp::input(
+                "Open epic", "Show an epic with its stories", ":id: or enter", &[PageKind::Home]
+            ),
+            Action::NavigateToStoryDetail { .. } => ActionHelp::input(
+                "Open story", "Show a story with its details and custom fields", ":id: or enter",
+                &[PageKind::EpicDetail, PageKind::SearchResults, PageKind::SprintPlanning, PageKind::Board]
+            ),
+            Action::GoToItem { .. } => ActionHelp::input(
+                "Go to item", "Open any epic or story by key, like PROJ-42, or id", "g :key: or #:key:", &[]
+            ),
+            Action::NavigateToPreviousPage => ActionHelp {
+                input: Some("ctrl+b"),
+                ..ActionHelp::command("Back", "Return to the previous page", Command::Previous)
+            },
+            Action::NavigateForward => ActionHelp::input(
+                "Forward", "Reopen the page last left by going back", "ctrl+f", &[]
+            ),
+            Action::NavigateToBreadcrumb { .. } => ActionHelp::input(
+                "Jump to breadcrumb", "Go back to a page of the breadcrumb line, 1 being home", "^n", &[]
+            ),
+            Action::CreateEpic => ActionHelp::command("Create epic", "Add an epic", Command::CreateEpic),
+            Action::UpdateEpicStatus { .. } => ActionHelp::command(
+                "Update epic", "Change the status of the epic", Command::UpdateEpicStatus
+            ),
+            Action::DeleteEpic { .. } => ActionHelp::command(
+                "Delete epic", "Delete the epic and all of its stories", Command::DeleteEpic
+            ),
+            Action::UpdateEpicField { .. } => ActionHelp::command(
+                "Set epic field", "Set a custom field of the epic", Command::SetEpicField
+            ),
+            Action::CreateStory { .. } => ActionHelp::command("Create story", "Add a story to the epic", Command::CreateStory),
+            Action::UpdateStoryStatus { .. } => ActionHelp::command(
+                "Update story", "Change the status of the story", Command::UpdateStoryStatus
+            ),
+            Action::DeleteStory { .. } => ActionHelp::command("Delete story", "Delete the story", Command::DeleteStory),
+            Action::UpdateStoryPoints { .. } => ActionHelp::command(
+                "Estimate points", "Set the story points of the story", Command::EstimatePoints
+            ),
+            Action::UpdateStoryPriority { .. } => ActionHelp::command(
+                "Set priority", "Set the priority of the story", Command::SetPriority
+            ),
+            Action::UpdateStoryDueDate { .. } => ActionHelp::command(
+                "Set due date", "Set or clear the due date of the story", Command::SetDueDate
+            ),
+            Action::UpdateStoryField { .. } => ActionHelp::command(
+                "Set story field", "Set a custom field of the story", Command::SetStoryField
+            ),
+            Action::CreateFieldDefinition => ActionHelp::command(
+                "Define field", "Add a custom field to epics or stories", Command::DefineField
+            ),
+            Action::SearchStories => ActionHelp::command(
+                "Search stories", "Find stories by text, type, status or custom field", Command::SearchStories
+            ),
+            Action::NavigateToBoard { .. } => ActionHelp::command(
+                "Board", "Show stories in one column per status", Command::Board
+            ),
+            Action::SetStoryStatus { .. } => ActionHelp::input(
+                "Move card", "Move the selected story to the previous or next status", "< or >", &[PageKind::Board]
+            ),
+            Action::NavigateToSprints => ActionHelp::command("Sprints", "List the sprints", Command::Sprints),
+            Action::NavigateToSprintPlanning { .. } => ActionHelp::input(
+                "Plan sprint", "Show a sprint with its stories and the backlog", ":id:", &[PageKind::Sprints]
+            ),
+            Action::CreateSprint => ActionHelp::command("Create sprint", "Add a sprint", Command::CreateSprint),
+            Action::StartSprint { .. } => ActionHelp::command(
+                "Start sprint", "Start a planned sprint", Command::StartSprint
+            ),
+            Action::CloseSprint { .. } => ActionHelp::command(
+                "Close sprint", "Close the sprint, moving unfinished stories to the backlog or another sprint", Command::CloseSprint
+            ),
+            Action::AddStoryToSprint { .. } => ActionHelp::input(
+                "Add to sprint", "Add a backlog story to the sprint", "a :id:", &[PageKind::SprintPlanning]
+            ),
+            Action::RemoveStoryFromSprint { .. } => ActionHelp::input(
+                "Remove from sprint", "Move a story of the sprint back to the backlog", "r :id:", &[PageKind::SprintPlanning]
+            ),
+            Action::ShowHelp => ActionHelp::command("Help", "Show this page", Command::Help),
+            Action::Exit => ActionHelp {
+                input: Some("ctrl+c"),
+                ..ActionHelp::command("Quit", "Save the session and exit", Command::Quit)
+            },
+        }
+    }
+}
+
+/// The kinds of pages key bindings are configured for.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum PageKind {
+    Home,
+    EpicDetail,
+    StoryDetail,
+    SearchResults,
+    Sprints,
+    SprintPlanning,
+    Board,
+    Help,
+}
+
+impl PageKind {
+    pub fn all() -> [PageKind; 8] {
+        [
+            PageKind::Home,
+            PageKind::EpicDetail,
+            PageKind::StoryDetail,
+            PageKind::SearchResults,
+            PageKind::Sprints,
+            PageKind::SprintPlanning,
+            PageKind::Board,
+            PageKind::Help,
+        ]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PageKind::Home => "home",
+            PageKind::EpicDetail => "epic_detail",
+            PageKind::StoryDetail => "story_detail",
+            PageKind::SearchResults => "search_results",
+            PageKind::Sprints => "sprints",
+            PageKind::SprintPlanning => "sprint_planning",
+            PageKind::Board => "board",
+            PageKind::Help => "help",
+        }
+    }
+
+    /// Built-in bindings, in the order they are listed in the page footer.
+    fn default_bindings(self) -> Vec<(&'static str, Command)> {
+        let mut bindings = match self {
+            PageKind::Home => vec![
+                ("q", Command::Quit),
+                ("c", Command::CreateEpic),
+                ("s", Command::SearchStories),
+                ("b", Command::Board),
+                ("r", Command::Sprints),
+                ("f", Command::DefineField),
+            ],
+            PageKind::EpicDetail => vec![
+                ("p", Command::Previous),
+                ("u", Command::UpdateEpicStatus),
+                ("d", Command::DeleteEpic),
+                ("f", Command::SetEpicField),
+                ("b", Command::Board),
+                ("c", Command::CreateStory),
+            ],
+            PageKind::StoryDetail => vec![
+                ("p", Command::Previous),
+                ("u", Command::UpdateStoryStatus),
+                ("d", Command::DeleteStory),
+                ("e", Command::EstimatePoints),
+                ("r", Command::SetPriority),
+                ("t", Command::SetDueDate),
+                ("f", Command::SetStoryField),
+            ],
+            PageKind::SearchResults => vec![("p", Command::Previous)],
+            PageKind::Sprints => vec![("p", Command::Previous), ("c", Command::CreateSprint)],
+            PageKind::SprintPlanning => vec![
+                ("p", Command::Previous),
+                ("s", Command::StartSprint),
+                ("x", Command::CloseSprint),
+            ],
+            PageKind::Board => vec![("p", Command::Previous)],
+            PageKind::Help => vec![("p", Command::Previous)],
+        };
+        bindings.push(("?", Command::Help));
+        bindings
+    }
+
+    /// Keys the page handles itself before looking at the keymap.
+    fn reserved_keys(self) -> &'static [&'static str] {
+        match self {
+            PageKind::Home | PageKind::EpicDetail => &["o", "O", "[", "]"],
+            PageKind::Board => &["h", "j", "k", "l", "<", ">"],
+            _ => &[]
+        }
+    }
+}
+
+/// A bindable command. Pages turn it into an `Action` for the item they show, see
+/// `Page::command_action`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Command {
+    Quit,
+    Previous,
+    CreateEpic,
+    SearchStories,
+    DefineField,
+    Sprints,
+    Board,
+    UpdateEpicStatus,
+    DeleteEpic,
+    SetEpicField,
+    CreateStory,
+    UpdateStoryStatus,
+    DeleteStory,
+    EstimatePoints,
+    SetPriority,
+    SetDueDate,
+    SetStoryField,
+    CreateSprint,
+    StartSprint,
+    CloseSprint,
+    Help,
+}
+
+impl Command {
+    pub fn name(self) -> &'static str {
+        match self {
+            Command::Quit => "quit",
+            Command::Previous => "previous",
+            Command::CreateEpic => "create_epic",
+            Command::SearchStories => "search_stories",
+            Command::DefineField => "define_field",
+            Command::Sprints => "sprints",
+            Command::Board => "board",
+            Command::UpdateEpicStatus => "update_epic_status",
+            Command::DeleteEpic => "delete_epic",
+            Command::SetEpicField => "set_epic_field",
+            Command::CreateStory => "create_story",
+            Command::UpdateStoryStatus => "update_story_status",
+            Command::DeleteStory => "delete_story",
+            Command::EstimatePoints => "estimate_points",
+            Command::SetPriority => "set_priority",
+            Command::SetDueDate => "set_due_date",
+            Command::SetStoryField => "set_story_field",
+            Command::CreateSprint => "create_sprint",
+            Command::StartSprint => "start_sprint",
+            Command::CloseSprint => "close_sprint",
+            Command::Help => "help",
+        }
+    }
+
+    /// Footer text of the command.
+    pub fn label(self) -> &'static str {
+        match self {
+            Command::Quit => "quit",
+            Command::Previous => "previous",
+            Command::CreateEpic => "create epic",
+            Command::SearchStories => "search stories",
+            Command::DefineField => "define custom field",
+            Command::Sprints => "sprints",
+            Command::Board => "board",
+            Command::UpdateEpicStatus => "update epic",
+            Command::DeleteEpic => "delete epic",
+            Command::SetEpicField | Command::SetStoryField => "set field",
+            Command::CreateStory => "create story",
+            Command::UpdateStoryStatus => "update story",
+            Command::DeleteStory => "delete story",
+            Command::EstimatePoints => "estimate points",
+            Command::SetPriority => "set priority",
+            Command::SetDueDate => "set due date",
+            Command::CreateSprint => "create sprint",
+            Command::StartSprint => "start sprint",
+            Command::CloseSprint => "close sprint",
+            Command::Help => "help",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Binding {
+    pub keys: String,
+    pub command: Command
+}
+
+/// Overrides read from the keymap file: the key sequence of a command, per page.
+///
+/// ```json
+/// { "home": { "create_epic": "n" }, "story_detail": { "set_priority": "P" } }
+/// ```
+pub type KeymapOverrides = HashMap<PageKind, HashMap<Command, String>>;
+
+/// Key sequences bound to commands on each page. Sequences are typed into the
+/// command line and run as soon as they match a binding.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Keymap {
+    pages: HashMap<PageKind, Vec<Binding>>
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let pages = PageKind::all()
+            .into_iter()
+            .map(|kind| {
+                let bindings = kind
+                    .default_bindings()
+                    .into_iter()
+                    .map(|(keys, command)| Binding { keys: keys.to_owned(), command })
+                    .collect();
+                (kind, bindings)
+            })
+            .collect();
+        Keymap { pages }
+    }
+}
+
+impl Keymap {
+    /// Reads the keymap file; a missing file gives the default bindings.
+    pub fn load(file_path: &str) -> Result<Keymap> {
+        let contents = match fs::read_to_string(file_path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Keymap::default()),
+            Err(error) => return Err(error).with_context(|| anyhow!("couldn't read keymap file '{}'", file_path))
+        };
+
+        let overrides: KeymapOverrides = serde_json::from_str(&contents)
+            .with_context(|| anyhow!("invalid keymap file '{}'", file_path))?;
+        Keymap::with_overrides(overrides).with_context(|| anyhow!("invalid keymap file '{}'", file_path))
+    }
+
+    /// The default bindings with the commands in `overrides` moved to new keys.
+    ///
+    /// Fails listing every problem when a command isn't available on the page, a key
+    /// is reserved, two commands share a key or one key sequence is the start of
+    /// another, which would make the longer one unreachable.
+    pub fn with_overrides(overrides: KeymapOverrides) -> Result<Keymap> {
+        let mut keymap = Keymap::default();
+        let mut errors = vec![];
+
+        for (kind, commands) in overrides.into_iter().sorted_by_key(|(kind, _)| kind.name()) {
+            let bindings = keymap.pages.entry(kind).or_default();
+            for (command, keys) in commands.into_iter().sorted_by_key(|(command, _)| command.name()) {
+                match bindings.iter_mut().find(|binding| binding.command == command) {
+                    Some(binding) => binding.keys = keys,
+                    None => errors.push(format!("'{}' isn't available on the {} page", command.name(), kind.name()))
+                }
+            }
+        }
+
+        for kind in PageKind::all() {
+            errors.extend(keymap.conflicts(kind));
+        }
+
+        if errors.is_empty() {
+            Ok(keymap)
+        } else {
+            Err(anyhow!("{}", errors.join("; ")))
+        }
+    }
+
+    fn conflicts(&self, kind: PageKind) -> Vec<String> {
+        let bindings = self.bindings(kind);
+        let mut errors = vec![];
+
+        for binding in bindings {
+            let keys = &binding.keys;
+            // Keys that start like `g :key:` or a page key, or are the start of one,
+            // would never reach the binding.
+            let reserved = keys.is_empty()
+                || keys.starts_with(|c: char| c.is_ascii_digit() || c.is_whitespace() || c == '#' || c == '^')
+                || ["g"].iter().chain(kind.reserved_keys()).any(|reserved| keys.starts_with(reserved) || reserved.starts_with(keys.as_str()));
+            if reserved {
+                errors.push(format!("'{}' can't be bound to '{}' on the {} page", keys, binding.command.name(), kind.name()));
+            }
+        }
+
+        for (first, second) in bindings.iter().tuple_combinations() {
+            if first.keys == second.keys {
+                errors.push(format!(
+                    "'{}' is bound to both '{}' and '{}' on the {} page",
+                    first.keys, first.command.name(), second.command.name(), kind.name()
+                ));
+            } else if let Some((short, long)) = prefix_pair(first, second) {
+                errors.push(format!(
+                    "'{}' ('{}') hides '{}' ('{}') on the {} page",
+                    short.keys, short.command.name(), long.keys, long.command.name(), kind.name()
+                ));
+            }
+        }
+        errors
+    }
+
+    /// Bindings of a page, in footer order.
+    pub fn bindings(&self, kind: PageKind) -> &[Binding] {
+        self.pages.get(&kind).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn command(&self, kind: PageKind, keys: &str) -> Option<Command> {
+        self.bindings(kind)
+            .iter()
+            .find(|binding| binding.keys == keys)
+            .map(|binding| binding.command)
+    }
+
+    /// The action for typed input: a bound key sequence, or else whatever the page
+    /// makes of it, like an id.
+    pub fn action(&self, page: &dyn Page, input: &str) -> Result<Option<Action>> {
+        if let Some(command) = self.command(page.kind(), input) {
+            if let Some(action) = page.command_action(command)? {
+                return Ok(Some(action));
+            }
+        }
+        page.handle_input(input)
+    }
+}
+
+fn prefix_pair<'a>(first: &'a Binding, second: &'a Binding) -> Option<(&'a Binding, &'a Binding)> {
+    if second.keys.starts_with(&first.keys) {
+        Some((first, second))
+    } else if first.keys.starts_with(&second.keys) {
+        Some((second, first))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use super::*;
+
+    fn overrides(kind: PageKind, bindings: &[(Command, &str)]) -> KeymapOverrides {
+        let commands = bindings.iter().map(|(command, keys)| (*command, keys.to_string())).collect();
+        HashMap::from([(kind, commands)])
+    }
+
+    #[test]
+    fn action_all_should_list_every_action_once() {
+        // No wildcard arm, so a new action doesn't compile until it is numbered here,
+        // which is the reminder to add it to `Action::all` for the help page.
+        let number = |action: &Action| match action {
+            Action::NavigateToEpicDetail { .. } => 0,
+            Action::NavigateToStoryDetail { .. } => 1,
+            Action::NavigateToPreviousPage => 2,
+            Action::NavigateForward => 3,
+            Action::NavigateToBreadcrumb { .. } => 4,
+            Action::CreateEpic => 5,
+            Action::UpdateEpicStatus { .. } => 6,
+            Action::DeleteEpic { .. } => 7,
+            Action::CreateStory { .. } => 8,
+            Action::UpdateStoryStatus { .. } => 9,
+            Action::DeleteStory { .. } => 10,
+            Action::SearchStories => 11,
+            Action::CreateFieldDefinition => 12,
+            Action::UpdateEpicField { .. } => 13,
+            Action::UpdateStoryField { .. } => 14,
+            Action::UpdateStoryPoints { .. } => 15,
+            Action::UpdateStoryPriority { .. } => 16,
+            Action::UpdateStoryDueDate { .. } => 17,
+            Action::NavigateToSprints => 18,
+            Action::NavigateToSprintPlanning { .. } => 19,
+            Action::CreateSprint => 20,
+            Action::StartSprint { .. } => 21,
+            Action::CloseSprint { .. } => 22,
+            Action::AddStoryToSprint { .. } => 23,
+            Action::RemoveStoryFromSprint { .. } => 24,
+            Action::NavigateToBoard { .. } => 25,
+            Action::SetStoryStatus { .. } => 26,
+            Action::GoToItem { .. } => 27,
+            Action::ShowHelp => 28,
+            Action::Exit => 29,
+        };
+
+        let numbers = Action::all().iter().map(number).sorted().collect::<Vec<_>>();
+        assert_eq!(numbers, (0..30).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn default_keymap_should_have_no_conflicts() {
+        let keymap = Keymap::default();
+
+        for kind in PageKind::all() {
+            assert_eq!(keymap.conflicts(kind), Vec::<String>::new());
+        }
+        assert_eq!(keymap.command(PageKind::Home, "q"), Some(Command::Quit));
+        assert_eq!(keymap.command(PageKind::EpicDetail, "q"), None);
+    }
+
+    #[test]
+    fn overrides_should_move_commands_to_new_keys() {
+        let keymap = Keymap::with_overrides(overrides(PageKind::Home, &[(Command::CreateEpic, "n"), (Command::Board, "vb")])).unwrap();
+
+        assert_eq!(keymap.command(PageKind::Home, "n"), Some(Command::CreateEpic));
+        assert_eq!(keymap.command(PageKind::Home, "vb"), Some(Command::Board));
+        assert_eq!(keymap.command(PageKind::Home, "c"), None);
+        assert_eq!(keymap.bindings(PageKind::Home)[1], Binding { keys: "n".to_owned(), command: Command::CreateEpic });
+    }
+
+    #[test]
+    fn overrides_should_detect_conflicts() {
+        let duplicate = Keymap::with_overrides(overrides(PageKind::Home, &[(Command::CreateEpic, "q")]));
+        assert_eq!(duplicate.unwrap_err().to_string(), "'q' is bound to both 'quit' and 'create_epic' on the home page");
+
+        let prefix = Keymap::with_overrides(overrides(PageKind::StoryDetail, &[(Command::SetPriority, "pp")]));
+        assert_eq!(prefix.unwrap_err().to_string(), "'p' ('previous') hides 'pp' ('set_priority') on the story_detail page");
+
+        let reserved = Keymap::with_overrides(overrides(PageKind::Board, &[(Command::Previous, "h")]));
+        assert_eq!(reserved.unwrap_err().to_string(), "'h' can't be bound to 'previous' on the board page");
+
+        let go_to = Keymap::with_overrides(overrides(PageKind::Home, &[(Command::CreateEpic, "gx"), (Command::Board, "g1")]));
+        assert_eq!(go_to.unwrap_err().to_string(), "'gx' can't be bound to 'create_epic' on the home page; 'g1' can't be bound to 'board' on the home page");
+
+        let chord = Keymap::with_overrides(overrides(PageKind::Board, &[(Command::Previous, "hx")]));
+        assert_eq!(chord.unwrap_err().to_string(), "'hx' can't be bound to 'previous' on the board page");
+
+        let unavailable = Keymap::with_overrides(overrides(PageKind::Sprints, &[(Command::Quit, "Q")]));
+        assert_eq!(unavailable.unwrap_err().to_string(), "'quit' isn't available on the sprints page");
+    }
+
+    #[test]
+    fn load_should_read_overrides_from_json() {
+        let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+        write!(tmpfile, r#"{{ "epic_detail": {{ "create_story": "n" }} }}"#).unwrap();
+
+        let keymap = Keymap::load(tmpfile.path().to_str().unwrap()).unwrap();
+
+        assert_eq!(keymap.command(PageKind::EpicDetail, "n"), Some(Command::CreateStory));
+        assert_eq!(Keymap::load("INVALID_PATH").unwrap(), Keymap::default());
+    }
+
+    #[test]
+    fn load_should_fail_with_unknown_commands() {
+        let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+        write!(tmpfile, r#"{{ "home": {{ "fly": "z" }} }}"#).unwrap();
+
+        assert_eq!(Keymap::load(tmpfile.path().to_str().unwrap()).is_err(), true);
+    }
+}
@@ -0,0 +1,475 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use serde_json::{Map, Value};
+use crate::models::{item_replica, item_sequence, DBState, ItemId};
+
+/// Maps of items merged item by item: two items with an id that isn't in the base
+/// are different items, so they conflict as a whole instead of field by field.
+/// `Merge::new` renumbers such items first, so this only happens for hand-edited ids.
+const ITEM_MAPS: [&str; 3] = ["epics", "stories", "sprints"];
+
+/// Which copy a conflict is resolved with.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Side {
+    Ours,
+    Theirs
+}
+
+/// A value both copies changed in different ways. `None` means the value or the
+/// whole item was removed, or wasn't there.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Conflict {
+    pub path: Vec<String>,
+    pub base: Option<Value>,
+    pub ours: Option<Value>,
+    pub theirs: Option<Value>
+}
+
+impl Conflict {
+    /// The map and id of the item the conflict is in, e.g. `("stories", 7)`.
+    pub fn item(&self) -> Option<(&str, u64)> {
+        match self.path.as_slice() {
+            [map, id, ..] if ITEM_MAPS.contains(&map.as_str()) => Some((map.as_str(), id.parse().ok()?)),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.join("."))
+    }
+}
+
+/// An item created in their copy that got a new id, because our copy may have
+/// handed out its id too. Only items without a replica are renumbered, and only when
+/// our copy created such items as well; the ids of the others can't collide, see
+/// `ItemId`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Renumbered {
+    /// `"item"` for epics and stories, which share a counter, or `"sprint"`.
+    pub kind: &'static str,
+    pub from: u64,
+    pub to: u64
+}
+
+impl fmt::Display for Renumbered {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "their {} {} is now {}", self.kind, self.from, self.to)
+    }
+}
+
+/// A three-way merge of two copies of the database that started from the same base.
+///
+/// Copies that claimed the same replica apart from each other are refused, since
+/// they hand out the same ids for different items. Items created in their copy with
+/// ids our copy also handed out are renumbered first, see `Renumbered`. Epics, stories and sprints are then merged by id and
+/// field by field, lists like `Epic.stories` as sets, counters take the highest value
+/// and `updated_at` the latest time. Whatever both copies changed differently becomes
+/// a `Conflict`, kept as in our copy until it is resolved.
+pub struct Merge {
+    merged: Value,
+    ours: Value,
+    theirs: Value,
+    pub renumbered: Vec<Renumbered>,
+    pub conflicts: Vec<Conflict>
+}
+
+impl Merge {
+    pub fn new(base: &DBState, ours: &DBState, theirs: &DBState) -> Result<Merge> {
+        let mut theirs = theirs.clone();
+        let renumbered = renumber_new_items(base, ours, &mut theirs);
+        check_replicas(base, ours, &theirs)?;
+
+        let base = serde_json::to_value(base)?;
+        let ours = serde_json::to_value(ours)?;
+        let theirs = serde_json::to_value(&theirs)?;
+        let mut conflicts = vec![];
+        let merged = merge_value(&mut vec![], Some(&base), Some(&ours), Some(&theirs), &mut conflicts)
+            .ok_or_else(|| anyhow!("the merged database is empty"))?;
+        Ok(Merge { merged, ours, theirs, renumbered, conflicts })
+    }
+
+    /// The side whose copy of the conflicting item was updated last, ours on a tie
+    /// or for conflicts outside of items.
+    pub fn most_recent(&self, conflict: &Conflict) -> Side {
+        let updated_at = |value: &Value| {
+            let (map, id) = conflict.item()?;
+            parse_time(value.get(map)?.get(id.to_string())?.get("updated_at")?)
+        };
+        if updated_at(&self.theirs) > updated_at(&self.ours) {
+            Side::Theirs
+        } else {
+            Side::Ours
+        }
+    }
+
+    /// Applies the side `choose` picks for every conflict. Story ids that no longer
+    /// point to a story are dropped from epics, and stories left without an epic are
+    /// put back in the epic that had them, or removed with it.
+    pub fn resolve(self, mut choose: impl FnMut(&Conflict) -> Result<Side>) -> Result<DBState> {
+        let mut merged = self.merged;
+        for conflict in &self.conflicts {
+            let value = match choose(conflict)? {
+                Side::Ours => &conflict.ours,
+                Side::Theirs => &conflict.theirs
+            };
+            set_path(&mut merged, &conflict.path, value.clone());
+        }
+
+        let mut state: DBState = serde_json::from_value(merged)?;
+        let story_ids = state.stories.keys().copied().collect::<Vec<_>>();
+        for epic in state.epics.values_mut() {
+            epic.stories.retain(|id| story_ids.contains(id));
+        }
+        for story_id in story_ids {
+            if state.epics.values().any(|epic| epic.stories.contains(&story_id)) {
+                continue;
+            }
+            let owner = [&self.ours, &self.theirs]
+                .into_iter()
+                .filter_map(|copy| serde_json::from_value::<DBState>(copy.clone()).ok())
+                .find_map(|copy| copy.epic_id_of_story(story_id))
+                .filter(|epic_id| state.epics.contains_key(epic_id));
+            match owner {
+                Some(epic_id) => state.epics.get_mut(&epic_id).unwrap().stories.push(story_id),
+                None => {
+                    state.stories.remove(&story_id);
+                }
+            }
+        }
+        state.last_item_id = state.epics.keys().chain(state.stories.keys()).map(|id| item_sequence(*id)).fold(state.last_item_id, u32::max);
+        state.last_sprint_id = state.sprints.keys().copied().fold(state.last_sprint_id, u32::max);
+        Ok(state)
+    }
+}
+
+/// Fails when both copies claimed a replica the base didn't have, or created items
+/// with the same id in a replica, see `ItemId`.
+fn check_replicas(base: &DBState, ours: &DBState, theirs: &DBState) -> Result<()> {
+    if let Some(replica) = ours.replicas.intersection(&theirs.replicas).find(|replica| !base.replicas.contains(replica)) {
+        return Err(anyhow!("both copies claimed replica {}, so their ids may clash", replica));
+    }
+    let new_items = |copy: &DBState| new_items(base, copy).into_iter().filter(|id| item_replica(*id) != 0).collect::<BTreeSet<_>>();
+    if let Some(id) = new_items(ours).intersection(&new_items(theirs)).next() {
+        return Err(anyhow!("both copies created item {}, so they share replica {}", ours.item_key(*id), item_replica(*id)));
+    }
+    Ok(())
+}
+
+/// The ids of the epics and stories `copy` created since `base`.
+fn new_items(base: &DBState, copy: &DBState) -> Vec<ItemId> {
+    copy.epics.keys()
+        .chain(copy.stories.keys())
+        .filter(|id| !base.epics.contains_key(id) && !base.stories.contains_key(id))
+        .copied()
+        .sorted()
+        .collect()
+}
+
+/// Moves the epics, stories and sprints created in `theirs` past the ids our copy
+/// handed out, updating the story lists of epics and the sprints of stories.
+fn renumber_new_items(base: &DBState, ours: &DBState, theirs: &mut DBState) -> Vec<Renumbered> {
+    let without_replica = |copy: &DBState| new_items(base, copy).into_iter().filter(|id| item_replica(*id) == 0).collect::<Vec<_>>();
+    let new_items = if without_replica(ours).is_empty() { vec![] } else { without_replica(theirs) };
+    let items = new_ids("item", &new_items, ours.last_item_id);
+    let new_sprints = theirs.sprints.keys()
+        .filter(|id| !base.sprints.contains_key(id))
+        .map(|id| u64::from(*id))
+        .collect::<Vec<_>>();
+    let sprints = new_ids("sprint", &new_sprints, ours.last_sprint_id);
+
+    let item_id = |id: ItemId| items.iter().find(|item| item.from == id).map_or(id, |item| item.to);
+    let sprint_id = |id: u32| sprints.iter().find(|sprint| sprint.from == u64::from(id)).map_or(id, |sprint| sprint.to as u32);
+    theirs.epics = std::mem::take(&mut theirs.epics)
+        .into_iter()
+        .map(|(id, mut epic)| {
+            epic.stories.iter_mut().for_each(|story_id| *story_id = item_id(*story_id));
+            (item_id(id), epic)
+        })
+        .collect();
+    theirs.stories = std::mem::take(&mut theirs.stories)
+        .into_iter()
+        .map(|(id, mut story)| {
+            story.sprint_id = story.sprint_id.map(sprint_id);
+            (item_id(id), story)
+        })
+        .collect();
+    theirs.sprints = std::mem::take(&mut theirs.sprints)
+        .into_iter()
+        .map(|(id, sprint)| (sprint_id(id), sprint))
+        .collect();
+    theirs.last_item_id = theirs.last_item_id.max(ours.last_item_id + new_items.len() as u32);
+    theirs.last_sprint_id = theirs.last_sprint_id.max(ours.last_sprint_id + new_sprints.len() as u32);
+
+    items.into_iter().chain(sprints).filter(|renumbered| renumbered.from != renumbered.to).collect()
+}
+
+/// Gives the new ids, in order, the ids after `last_id`.
+fn new_ids(kind: &'static str, new_ids: &[u64], last_id: u32) -> Vec<Renumbered> {
+    new_ids
+        .iter()
+        .zip(u64::from(last_id) + 1..)
+        .map(|(from, to)| Renumbered { kind, from: *from, to })
+        .collect()
+}
+
+fn merge_value(path: &mut Vec<String>, base: Option<&Value>, ours: Option<&Value>, theirs: Option<&Value>, conflicts: &mut Vec<Conflict>) -> Option<Value> {
+    if ours == theirs || theirs == base {
+        return ours.cloned();
+    }
+    if ours == base {
+        return theirs.cloned();
+    }
+
+    let key = path.last().map(String::as_str);
+    let new_item = path.len() == 2 && ITEM_MAPS.contains(&path[0].as_str()) && base.is_none();
+    match (base, ours, theirs) {
+        (_, Some(Value::Number(ours)), Some(Value::Number(theirs))) if matches!(key, Some("last_item_id" | "last_sprint_id")) && path.len() == 1 => {
+            Some(Value::from(ours.as_u64()?.max(theirs.as_u64()?)))
+        }
+        (_, Some(ours), Some(theirs)) if key == Some("updated_at") => {
+            Some(if parse_time(theirs) > parse_time(ours) { theirs.clone() } else { ours.clone() })
+        }
+        (_, Some(Value::Object(ours)), Some(Value::Object(theirs))) if !new_item => {
+            let empty = Map::new();
+            let base = base.and_then(Value::as_object).unwrap_or(&empty);
+            let mut keys = ours.keys().chain(theirs.keys()).collect::<Vec<_>>();
+            keys.sort();
+            keys.dedup();
+
+            let mut merged = Map::new();
+            for key in keys {
+                path.push(key.clone());
+                if let Some(value) = merge_value(path, base.get(key), ours.get(key), theirs.get(key), conflicts) {
+                    merged.insert(key.clone(), value);
+                }
+                path.pop();
+            }
+            Some(Value::Object(merged))
+        }
+        (Some(Value::Array(base)), Some(Value::Array(ours)), Some(Value::Array(theirs))) => {
+            let mut merged = ours
+                .iter()
+                .filter(|value| !base.contains(value) || theirs.contains(value))
+                .cloned()
+                .collect::<Vec<_>>();
+            for value in theirs {
+                if !base.contains(value) && !merged.contains(value) {
+                    merged.push(value.clone());
+                }
+            }
+            Some(Value::Array(merged))
+        }
+        _ => {
+            conflicts.push(Conflict { path: path.clone(), base: base.cloned(), ours: ours.cloned(), theirs: theirs.cloned() });
+            ours.cloned()
+        }
+    }
+}
+
+fn parse_time(value: &Value) -> Option<DateTime<Utc>> {
+    serde_json::from_value(value.clone()).ok()
+}
+
+/// Sets the value at `path`, or removes it for `None`.
+fn set_path(root: &mut Value, path: &[String], value: Option<Value>) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let mut current = root;
+    for key in parents {
+        let Some(object) = current.as_object_mut() else {
+            return;
+        };
+        current = object.entry(key.clone()).or_insert_with(|| Value::Object(Map::new()));
+    }
+    if let Some(object) = current.as_object_mut() {
+        match value {
+            Some(value) => object.insert(last.clone(), value),
+            None => object.remove(last)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use crate::db::{test_utils::MockDb, ProjectsDatabase};
+    use chrono::NaiveDate;
+    use crate::models::{item_id, Epic, Sprint, Status, Story};
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+    }
+
+    fn base() -> DBState {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        let epic_id = db.create_epic(Epic::new("Auth".to_owned(), "".to_owned())).unwrap();
+        db.create_story(Story::new("Login".to_owned(), "".to_owned()), epic_id).unwrap();
+        db.create_story(Story::new("Logout".to_owned(), "".to_owned()), epic_id).unwrap();
+        db.read_db().unwrap()
+    }
+
+    #[test]
+    fn merge_should_combine_changes_to_different_fields_and_items() {
+        let base = base();
+        let mut ours = base.clone();
+        ours.stories.get_mut(&2).unwrap().status = Status::InProgress;
+        ours.epics.get_mut(&1).unwrap().stories.retain(|id| *id != 3);
+        ours.stories.remove(&3);
+        let mut theirs = base.clone();
+        theirs.stories.get_mut(&2).unwrap().points = Some(5);
+        theirs.epics.get_mut(&1).unwrap().name = "Auth v2".to_owned();
+
+        let merge = Merge::new(&base, &ours, &theirs).unwrap();
+        assert_eq!(merge.conflicts, vec![]);
+        let state = merge.resolve(|_| Ok(Side::Ours)).unwrap();
+
+        assert_eq!(state.stories[&2].status, Status::InProgress);
+        assert_eq!(state.stories[&2].points, Some(5));
+        assert_eq!(state.epics[&1].name, "Auth v2");
+        assert_eq!(state.epics[&1].stories, vec![2]);
+        assert_eq!(state.stories.contains_key(&3), false);
+    }
+
+    #[test]
+    fn merge_should_report_conflicts_and_apply_the_chosen_side() {
+        let base = base();
+        let mut ours = base.clone();
+        ours.stories.get_mut(&2).unwrap().name = "Sign in".to_owned();
+        ours.stories.get_mut(&2).unwrap().updated_at = Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+        let mut theirs = base.clone();
+        theirs.stories.get_mut(&2).unwrap().name = "Log in".to_owned();
+        theirs.stories.get_mut(&2).unwrap().updated_at = Some(Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap());
+
+        let merge = Merge::new(&base, &ours, &theirs).unwrap();
+
+        assert_eq!(merge.conflicts.len(), 1);
+        assert_eq!(merge.conflicts[0].to_string(), "stories.2.name");
+        assert_eq!(merge.conflicts[0].item(), Some(("stories", 2)));
+        assert_eq!(merge.most_recent(&merge.conflicts[0]), Side::Theirs);
+        let state = merge.resolve(|_| Ok(Side::Theirs)).unwrap();
+        assert_eq!(state.stories[&2].name, "Log in");
+        assert_eq!(state.stories[&2].updated_at, Some(Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn merge_should_renumber_their_items_created_with_the_same_ids() {
+        let base = base();
+        let ours_db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        ours_db.database.write_db(&base).unwrap();
+        ours_db.create_epic(Epic::new("Billing".to_owned(), "".to_owned())).unwrap();
+        let theirs_db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        theirs_db.database.write_db(&base).unwrap();
+        let search = theirs_db.create_epic(Epic::new("Search".to_owned(), "".to_owned())).unwrap();
+        let story = theirs_db.create_story(Story::new("Index".to_owned(), "".to_owned()), search).unwrap();
+        let sprint = theirs_db.create_sprint(Sprint::new("Sprint 1".to_owned(), "".to_owned(), date(1), date(14))).unwrap();
+        theirs_db.assign_story_to_sprint(story, Some(sprint)).unwrap();
+        ours_db.create_sprint(Sprint::new("Sprint A".to_owned(), "".to_owned(), date(1), date(14))).unwrap();
+
+        let merge = Merge::new(&base, &ours_db.read_db().unwrap(), &theirs_db.read_db().unwrap()).unwrap();
+
+        assert_eq!(merge.conflicts, vec![]);
+        assert_eq!(merge.renumbered.iter().map(ToString::to_string).collect::<Vec<_>>(), vec![
+            "their item 4 is now 5",
+            "their item 5 is now 6",
+            "their sprint 1 is now 2",
+        ]);
+        let state = merge.resolve(|_| Ok(Side::Ours)).unwrap();
+        assert_eq!(state.epics[&4].name, "Billing");
+        assert_eq!(state.epics[&5].name, "Search");
+        assert_eq!(state.epics[&5].stories, vec![6]);
+        assert_eq!(state.stories[&6].sprint_id, Some(2));
+        assert_eq!(state.sprints[&1].name, "Sprint A");
+        assert_eq!((state.last_item_id, state.last_sprint_id), (6, 2));
+    }
+
+    #[test]
+    fn merge_should_keep_the_ids_of_items_created_in_replicas() {
+        let base = base();
+        let mut ours = base.clone();
+        ours.epics.insert(item_id(1, 4), Epic::new("Billing".to_owned(), "".to_owned()));
+        ours.last_item_id = 4;
+        let mut theirs = base.clone();
+        theirs.epics.insert(item_id(2, 4), Epic::new("Search".to_owned(), "".to_owned()));
+        theirs.last_item_id = 4;
+
+        let merge = Merge::new(&base, &ours, &theirs).unwrap();
+
+        assert_eq!(merge.renumbered, vec![]);
+        assert_eq!(merge.conflicts, vec![]);
+        let state = merge.resolve(|_| Ok(Side::Ours)).unwrap();
+        assert_eq!(state.epics[&item_id(1, 4)].name, "Billing");
+        assert_eq!(state.epics[&item_id(2, 4)].name, "Search");
+        assert_eq!(state.item_key(item_id(2, 4)), "4.2");
+        assert_eq!(state.last_item_id, 4);
+    }
+
+    #[test]
+    fn merge_should_keep_their_ids_when_ours_created_items_in_a_replica() {
+        let base = base();
+        let mut ours = base.clone();
+        ours.epics.insert(item_id(7, 4), Epic::new("Billing".to_owned(), "".to_owned()));
+        ours.last_item_id = 4;
+        let mut theirs = base.clone();
+        theirs.epics.insert(4, Epic::new("Search".to_owned(), "".to_owned()));
+        theirs.last_item_id = 4;
+
+        let merge = Merge::new(&base, &ours, &theirs).unwrap();
+
+        assert_eq!(merge.renumbered, vec![]);
+        assert_eq!(merge.resolve(|_| Ok(Side::Ours)).unwrap().epics[&4].name, "Search");
+    }
+
+    #[test]
+    fn merge_should_refuse_copies_sharing_a_replica() {
+        let base = base();
+        let mut ours = base.clone();
+        ours.replicas.insert(3);
+        let mut theirs = ours.clone();
+        assert_eq!(Merge::new(&base, &ours, &theirs).err().unwrap().to_string(), "both copies claimed replica 3, so their ids may clash");
+
+        let base = ours.clone();
+        ours.epics.insert(item_id(3, 4), Epic::new("Billing".to_owned(), "".to_owned()));
+        theirs.epics.insert(item_id(3, 4), Epic::new("Search".to_owned(), "".to_owned()));
+        assert_eq!(Merge::new(&base, &ours, &theirs).err().unwrap().to_string(), "both copies created item 4.3, so they share replica 3");
+    }
+
+    #[test]
+    fn merge_should_conflict_on_hand_made_items_with_the_same_id() {
+        let base = base();
+        let mut ours = base.clone();
+        ours.epics.insert(4, Epic::new("Billing".to_owned(), "".to_owned()));
+        let mut theirs = base.clone();
+        theirs.epics.insert(4, Epic::new("Search".to_owned(), "".to_owned()));
+        theirs.last_item_id = 4;
+
+        let merge = Merge::new(&base, &ours, &theirs).unwrap();
+
+        assert_eq!(merge.renumbered, vec![]);
+        assert_eq!(merge.conflicts.iter().map(ToString::to_string).collect::<Vec<_>>(), vec!["epics.4"]);
+        assert_eq!(merge.resolve(|_| Ok(Side::Theirs)).unwrap().epics[&4].name, "Search");
+    }
+
+    #[test]
+    fn resolve_should_restore_edited_stories_into_their_epic() {
+        let base = base();
+        let mut ours = base.clone();
+        ours.epics.get_mut(&1).unwrap().stories.retain(|id| *id != 3);
+        ours.stories.remove(&3);
+        let mut theirs = base.clone();
+        theirs.stories.get_mut(&3).unwrap().status = Status::Closed;
+
+        let merge = Merge::new(&base, &ours, &theirs).unwrap();
+        assert_eq!(merge.conflicts.iter().map(ToString::to_string).collect::<Vec<_>>(), vec!["stories.3"]);
+
+        let state = merge.resolve(|_| Ok(Side::Theirs)).unwrap();
+        assert_eq!(state.epics[&1].stories, vec![2, 3]);
+        assert_eq!(state.stories[&3].status, Status::Closed);
+    }
+}
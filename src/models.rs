@@ -1,22 +1,108 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Eq)]
+/// Id of an epic or story. The low 32 bits are the sequence number handed out from
+/// `DBState.last_item_id`, the number shown in keys like `PROJ-43`. The high 32 bits
+/// are the replica that created the item, so copies of the database edited apart
+/// never hand out the same id or key, see `DBState::item_key`. Every copy claims
+/// a replica the first time it writes, the first one replica 0, so ids from before
+/// replicas keep their value.
+pub type ItemId = u64;
+
+/// The id of the item `replica` creates with the `sequence` number.
+pub fn item_id(replica: u32, sequence: u32) -> ItemId {
+    (u64::from(replica) << 32) | u64::from(sequence)
+}
+
+pub fn item_sequence(id: ItemId) -> u32 {
+    id as u32
+}
+
+pub fn item_replica(id: ItemId) -> u32 {
+    (id >> 32) as u32
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Action {
-    NavigateToEpicDetail { epic_id: u32 },
-    NavigateToStoryDetail { epic_id: u32, story_id: u32 },
+    NavigateToEpicDetail { epic_id: ItemId },
+    NavigateToStoryDetail { epic_id: ItemId, story_id: ItemId },
     NavigateToPreviousPage,
+    /// Reopens the page last left with `NavigateToPreviousPage` or a breadcrumb jump.
+    NavigateForward,
+    /// Goes back to a page of the breadcrumb trail, 0 being the home page.
+    NavigateToBreadcrumb { level: usize },
     CreateEpic,
-    UpdateEpicStatus { epic_id: u32 },
-    DeleteEpic { epic_id: u32 },
-    CreateStory { epic_id: u32 },
-    UpdateStoryStatus { story_id: u32 },
-    DeleteStory { epic_id: u32, story_id: u32 },
+    UpdateEpicStatus { epic_id: ItemId },
+    DeleteEpic { epic_id: ItemId },
+    CreateStory { epic_id: ItemId },
+    UpdateStoryStatus { story_id: ItemId },
+    DeleteStory { epic_id: ItemId, story_id: ItemId },
+    SearchStories,
+    CreateFieldDefinition,
+    UpdateEpicField { epic_id: ItemId },
+    UpdateStoryField { story_id: ItemId },
+    UpdateStoryPoints { story_id: ItemId },
+    UpdateStoryPriority { story_id: ItemId },
+    UpdateStoryDueDate { story_id: ItemId },
+    NavigateToSprints,
+    NavigateToSprintPlanning { sprint_id: u32 },
+    CreateSprint,
+    StartSprint { sprint_id: u32 },
+    CloseSprint { sprint_id: u32 },
+    AddStoryToSprint { sprint_id: u32, story_id: ItemId },
+    RemoveStoryFromSprint { story_id: ItemId },
+    NavigateToBoard { epic_id: Option<ItemId> },
+    SetStoryStatus { story_id: ItemId, status: Status },
+    /// Opens any epic or story by its key or id, wherever it lives, see `DBState::find_item`.
+    GoToItem { key: String },
+    /// Lists every action with its keys, see `Action::help`.
+    ShowHelp,
     Exit
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+impl Action {
+    /// One of each action, in the order the help page lists them.
+    pub fn all() -> Vec<Action> {
+        vec![
+            Action::NavigateToEpicDetail { epic_id: 0 },
+            Action::NavigateToStoryDetail { epic_id: 0, story_id: 0 },
+            Action::GoToItem { key: String::new() },
+            Action::NavigateToPreviousPage,
+            Action::NavigateForward,
+            Action::NavigateToBreadcrumb { level: 0 },
+            Action::CreateEpic,
+            Action::UpdateEpicStatus { epic_id: 0 },
+            Action::DeleteEpic { epic_id: 0 },
+            Action::UpdateEpicField { epic_id: 0 },
+            Action::CreateStory { epic_id: 0 },
+            Action::UpdateStoryStatus { story_id: 0 },
+            Action::DeleteStory { epic_id: 0, story_id: 0 },
+            Action::UpdateStoryPoints { story_id: 0 },
+            Action::UpdateStoryPriority { story_id: 0 },
+            Action::UpdateStoryDueDate { story_id: 0 },
+            Action::UpdateStoryField { story_id: 0 },
+            Action::CreateFieldDefinition,
+            Action::SearchStories,
+            Action::NavigateToBoard { epic_id: None },
+            Action::SetStoryStatus { story_id: 0, status: Status::Open },
+            Action::NavigateToSprints,
+            Action::NavigateToSprintPlanning { sprint_id: 0 },
+            Action::CreateSprint,
+            Action::StartSprint { sprint_id: 0 },
+            Action::CloseSprint { sprint_id: 0 },
+            Action::AddStoryToSprint { sprint_id: 0, story_id: 0 },
+            Action::RemoveStoryFromSprint { story_id: 0 },
+            Action::ShowHelp,
+            Action::Exit,
+        ]
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
 pub enum Status {
     Open,
     InProgress,
@@ -35,40 +121,600 @@ impl Display for Status {
     }
 }
 
+impl Status {
+    pub fn all() -> Vec<Status> {
+        vec![Status::Open, Status::InProgress, Status::Resolved, Status::Closed]
+    }
+
+    /// The status that follows this one in the workflow, if any.
+    pub fn next(&self) -> Option<Status> {
+        match self {
+            Status::Open => Some(Status::InProgress),
+            Status::InProgress => Some(Status::Resolved),
+            Status::Resolved => Some(Status::Closed),
+            Status::Closed => None
+        }
+    }
+
+    pub fn previous(&self) -> Option<Status> {
+        match self {
+            Status::Open => None,
+            Status::InProgress => Some(Status::Open),
+            Status::Resolved => Some(Status::InProgress),
+            Status::Closed => Some(Status::Resolved)
+        }
+    }
+
+    /// Resolved and closed stories count as done for sprint planning.
+    pub fn is_done(&self) -> bool {
+        matches!(self, Status::Resolved | Status::Closed)
+    }
+}
+
+impl FromStr for Status {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "open" => Ok(Status::Open),
+            "in-progress" | "inprogress" | "in progress" => Ok(Status::InProgress),
+            "resolved" => Ok(Status::Resolved),
+            "closed" => Ok(Status::Closed),
+            _ => Err(anyhow!("unknown status '{}'", s))
+        }
+    }
+}
+
+/// The name of one of the project's issue types, see `IssueTypeConfig`.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone)]
+#[serde(transparent)]
+pub struct IssueType(String);
+
+impl IssueType {
+    pub fn new(name: &str) -> Self {
+        IssueType(name.trim().to_owned())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for IssueType {
+    fn default() -> Self {
+        IssueType::new("Story")
+    }
+}
+
+impl Display for IssueType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.to_uppercase())
+    }
+}
+
+/// An issue type enabled for this project: its name, the icon (or letter) used to
+/// display it in story lists and the fields only stories of this type have.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct IssueTypeConfig {
+    pub(crate) issue_type: IssueType,
+    pub(crate) icon: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) fields: Vec<FieldDefinition>
+}
+
+impl IssueTypeConfig {
+    pub fn new(name: &str, icon: &str) -> Self {
+        IssueTypeConfig { issue_type: IssueType::new(name), icon: icon.to_owned(), fields: vec![] }
+    }
+
+    pub fn with_field(mut self, name: &str, kind: FieldKind) -> Self {
+        self.fields.push(FieldDefinition::new(name.to_owned(), kind, FieldTarget::Stories));
+        self
+    }
+}
+
+pub fn default_issue_types() -> Vec<IssueTypeConfig> {
+    let severities = ["LOW", "MEDIUM", "HIGH", "CRITICAL"].map(str::to_owned).to_vec();
+    vec![
+        IssueTypeConfig::new("Story", "S"),
+        IssueTypeConfig::new("Bug", "B")
+            .with_field(STEPS_TO_REPRODUCE, FieldKind::Text)
+            .with_field(SEVERITY, FieldKind::SingleSelect { options: severities }),
+        IssueTypeConfig::new("Task", "T"),
+        IssueTypeConfig::new("Spike", "K"),
+    ]
+}
+
+const STEPS_TO_REPRODUCE: &str = "Steps to reproduce";
+const SEVERITY: &str = "Severity";
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, Default)]
+pub enum Priority {
+    Lowest,
+    Low,
+    #[default]
+    Medium,
+    High,
+    Highest,
+}
+
+impl Priority {
+    pub fn all() -> [Priority; 5] {
+        [Priority::Lowest, Priority::Low, Priority::Medium, Priority::High, Priority::Highest]
+    }
+}
+
+impl FromStr for Priority {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Priority::all()
+            .into_iter()
+            .find(|priority| priority.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| anyhow!("unknown priority '{}'", s))
+    }
+}
+
+impl Display for Priority {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Priority::Lowest => { write!(f, "LOWEST") }
+            Priority::Low => { write!(f, "LOW") }
+            Priority::Medium => { write!(f, "MEDIUM") }
+            Priority::High => { write!(f, "HIGH") }
+            Priority::Highest => { write!(f, "HIGHEST") }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum FieldKind {
+    Text,
+    Number,
+    Date,
+    SingleSelect { options: Vec<String> },
+    MultiSelect { options: Vec<String> },
+    User,
+}
+
+impl Display for FieldKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldKind::Text => { write!(f, "text") }
+            FieldKind::Number => { write!(f, "number") }
+            FieldKind::Date => { write!(f, "date") }
+            FieldKind::SingleSelect { options } => { write!(f, "single-select ({})", options.join(", ")) }
+            FieldKind::MultiSelect { options } => { write!(f, "multi-select ({})", options.join(", ")) }
+            FieldKind::User => { write!(f, "user") }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum FieldTarget {
+    Epics,
+    Stories,
+    Both,
+}
+
+impl FieldTarget {
+    pub fn applies_to_epics(&self) -> bool {
+        matches!(self, FieldTarget::Epics | FieldTarget::Both)
+    }
+
+    pub fn applies_to_stories(&self) -> bool {
+        matches!(self, FieldTarget::Stories | FieldTarget::Both)
+    }
+}
+
+/// A project-wide custom field that can be set on epics and/or stories.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct FieldDefinition {
+    pub(crate) name: String,
+    pub(crate) kind: FieldKind,
+    pub(crate) target: FieldTarget
+}
+
+impl FieldDefinition {
+    pub fn new(name: String, kind: FieldKind, target: FieldTarget) -> Self {
+        FieldDefinition { name, kind, target }
+    }
+
+    /// Validates raw user input against the field kind and converts it into a value.
+    pub fn parse_value(&self, input: &str) -> Result<FieldValue> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err(anyhow!("value for '{}' must not be empty", self.name));
+        }
+
+        match &self.kind {
+            FieldKind::Text => Ok(FieldValue::Text(input.to_owned())),
+            FieldKind::Number => input
+                .parse::<f64>()
+                .ok()
+                .filter(|number| number.is_finite())
+                .map(FieldValue::Number)
+                .ok_or_else(|| anyhow!("'{}' is not a number", input)),
+            FieldKind::Date => NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                .map(FieldValue::Date)
+                .map_err(|_| anyhow!("'{}' is not a date in YYYY-MM-DD format", input)),
+            FieldKind::SingleSelect { options } => find_option(options, input)
+                .map(FieldValue::SingleSelect),
+            FieldKind::MultiSelect { options } => input
+                .split(',')
+                .map(|option| find_option(options, option.trim()))
+                .collect::<Result<Vec<_>>>()
+                .map(FieldValue::MultiSelect),
+            FieldKind::User => {
+                let user = input.trim_start_matches('@');
+                if user.is_empty() || user.contains(char::is_whitespace) {
+                    return Err(anyhow!("'{}' is not a valid user name", input));
+                }
+                Ok(FieldValue::User(user.to_owned()))
+            }
+        }
+    }
+}
+
+fn find_option(options: &[String], input: &str) -> Result<String> {
+    options
+        .iter()
+        .find(|option| option.eq_ignore_ascii_case(input))
+        .cloned()
+        .ok_or_else(|| anyhow!("'{}' is not one of: {}", input, options.join(", ")))
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum FieldValue {
+    Text(String),
+    Number(f64),
+    Date(NaiveDate),
+    SingleSelect(String),
+    MultiSelect(Vec<String>),
+    User(String),
+}
+
+impl FieldValue {
+    /// Case-insensitive match used by search; multi-select values match any of their options.
+    pub fn matches(&self, query: &str) -> bool {
+        match self {
+            FieldValue::MultiSelect(values) => values.iter().any(|value| value.eq_ignore_ascii_case(query)),
+            value => value.to_string().to_lowercase().contains(&query.to_lowercase())
+        }
+    }
+}
+
+impl Display for FieldValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldValue::Text(text) => { write!(f, "{}", text) }
+            FieldValue::Number(number) => { write!(f, "{}", number) }
+            FieldValue::Date(date) => { write!(f, "{}", date.format("%Y-%m-%d")) }
+            FieldValue::SingleSelect(option) => { write!(f, "{}", option) }
+            FieldValue::MultiSelect(options) => { write!(f, "{}", options.join(", ")) }
+            FieldValue::User(user) => { write!(f, "@{}", user) }
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct Epic {
     pub(crate) name: String,
     pub(crate) description: String,
     pub(crate) status: Status,
-    pub(crate) stories: Vec<u32>
+    pub(crate) stories: Vec<ItemId>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) fields: BTreeMap<String, FieldValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) updated_at: Option<DateTime<Utc>>
 }
 
 impl Epic {
     pub fn new(name: String, description: String) -> Self {
         Epic {
-            name, description, status: Status::Open, stories: vec![]
+            name, description, status: Status::Open, stories: vec![], fields: BTreeMap::new(), updated_at: None
         }
     }
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(from = "StoredStory")]
 pub struct Story {
     pub(crate) name: String,
     pub(crate) description: String,
-    pub(crate) status: Status
+    pub(crate) status: Status,
+    #[serde(default)]
+    pub(crate) issue_type: IssueType,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) fields: BTreeMap<String, FieldValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) points: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) sprint_id: Option<u32>,
+    #[serde(default)]
+    pub(crate) priority: Priority,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) due_date: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) updated_at: Option<DateTime<Utc>>
 }
 
 impl Story {
     pub fn new(name: String, description: String) -> Self {
         Story {
-            name, description, status: Status::Open
+            name,
+            description,
+            status: Status::Open,
+            issue_type: IssueType::default(),
+            fields: BTreeMap::new(),
+            points: None,
+            sprint_id: None,
+            priority: Priority::Medium,
+            due_date: None,
+            updated_at: None
+        }
+    }
+
+    pub fn with_issue_type(mut self, issue_type: IssueType) -> Self {
+        self.issue_type = issue_type;
+        self
+    }
+
+    /// A story is overdue when its due date has passed and it isn't done yet.
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        !self.status.is_done() && self.due_date.is_some_and(|due_date| due_date < today)
+    }
+}
+
+/// A story as stored, which may still have the `bug` details older versions kept
+/// apart from the fields; they become the Bug type's fields when read.
+#[derive(Deserialize)]
+struct StoredStory {
+    name: String,
+    description: String,
+    status: Status,
+    #[serde(default)]
+    issue_type: IssueType,
+    #[serde(default)]
+    bug: Option<StoredBugDetails>,
+    #[serde(default)]
+    fields: BTreeMap<String, FieldValue>,
+    #[serde(default)]
+    points: Option<u32>,
+    #[serde(default)]
+    sprint_id: Option<u32>,
+    #[serde(default)]
+    priority: Priority,
+    #[serde(default)]
+    due_date: Option<NaiveDate>,
+    #[serde(default)]
+    updated_at: Option<DateTime<Utc>>
+}
+
+#[derive(Deserialize)]
+struct StoredBugDetails {
+    steps_to_reproduce: String,
+    severity: String
+}
+
+impl From<StoredStory> for Story {
+    fn from(stored: StoredStory) -> Self {
+        let mut fields = stored.fields;
+        if let Some(bug) = stored.bug {
+            if !bug.steps_to_reproduce.is_empty() {
+                fields.insert(STEPS_TO_REPRODUCE.to_owned(), FieldValue::Text(bug.steps_to_reproduce));
+            }
+            fields.insert(SEVERITY.to_owned(), FieldValue::SingleSelect(bug.severity.to_uppercase()));
+        }
+        Story {
+            name: stored.name,
+            description: stored.description,
+            status: stored.status,
+            issue_type: stored.issue_type,
+            fields,
+            points: stored.points,
+            sprint_id: stored.sprint_id,
+            priority: stored.priority,
+            due_date: stored.due_date,
+            updated_at: stored.updated_at
         }
     }
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum SprintState {
+    Planned,
+    Active,
+    Closed,
+}
+
+impl Display for SprintState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SprintState::Planned => { write!(f, "PLANNED") }
+            SprintState::Active => { write!(f, "ACTIVE") }
+            SprintState::Closed => { write!(f, "CLOSED") }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Sprint {
+    pub(crate) name: String,
+    pub(crate) goal: String,
+    pub(crate) start: NaiveDate,
+    pub(crate) end: NaiveDate,
+    pub(crate) state: SprintState
+}
+
+impl Sprint {
+    pub fn new(name: String, goal: String, start: NaiveDate, end: NaiveDate) -> Self {
+        Sprint {
+            name, goal, start, end, state: SprintState::Planned
+        }
+    }
+}
+
+/// Where the incomplete stories of a closed sprint go.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum SprintRollover {
+    NextSprint,
+    Backlog,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct DBState {
     pub(crate) last_item_id: u32,
-    pub(crate) epics: HashMap<u32, Epic>,
-    pub(crate) stories: HashMap<u32, Story>
+    pub(crate) epics: BTreeMap<ItemId, Epic>,
+    pub(crate) stories: BTreeMap<ItemId, Story>,
+    #[serde(default = "default_issue_types")]
+    pub(crate) issue_types: Vec<IssueTypeConfig>,
+    #[serde(default)]
+    pub(crate) custom_fields: Vec<FieldDefinition>,
+    #[serde(default)]
+    pub(crate) last_sprint_id: u32,
+    #[serde(default)]
+    pub(crate) sprints: BTreeMap<u32, Sprint>,
+    /// Project key put in front of item numbers, e.g. `PROJ` for `PROJ-43`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) key_prefix: Option<String>,
+    /// Replicas claimed by copies of the database, see `ItemId`.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) replicas: BTreeSet<u32>
+}
+
+impl Default for DBState {
+    fn default() -> Self {
+        DBState {
+            last_item_id: 0,
+            epics: BTreeMap::new(),
+            stories: BTreeMap::new(),
+            issue_types: default_issue_types(),
+            custom_fields: vec![],
+            last_sprint_id: 0,
+            sprints: BTreeMap::new(),
+            key_prefix: None,
+            replicas: BTreeSet::new()
+        }
+    }
+}
+
+impl DBState {
+    pub fn issue_type_config(&self, issue_type: &IssueType) -> Option<&IssueTypeConfig> {
+        self.issue_types.iter().find(|config| &config.issue_type == issue_type)
+    }
+
+    /// Finds one of the project's issue types by name, ignoring case.
+    pub fn issue_type(&self, name: &str) -> Result<IssueType> {
+        self.issue_types
+            .iter()
+            .find(|config| config.issue_type.name().eq_ignore_ascii_case(name.trim()))
+            .map(|config| config.issue_type.clone())
+            .ok_or_else(|| anyhow!("unknown issue type '{}'", name))
+    }
+
+    /// Types that are no longer configured are shown by their first letter.
+    pub fn issue_type_icon(&self, issue_type: &IssueType) -> String {
+        self.issue_type_config(issue_type)
+            .map(|config| config.icon.clone())
+            .unwrap_or_else(|| issue_type.name().chars().take(1).collect::<String>().to_uppercase())
+    }
+
+    pub fn field_definition(&self, name: &str) -> Option<&FieldDefinition> {
+        self.custom_fields
+            .iter()
+            .find(|definition| definition.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn epic_fields(&self) -> Vec<FieldDefinition> {
+        self.custom_fields
+            .iter()
+            .filter(|definition| definition.target.applies_to_epics())
+            .cloned()
+            .collect()
+    }
+
+    pub fn story_fields(&self) -> Vec<FieldDefinition> {
+        self.custom_fields
+            .iter()
+            .filter(|definition| definition.target.applies_to_stories())
+            .cloned()
+            .collect()
+    }
+
+    /// The fields of the story's issue type followed by the custom story fields.
+    pub fn fields_of_story(&self, story: &Story) -> Vec<FieldDefinition> {
+        self.issue_type_config(&story.issue_type)
+            .map(|config| config.fields.clone())
+            .unwrap_or_default()
+            .into_iter()
+            .chain(self.story_fields())
+            .collect()
+    }
+
+    /// The planned sprint that starts first, i.e. the one incomplete work rolls over into.
+    pub fn next_planned_sprint(&self) -> Option<u32> {
+        self.sprints
+            .iter()
+            .filter(|(_, sprint)| sprint.state == SprintState::Planned)
+            .min_by_key(|(id, sprint)| (sprint.start, **id))
+            .map(|(id, _)| *id)
+    }
+
+    pub fn sprint_points(&self, sprint_id: u32) -> u32 {
+        self.stories
+            .values()
+            .filter(|story| story.sprint_id == Some(sprint_id))
+            .filter_map(|story| story.points)
+            .sum()
+    }
+
+    /// Returns the id of the epic that owns the given story.
+    pub fn epic_id_of_story(&self, story_id: ItemId) -> Option<ItemId> {
+        self.epics
+            .iter()
+            .find(|(_, epic)| epic.stories.contains(&story_id))
+            .map(|(id, _)| *id)
+    }
+
+    /// The key people use for an item: its sequence number after the key prefix,
+    /// like `PROJ-43` or just `43`, followed by the replica that created it unless
+    /// that is replica 0, like `PROJ-43.7`. The key only depends on the id, so it
+    /// keeps pointing to the same item whatever other copies create.
+    pub fn item_key(&self, id: ItemId) -> String {
+        let number = match item_replica(id) {
+            0 => item_sequence(id).to_string(),
+            replica => format!("{}.{}", item_sequence(id), replica)
+        };
+        match &self.key_prefix {
+            Some(prefix) => format!("{}-{}", prefix, number),
+            None => number
+        }
+    }
+
+    /// Finds an epic or story by its key, with or without the prefix, or by its id.
+    pub fn find_item(&self, key: &str) -> Result<ItemId> {
+        let key = key.trim();
+        let number = match &self.key_prefix {
+            Some(prefix) => key
+                .get(..prefix.len() + 1)
+                .filter(|start| start.eq_ignore_ascii_case(&format!("{}-", prefix)))
+                .map_or(key, |start| &key[start.len()..]),
+            None => key
+        };
+        let invalid = || anyhow!("'{}' isn't an item key", key);
+        let id = match number.split_once('.') {
+            Some((sequence, replica)) => item_id(
+                replica.parse().map_err(|_| invalid())?,
+                sequence.parse().map_err(|_| invalid())?
+            ),
+            // Ids of items from other replicas are above every sequence number.
+            None => number.parse().map_err(|_| invalid())?
+        };
+        if self.epics.contains_key(&id) || self.stories.contains_key(&id) {
+            Ok(id)
+        } else {
+            Err(anyhow!("no epic or story with key {}", key))
+        }
+    }
 }
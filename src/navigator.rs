@@ -1,23 +1,39 @@
 use anyhow::{anyhow, Context, Result};
 use std::rc::Rc;
 use crate::db::ProjectsDatabase;
-use crate::models::Action;
-use crate::ui::{EpicDetail, HomePage, Page, StoryDetail};
+use crate::keymap::Keymap;
+use crate::models::{Action, DBState, ItemId, SprintRollover};
+use crate::session::{PageState, Session};
+use crate::ui::{Board, EpicDetail, HelpPage, HomePage, Page, SearchResults, SprintPlanning, SprintsPage, StoryDetail};
 use crate::ui::prompts::Prompts;
 
+const RECENT_ITEMS: usize = 10;
+
+/// An entry of the command palette.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PaletteEntry {
+    pub label: String,
+    pub action: Action
+}
+
+/// Keeps the trail of open pages, from the home page to the current one, and the
+/// pages left by going back so they can be reopened with `NavigateForward`.
 pub struct Navigator {
     pages: Vec<Box<dyn Page>>,
+    forward: Vec<Box<dyn Page>>,
+    /// Ids of the epics and stories opened lately, most recent first.
+    recent: Vec<ItemId>,
     prompts: Prompts,
     db: Rc<ProjectsDatabase>
 }
 
 impl Navigator {
-    pub fn new(db: Rc<ProjectsDatabase>) -> Self {
+    pub fn new(db: Rc<ProjectsDatabase>, prompts: Prompts) -> Self {
         Navigator {
-            pages: vec![Box::new(HomePage {
-                db: Rc::clone(&db)
-            })],
-            prompts: Prompts::new(),
+            pages: vec![Box::new(HomePage::new(Rc::clone(&db)))],
+            forward: vec![],
+            recent: vec![],
+            prompts,
             db
         }
     }
@@ -26,29 +42,162 @@ impl Navigator {
         self.pages.last()
     }
 
+    pub fn get_current_page_mut(&mut self) -> Option<&mut Box<dyn Page>> {
+        self.pages.last_mut()
+    }
+
+    /// Labels of the pages from the home page to the current one.
+    pub fn breadcrumbs(&self) -> Vec<String> {
+        self.pages.iter().map(|page| page.breadcrumb()).collect()
+    }
+
+    pub fn session(&self) -> Session {
+        Session { pages: self.pages.iter().map(|page| page.state()).collect() }
+    }
+
+    /// Reopens the pages of a saved session on top of a fresh home page. Pages whose
+    /// epic, story or sprint no longer exists are dropped.
+    pub fn restore(&mut self, session: Session) -> Result<()> {
+        let db_state = self.db.read_db()?;
+        let mut pages: Vec<Box<dyn Page>> = vec![];
+        for (idx, state) in session.pages.into_iter().enumerate() {
+            if idx == 0 {
+                let home = match state {
+                    PageState::Home { list } => HomePage::new(Rc::clone(&self.db)).with_list_state(list),
+                    _ => HomePage::new(Rc::clone(&self.db))
+                };
+                pages.push(Box::new(home));
+            } else if let Some(page) = self.restore_page(state, &db_state) {
+                pages.push(page);
+            }
+        }
+
+        if !pages.is_empty() {
+            self.pages = pages;
+            self.forward.clear();
+        }
+        Ok(())
+    }
+
+    fn restore_page(&self, state: PageState, db_state: &DBState) -> Option<Box<dyn Page>> {
+        let db = Rc::clone(&self.db);
+        let page: Box<dyn Page> = match state {
+            PageState::Home { .. } => return None,
+            PageState::EpicDetail { epic_id, list } => {
+                db_state.epics.get(&epic_id)?;
+                Box::new(EpicDetail::new(epic_id, db).with_list_state(list))
+            }
+            PageState::StoryDetail { story_id, .. } => {
+                db_state.stories.get(&story_id)?;
+                let epic_id = db_state.epic_id_of_story(story_id)?;
+                Box::new(StoryDetail { epic_id, story_id, db })
+            }
+            PageState::SearchResults { query } => Box::new(SearchResults { query, db }),
+            PageState::Sprints => Box::new(SprintsPage { db }),
+            PageState::SprintPlanning { sprint_id } => {
+                db_state.sprints.get(&sprint_id)?;
+                Box::new(SprintPlanning { sprint_id, db })
+            }
+            PageState::Board { epic_id, selected } => {
+                if let Some(epic_id) = epic_id {
+                    db_state.epics.get(&epic_id)?;
+                }
+                Box::new(Board::new(epic_id, db).with_selected(selected))
+            }
+            PageState::Help { offset } => Box::new(HelpPage { offset }),
+        };
+        Some(page)
+    }
+
+    /// Opens a new page; like in a browser, this forgets the forward history.
+    fn push(&mut self, page: Box<dyn Page>) {
+        match page.state() {
+            PageState::EpicDetail { epic_id, .. } => self.remember(epic_id),
+            PageState::StoryDetail { story_id, .. } => self.remember(story_id),
+            _ => {}
+        }
+        self.forward.clear();
+        self.pages.push(page);
+    }
+
+    fn remember(&mut self, id: ItemId) {
+        self.recent.retain(|recent| *recent != id);
+        self.recent.insert(0, id);
+        self.recent.truncate(RECENT_ITEMS);
+    }
+
+    /// Everything the command palette offers on the current page: its bound commands,
+    /// history navigation and the epics and stories opened lately.
+    pub fn palette_entries(&self, keymap: &Keymap) -> Result<Vec<PaletteEntry>> {
+        let mut entries = vec![];
+        if let Some(page) = self.get_current_page() {
+            for binding in keymap.bindings(page.kind()) {
+                if let Some(action) = page.command_action(binding.command)? {
+                    entries.push(PaletteEntry { label: format!("{} [{}]", binding.command.label(), binding.keys), action });
+                }
+            }
+        }
+
+        if self.pages.len() > 1 {
+            entries.push(PaletteEntry { label: "back [ctrl+b]".to_owned(), action: Action::NavigateToPreviousPage });
+        }
+        if !self.forward.is_empty() {
+            entries.push(PaletteEntry { label: "forward [ctrl+f]".to_owned(), action: Action::NavigateForward });
+        }
+        for (level, breadcrumb) in self.breadcrumbs().into_iter().enumerate().take(self.pages.len().saturating_sub(1)) {
+            entries.push(PaletteEntry { label: format!("go to {} [^{}]", breadcrumb, level + 1), action: Action::NavigateToBreadcrumb { level } });
+        }
+
+        let db_state = self.db.read_db()?;
+        for id in &self.recent {
+            let key = db_state.item_key(*id);
+            let label = if let Some(epic) = db_state.epics.get(id) {
+                format!("recent: Epic {} \"{}\"", key, epic.name)
+            } else if let Some(story) = db_state.stories.get(id) {
+                format!("recent: Story {} \"{}\"", key, story.name)
+            } else {
+                continue;
+            };
+            entries.push(PaletteEntry { label, action: Action::GoToItem { key } });
+        }
+        Ok(entries)
+    }
+
     pub fn handle_action(&mut self, action: Action) -> Result<()> {
         match action {
             Action::NavigateToEpicDetail { epic_id } => {
-                self.pages.push(Box::new(EpicDetail {
-                    epic_id,
-                    db: Rc::clone(&self.db),
-                }))
+                self.push(Box::new(EpicDetail::new(epic_id, Rc::clone(&self.db))))
             }
             Action::NavigateToStoryDetail { epic_id, story_id } => {
-                self.pages.push(Box::new(StoryDetail {
+                self.push(Box::new(StoryDetail {
                     epic_id,
                     story_id,
                     db: Rc::clone(&self.db),
                 }))
             }
             Action::NavigateToPreviousPage => {
-                if !self.pages.is_empty() {
-                    self.pages.pop();
+                // The home page stays, leaving is `Exit`.
+                if self.pages.len() > 1 {
+                    let page = self.pages.pop().unwrap();
+                    self.forward.push(page);
+                }
+            }
+            Action::NavigateForward => {
+                if let Some(page) = self.forward.pop() {
+                    self.pages.push(page);
                 }
             }
+            Action::NavigateToBreadcrumb { level } => {
+                if level >= self.pages.len() {
+                    return Err(anyhow!("no breadcrumb at level {}", level + 1));
+                }
+                let left = self.pages.split_off(level + 1);
+                self.forward.extend(left.into_iter().rev());
+            }
             Action::CreateEpic => {
-                let new_epic = (self.prompts.create_epic)();
-                self.db.create_epic(new_epic).with_context(|| anyhow!("failed to create a new epic!"))?;
+                if let Some(new_epic) = (self.prompts.create_epic)() {
+                    self.db.create_epic(new_epic).with_context(|| anyhow!("failed to create a new epic!"))?;
+                }
             }
             Action::UpdateEpicStatus { epic_id } => {
                 let new_status = (self.prompts.update_status)();
@@ -60,14 +209,17 @@ impl Navigator {
                 if  (self.prompts.delete_epic)() {
                     self.db.delete_epic(epic_id).with_context(|| anyhow!("failed to delete epic"))?;
 
+                    self.forward.clear();
                     if !self.pages.is_empty() {
                         self.pages.pop();
                     }
                 }
             }
             Action::CreateStory { epic_id } => {
-                let new_story = (self.prompts.create_story)();
-                self.db.create_story(new_story, epic_id).with_context(|| anyhow!("couldn't create a new story"))?;
+                let issue_types = self.db.read_db()?.issue_types;
+                if let Some(new_story) = (self.prompts.create_story)(&issue_types) {
+                    self.db.create_story(new_story, epic_id).with_context(|| anyhow!("couldn't create a new story"))?;
+                }
             }
             Action::UpdateStoryStatus { story_id } => {
                 let new_status = (self.prompts.update_status)();
@@ -80,11 +232,122 @@ impl Navigator {
                 if (self.prompts.delete_story)() {
                     self.db.delete_story(epic_id, story_id).with_context(|| anyhow!("couldn't delete story!"))?;
 
+                    self.forward.clear();
                     if !self.pages.is_empty() {
                         self.pages.pop();
                     }
                 }
             }
+            Action::SearchStories => {
+                if let Some(query) = (self.prompts.search)() {
+                    self.push(Box::new(SearchResults {
+                        query,
+                        db: Rc::clone(&self.db),
+                    }))
+                }
+            }
+            Action::CreateFieldDefinition => {
+                if let Some(definition) = (self.prompts.create_field_definition)() {
+                    self.db.create_field_definition(definition).with_context(|| anyhow!("failed to create custom field!"))?;
+                }
+            }
+            Action::UpdateEpicField { epic_id } => {
+                let fields = self.db.read_db()?.epic_fields();
+                if let Some((name, value)) = (self.prompts.update_field)(&fields) {
+                    self.db.set_epic_field(epic_id, &name, &value).with_context(|| anyhow!("failed to update epic field!"))?;
+                }
+            }
+            Action::UpdateStoryField { story_id } => {
+                let db_state = self.db.read_db()?;
+                let fields = db_state.stories.get(&story_id).map(|story| db_state.fields_of_story(story)).unwrap_or_default();
+                if let Some((name, value)) = (self.prompts.update_field)(&fields) {
+                    self.db.set_story_field(story_id, &name, &value).with_context(|| anyhow!("couldn't update story field!"))?;
+                }
+            }
+            Action::UpdateStoryPoints { story_id } => {
+                if let Some(points) = (self.prompts.update_points)() {
+                    self.db.update_story_points(story_id, points).with_context(|| anyhow!("couldn't update story points!"))?;
+                }
+            }
+            Action::UpdateStoryPriority { story_id } => {
+                if let Some(priority) = (self.prompts.update_priority)() {
+                    self.db.update_story_priority(story_id, priority).with_context(|| anyhow!("couldn't update story priority!"))?;
+                }
+            }
+            Action::UpdateStoryDueDate { story_id } => {
+                if let Some(due_date) = (self.prompts.update_due_date)() {
+                    self.db.update_story_due_date(story_id, due_date).with_context(|| anyhow!("couldn't update story due date!"))?;
+                }
+            }
+            Action::NavigateToSprints => {
+                self.push(Box::new(SprintsPage {
+                    db: Rc::clone(&self.db),
+                }))
+            }
+            Action::NavigateToSprintPlanning { sprint_id } => {
+                self.push(Box::new(SprintPlanning {
+                    sprint_id,
+                    db: Rc::clone(&self.db),
+                }))
+            }
+            Action::CreateSprint => {
+                if let Some(sprint) = (self.prompts.create_sprint)() {
+                    self.db.create_sprint(sprint).with_context(|| anyhow!("failed to create a new sprint!"))?;
+                }
+            }
+            Action::StartSprint { sprint_id } => {
+                self.db.start_sprint(sprint_id).with_context(|| anyhow!("failed to start sprint!"))?;
+            }
+            Action::CloseSprint { sprint_id } => {
+                let db_state = self.db.read_db()?;
+                let next_sprint_id = db_state.next_planned_sprint().filter(|id| *id != sprint_id);
+                let next_sprint = next_sprint_id.and_then(|id| db_state.sprints.get(&id));
+
+                if let Some(rollover) = (self.prompts.close_sprint)(next_sprint) {
+                    let rollover_sprint_id = match rollover {
+                        SprintRollover::NextSprint => next_sprint_id,
+                        SprintRollover::Backlog => None,
+                    };
+                    self.db.close_sprint(sprint_id, rollover_sprint_id).with_context(|| anyhow!("failed to close sprint!"))?;
+                }
+            }
+            Action::AddStoryToSprint { sprint_id, story_id } => {
+                self.db.assign_story_to_sprint(story_id, Some(sprint_id)).with_context(|| anyhow!("couldn't add story to sprint!"))?;
+            }
+            Action::RemoveStoryFromSprint { story_id } => {
+                self.db.assign_story_to_sprint(story_id, None).with_context(|| anyhow!("couldn't move story to backlog!"))?;
+            }
+            Action::NavigateToBoard { epic_id } => {
+                self.push(Box::new(Board::new(epic_id, Rc::clone(&self.db))))
+            }
+            Action::SetStoryStatus { story_id, status } => {
+                self.db.update_story_status(story_id, status).with_context(|| anyhow!("couldn't update story status!"))?;
+            }
+            Action::GoToItem { key } => {
+                let db_state = self.db.read_db()?;
+                let id = db_state.find_item(&key)?;
+                let epic_id = if db_state.epics.contains_key(&id) {
+                    id
+                } else {
+                    db_state.epic_id_of_story(id).ok_or_else(|| anyhow!("story {} doesn't belong to an epic", key))?
+                };
+
+                // A jump is opened on top of the current page, so going back returns there.
+                let on_epic = matches!(self.get_current_page().map(|page| page.state()), Some(PageState::EpicDetail { epic_id: current, .. }) if current == epic_id);
+                if !on_epic {
+                    self.push(Box::new(EpicDetail::new(epic_id, Rc::clone(&self.db))));
+                }
+                if epic_id != id {
+                    self.push(Box::new(StoryDetail {
+                        epic_id,
+                        story_id: id,
+                        db: Rc::clone(&self.db),
+                    }))
+                }
+            }
+            Action::ShowHelp => {
+                self.push(Box::new(HelpPage::default()))
+            }
             Action::Exit => {
                 self.pages.clear();
             },
@@ -97,7 +360,7 @@ impl Navigator {
         self.pages.len()
     }
 
-    fn set_prompts(&mut self, prompts: Prompts) {
+    pub fn set_prompts(&mut self, prompts: Prompts) {
         self.prompts = prompts;
     }
 
@@ -105,14 +368,20 @@ impl Navigator {
 
 #[cfg(test)]
 mod tests {
-    use crate::{db::test_utils::MockDb, models::{Epic, Status, Story}};
-    use crate::ui::{EpicDetail, HomePage, StoryDetail};
+    use chrono::NaiveDate;
+    use crate::{db::test_utils::MockDb, models::{Epic, FieldDefinition, FieldKind, FieldTarget, FieldValue, Priority, Sprint, SprintState, Status, Story}};
+    use crate::ui::{Board, EpicDetail, HomePage, ListState, SearchResults, SortColumn, StoryDetail};
+    use crate::ui::terminal::{HeadlessBackend, Terminal};
     use super::*;
 
+    fn prompts() -> Prompts {
+        Prompts::new(Terminal::shared(HeadlessBackend::new(80, 24)))
+    }
+
     #[test]
     fn should_start_on_home_page() {
         let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
-        let nav = Navigator::new(db);
+        let nav = Navigator::new(db, prompts());
 
         assert_eq!(nav.get_page_count(), 1);
 
@@ -126,7 +395,7 @@ mod tests {
     fn handle_action_should_navigate_pages() {
         let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
 
-        let mut nav = Navigator::new(db);
+        let mut nav = Navigator::new(db, prompts());
 
         nav.handle_action(Action::NavigateToEpicDetail { epic_id: 1 }).unwrap();
         assert_eq!(nav.get_page_count(), 2);
@@ -157,17 +426,183 @@ mod tests {
         assert_eq!(home_page.is_some(), true);
 
         nav.handle_action(Action::NavigateToPreviousPage).unwrap();
-        assert_eq!(nav.get_page_count(), 0);
+        assert_eq!(nav.get_page_count(), 1);
+    }
+
+    #[test]
+    fn handle_action_should_stay_on_home_when_going_back() {
+        let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
 
         nav.handle_action(Action::NavigateToPreviousPage).unwrap();
-        assert_eq!(nav.get_page_count(), 0);
+
+        assert_eq!(nav.get_page_count(), 1);
+        assert_eq!(nav.get_current_page().unwrap().as_any().downcast_ref::<HomePage>().is_some(), true);
+        assert_eq!(nav.forward.is_empty(), true);
+    }
+
+    #[test]
+    fn handle_action_should_go_to_any_item() {
+        let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+        let first_epic = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let second_epic = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), second_epic).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
+        nav.handle_action(Action::NavigateToEpicDetail { epic_id: first_epic }).unwrap();
+
+        nav.handle_action(Action::GoToItem { key: story_id.to_string() }).unwrap();
+        assert_eq!(nav.get_page_count(), 4);
+
+        let story_detail = nav.pages[3].as_any().downcast_ref::<StoryDetail>().unwrap();
+        assert_eq!((story_detail.epic_id, story_detail.story_id), (second_epic, story_id));
+        let epic_detail = nav.pages[2].as_any().downcast_ref::<EpicDetail>().unwrap();
+        assert_eq!(epic_detail.epic_id, second_epic);
+
+        nav.handle_action(Action::GoToItem { key: first_epic.to_string() }).unwrap();
+        assert_eq!(nav.get_page_count(), 5);
+
+        assert_eq!(nav.handle_action(Action::GoToItem { key: "99".to_owned() }).is_err(), true);
+        assert_eq!(nav.get_page_count(), 5);
+    }
+
+    #[test]
+    fn handle_action_should_go_back_and_forward_across_a_jump() {
+        let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+        let epic_id = db.create_epic(Epic::new("Auth".to_owned(), "".to_owned())).unwrap();
+        let story_id = db.create_story(Story::new("Login".to_owned(), "".to_owned()), epic_id).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
+        nav.handle_action(Action::NavigateToSprints).unwrap();
+        nav.handle_action(Action::GoToItem { key: story_id.to_string() }).unwrap();
+        nav.handle_action(Action::NavigateToPreviousPage).unwrap();
+        nav.handle_action(Action::NavigateToPreviousPage).unwrap();
+        assert_eq!(nav.breadcrumbs(), vec!["Home".to_owned(), "Sprints".to_owned()]);
+
+        nav.handle_action(Action::NavigateForward).unwrap();
+        nav.handle_action(Action::NavigateForward).unwrap();
+        assert_eq!(nav.breadcrumbs(), vec!["Home".to_owned(), "Sprints".to_owned(), "Epic 1 \"Auth\"".to_owned(), "Story 2 \"Login\"".to_owned()]);
+
+        nav.handle_action(Action::NavigateToPreviousPage).unwrap();
+        nav.handle_action(Action::GoToItem { key: story_id.to_string() }).unwrap();
+        assert_eq!(nav.get_page_count(), 4);
+    }
+
+    #[test]
+    fn handle_action_should_keep_forward_history() {
+        let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+        let epic_id = db.create_epic(Epic::new("Auth".to_owned(), "".to_owned())).unwrap();
+        let story_id = db.create_story(Story::new("Login".to_owned(), "".to_owned()), epic_id).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
+        nav.handle_action(Action::GoToItem { key: story_id.to_string() }).unwrap();
+        assert_eq!(nav.breadcrumbs(), vec!["Home".to_owned(), "Epic 1 \"Auth\"".to_owned(), "Story 2 \"Login\"".to_owned()]);
+
+        nav.handle_action(Action::NavigateToPreviousPage).unwrap();
+        nav.handle_action(Action::NavigateToPreviousPage).unwrap();
+        assert_eq!(nav.get_page_count(), 1);
+
+        nav.handle_action(Action::NavigateForward).unwrap();
+        nav.handle_action(Action::NavigateForward).unwrap();
+        nav.handle_action(Action::NavigateForward).unwrap();
+        assert_eq!(nav.get_page_count(), 3);
+        assert_eq!(nav.get_current_page().unwrap().as_any().downcast_ref::<StoryDetail>().is_some(), true);
+
+        nav.handle_action(Action::NavigateToBreadcrumb { level: 0 }).unwrap();
+        assert_eq!(nav.get_page_count(), 1);
+        nav.handle_action(Action::NavigateForward).unwrap();
+        assert_eq!(nav.get_current_page().unwrap().as_any().downcast_ref::<EpicDetail>().is_some(), true);
+
+        nav.handle_action(Action::NavigateToSprints).unwrap();
+        nav.handle_action(Action::NavigateForward).unwrap();
+        assert_eq!(nav.breadcrumbs(), vec!["Home".to_owned(), "Epic 1 \"Auth\"".to_owned(), "Sprints".to_owned()]);
+
+        assert_eq!(nav.handle_action(Action::NavigateToBreadcrumb { level: 3 }).is_err(), true);
+    }
+
+    #[test]
+    fn restore_should_reopen_saved_pages() {
+        let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+        let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
+        nav.handle_action(Action::GoToItem { key: story_id.to_string() }).unwrap();
+        let session = nav.session();
+
+        let mut restored = Navigator::new(Rc::clone(&db), prompts());
+        restored.restore(session.clone()).unwrap();
+
+        assert_eq!(restored.get_page_count(), 3);
+        assert_eq!(restored.session(), session);
+    }
+
+    #[test]
+    fn restore_should_drop_pages_of_missing_items() {
+        let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+        let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let mut list = ListState { selected: 3, ..ListState::default() };
+        list.sort.column = SortColumn::Priority;
+        list.sort.descending = true;
+        let session = Session {
+            pages: vec![
+                PageState::Home { list },
+                PageState::EpicDetail { epic_id, list },
+                PageState::StoryDetail { epic_id, story_id: 42 },
+                PageState::SprintPlanning { sprint_id: 7 },
+                PageState::Board { epic_id: Some(epic_id), selected: (1, 0) },
+            ]
+        };
+
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
+        nav.restore(session).unwrap();
+
+        let states = nav.session().pages;
+        assert_eq!(states.len(), 3);
+        assert_eq!(states[0], PageState::Home { list: ListState { selected: 3, ..ListState::default() } });
+        assert_eq!(states[1], PageState::EpicDetail { epic_id, list });
+        assert_eq!(states[2], PageState::Board { epic_id: Some(epic_id), selected: (1, 0) });
+    }
+
+    #[test]
+    fn palette_entries_should_list_page_commands_history_and_recent_items() {
+        let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+        let epic_id = db.create_epic(Epic::new("Auth".to_owned(), "".to_owned())).unwrap();
+        let story_id = db.create_story(Story::new("Login".to_owned(), "".to_owned()), epic_id).unwrap();
+        let other_id = db.create_story(Story::new("Logout".to_owned(), "".to_owned()), epic_id).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
+        nav.handle_action(Action::GoToItem { key: other_id.to_string() }).unwrap();
+        nav.handle_action(Action::GoToItem { key: story_id.to_string() }).unwrap();
+        db.delete_story(epic_id, other_id).unwrap();
+        nav.handle_action(Action::NavigateToPreviousPage).unwrap();
+
+        let entries = nav.palette_entries(&Keymap::default()).unwrap();
+        let labels = entries.iter().map(|entry| entry.label.as_str()).collect::<Vec<_>>();
+        assert_eq!(labels, vec![
+            "previous [p]",
+            "update epic [u]",
+            "delete epic [d]",
+            "set field [f]",
+            "board [b]",
+            "create story [c]",
+            "help [?]",
+            "back [ctrl+b]",
+            "forward [ctrl+f]",
+            "go to Home [^1]",
+            "go to Epic 1 \"Auth\" [^2]",
+            "go to Story 3 [^3]",
+            "recent: Story 2 \"Login\"",
+            "recent: Epic 1 \"Auth\"",
+        ]);
+        assert_eq!(entries[12].action, Action::GoToItem { key: story_id.to_string() });
     }
 
     #[test]
     fn handle_action_should_clear_pages_on_exit() {
         let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
 
-        let mut nav = Navigator::new(db);
+        let mut nav = Navigator::new(db, prompts());
 
         nav.handle_action(Action::NavigateToEpicDetail { epic_id: 1 }).unwrap();
         nav.handle_action(Action::NavigateToStoryDetail { epic_id: 1, story_id: 2 }).unwrap();
@@ -176,14 +611,27 @@ mod tests {
         assert_eq!(nav.get_page_count(), 0);
     }
 
+    #[test]
+    fn handle_action_should_open_help_page() {
+        let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+
+        let mut nav = Navigator::new(db, prompts());
+
+        nav.handle_action(Action::ShowHelp).unwrap();
+
+        assert_eq!(nav.get_page_count(), 2);
+        assert_eq!(nav.get_current_page().unwrap().as_any().downcast_ref::<HelpPage>().is_some(), true);
+        assert_eq!(nav.breadcrumbs(), vec!["Home".to_owned(), "Help".to_owned()]);
+    }
+
     #[test]
     fn handle_action_should_handle_create_epic() {
         let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
 
-        let mut nav = Navigator::new(Rc::clone(&db));
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
 
-        let mut prompts = Prompts::new();
-        prompts.create_epic = Box::new(|| Epic::new("name".to_owned(), "description".to_owned()));
+        let mut prompts = prompts();
+        prompts.create_epic = Box::new(|| Some(Epic::new("name".to_owned(), "description".to_owned())));
 
         nav.set_prompts(prompts);
 
@@ -202,9 +650,9 @@ mod tests {
         let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
         let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
 
-        let mut nav = Navigator::new(Rc::clone(&db));
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
 
-        let mut prompts = Prompts::new();
+        let mut prompts = prompts();
         prompts.update_status = Box::new(|| Some(Status::InProgress));
 
         nav.set_prompts(prompts);
@@ -220,9 +668,9 @@ mod tests {
         let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
         let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
 
-        let mut nav = Navigator::new(Rc::clone(&db));
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
 
-        let mut prompts = Prompts::new();
+        let mut prompts = prompts();
         prompts.delete_epic = Box::new(|| true);
 
         nav.set_prompts(prompts);
@@ -238,10 +686,10 @@ mod tests {
         let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
         let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
 
-        let mut nav = Navigator::new(Rc::clone(&db));
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
 
-        let mut prompts = Prompts::new();
-        prompts.create_story = Box::new(|| Story::new("name".to_owned(), "description".to_owned()));
+        let mut prompts = prompts();
+        prompts.create_story = Box::new(|_| Some(Story::new("name".to_owned(), "description".to_owned())));
 
         nav.set_prompts(prompts);
 
@@ -261,9 +709,9 @@ mod tests {
         let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
         let story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
 
-        let mut nav = Navigator::new(Rc::clone(&db));
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
 
-        let mut prompts = Prompts::new();
+        let mut prompts = prompts();
         prompts.update_status = Box::new(|| Some(Status::InProgress));
 
         nav.set_prompts(prompts);
@@ -274,15 +722,38 @@ mod tests {
         assert_eq!(db_state.stories.get(&story_id).unwrap().status, Status::InProgress);
     }
 
+    #[test]
+    fn handle_action_should_handle_story_priority_and_due_date() {
+        let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+        let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
+
+        let mut prompts = prompts();
+        prompts.update_priority = Box::new(|| Some(Priority::Highest));
+        prompts.update_due_date = Box::new(|| Some(NaiveDate::from_ymd_opt(2024, 3, 1)));
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::UpdateStoryPriority { story_id }).unwrap();
+        nav.handle_action(Action::UpdateStoryDueDate { story_id }).unwrap();
+
+        let db_state = db.read_db().unwrap();
+        let story = db_state.stories.get(&story_id).unwrap();
+        assert_eq!(story.priority, Priority::Highest);
+        assert_eq!(story.due_date, NaiveDate::from_ymd_opt(2024, 3, 1));
+    }
+
     #[test]
     fn handle_action_should_handle_delete_story() {
         let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
         let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
         let story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
 
-        let mut nav = Navigator::new(Rc::clone(&db));
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
 
-        let mut prompts = Prompts::new();
+        let mut prompts = prompts();
         prompts.delete_story = Box::new(|| true);
 
         nav.set_prompts(prompts);
@@ -292,4 +763,95 @@ mod tests {
         let db_state = db.read_db().unwrap();
         assert_eq!(db_state.stories.len(), 0);
     }
+
+    #[test]
+    fn handle_action_should_handle_search_stories() {
+        let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
+
+        let mut prompts = prompts();
+        prompts.search = Box::new(|| Some("type:bug".to_owned()));
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::SearchStories).unwrap();
+        assert_eq!(nav.get_page_count(), 2);
+
+        let current_page = nav.get_current_page().unwrap();
+        let search_results = current_page.as_any().downcast_ref::<SearchResults>().unwrap();
+        assert_eq!(search_results.query, "type:bug".to_owned());
+    }
+
+    #[test]
+    fn handle_action_should_handle_custom_fields() {
+        let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+        let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
+
+        let mut prompts = prompts();
+        prompts.create_field_definition = Box::new(|| Some(FieldDefinition::new("points".to_owned(), FieldKind::Number, FieldTarget::Stories)));
+        prompts.update_field = Box::new(|fields| Some((fields[0].name.clone(), "8".to_owned())));
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::CreateFieldDefinition).unwrap();
+        nav.handle_action(Action::UpdateStoryField { story_id }).unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.custom_fields.len(), 1);
+        assert_eq!(db_state.stories.get(&story_id).unwrap().fields.get("points"), Some(&FieldValue::Number(8.0)));
+    }
+
+    #[test]
+    fn handle_action_should_handle_close_sprint() {
+        let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+        let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
+
+        let mut prompts = prompts();
+        prompts.create_sprint = Box::new(|| Some(Sprint::new(
+            "sprint".to_owned(),
+            "".to_owned(),
+            NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 1, 14).unwrap()
+        )));
+        prompts.close_sprint = Box::new(|next_sprint| {
+            assert_eq!(next_sprint.is_some(), true);
+            Some(SprintRollover::NextSprint)
+        });
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::CreateSprint).unwrap();
+        nav.handle_action(Action::CreateSprint).unwrap();
+        nav.handle_action(Action::StartSprint { sprint_id: 1 }).unwrap();
+        nav.handle_action(Action::AddStoryToSprint { sprint_id: 1, story_id }).unwrap();
+        nav.handle_action(Action::CloseSprint { sprint_id: 1 }).unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.sprints.get(&1).unwrap().state, SprintState::Closed);
+        assert_eq!(db_state.stories.get(&story_id).unwrap().sprint_id, Some(2));
+    }
+
+    #[test]
+    fn handle_action_should_handle_set_story_status() {
+        let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+        let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db), prompts());
+
+        nav.handle_action(Action::NavigateToBoard { epic_id: Some(epic_id) }).unwrap();
+        assert_eq!(nav.get_current_page().unwrap().as_any().downcast_ref::<Board>().is_some(), true);
+
+        nav.handle_action(Action::SetStoryStatus { story_id, status: Status::Resolved }).unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.stories.get(&story_id).unwrap().status, Status::Resolved);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,145 @@
+use itertools::Itertools;
+use crate::models::{DBState, ItemId};
+
+/// Renders the project as Markdown: a table of contents, then every epic with its
+/// status, description and a checklist of its stories, done ones ticked.
+///
+/// Epics and stories are sorted by id and nothing depends on the clock, so the same
+/// state always gives the same document and committed reports diff cleanly.
+pub fn markdown_report(db_state: &DBState) -> String {
+    let epics = db_state.epics.iter().sorted_by_key(|(id, _)| **id).collect::<Vec<_>>();
+    let mut lines = vec!["# Project report".to_owned(), String::new()];
+
+    if epics.is_empty() {
+        lines.push("_No epics._".to_owned());
+    } else {
+        lines.push("## Contents".to_owned());
+        lines.push(String::new());
+        for (id, _) in &epics {
+            let title = epic_title(db_state, **id);
+            lines.push(format!("- [{}](#{})", escape(&title), anchor(&title)));
+        }
+    }
+
+    for (id, epic) in epics {
+        let story_ids = epic.stories.iter().filter(|id| db_state.stories.contains_key(id)).sorted().collect::<Vec<_>>();
+        let done = story_ids.iter().filter(|id| db_state.stories[id].status.is_done()).count();
+
+        lines.push(String::new());
+        lines.push(format!("## {}", escape(&epic_title(db_state, *id))));
+        lines.push(String::new());
+        lines.push(format!("**Status:** {} | **Done:** {} of {} stories", epic.status, done, story_ids.len()));
+        if !epic.description.trim().is_empty() {
+            lines.push(String::new());
+            lines.push(epic.description.trim().to_owned());
+        }
+        lines.push(String::new());
+
+        if story_ids.is_empty() {
+            lines.push("_No stories._".to_owned());
+        }
+        for story_id in story_ids {
+            let story = &db_state.stories[story_id];
+            let mut details = vec![story.issue_type.to_string(), story.status.to_string()];
+            if let Some(points) = story.points {
+                details.push(format!("{} points", points));
+            }
+            if let Some(due_date) = story.due_date {
+                details.push(format!("due {}", due_date.format("%Y-%m-%d")));
+            }
+            lines.push(format!(
+                "- [{}] #{} {} ({})",
+                if story.status.is_done() { "x" } else { " " },
+                db_state.item_key(*story_id),
+                escape(&story.name),
+                details.join(", ")
+            ));
+        }
+    }
+
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+fn epic_title(db_state: &DBState, id: ItemId) -> String {
+    format!("Epic {}: {}", db_state.item_key(id), db_state.epics[&id].name)
+}
+
+/// Escapes characters that would otherwise start Markdown formatting or links.
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|') {
+            escaped.push('\\');
+        }
+        escaped.push(if c == '\n' { ' ' } else { c });
+    }
+    escaped
+}
+
+/// The anchor wikis like GitHub generate for a heading: lowercase, spaces turned
+/// into dashes and other punctuation dropped.
+fn anchor(title: &str) -> String {
+    title
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::db::{test_utils::MockDb, ProjectsDatabase};
+    use crate::models::{Epic, IssueType, Status, Story};
+
+    #[test]
+    fn markdown_report_should_list_epics_and_story_checklists() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+        let epic_id = db.create_epic(Epic::new("Auth *v2*".to_owned(), "Sign in and out.\n".to_owned())).unwrap();
+        let first = db.create_story(Story::new("Login".to_owned(), "".to_owned()), epic_id).unwrap();
+        let second = db.create_story(Story::new("Logout [web]".to_owned(), "".to_owned()).with_issue_type(IssueType::new("Bug")), epic_id).unwrap();
+        db.update_story_status(first, Status::Resolved).unwrap();
+        db.update_story_points(first, Some(3)).unwrap();
+        db.update_story_due_date(second, NaiveDate::from_ymd_opt(2024, 5, 1)).unwrap();
+        db.create_epic(Epic::new("Billing".to_owned(), "".to_owned())).unwrap();
+
+        let report = markdown_report(&db.read_db().unwrap());
+
+        assert_eq!(report, "# Project report
+
+## Contents
+
+- [Epic 1: Auth \\*v2\\*](#epic-1-auth-v2)
+- [Epic 4: Billing](#epic-4-billing)
+
+## Epic 1: Auth \\*v2\\*
+
+**Status:** OPEN | **Done:** 1 of 2 stories
+
+Sign in and out.
+
+- [x] #2 Login (STORY, RESOLVED, 3 points)
+- [ ] #3 Logout \\[web\\] (BUG, OPEN, due 2024-05-01)
+
+## Epic 4: Billing
+
+**Status:** OPEN | **Done:** 0 of 0 stories
+
+_No stories._
+");
+        assert_eq!(markdown_report(&db.read_db().unwrap()), report);
+    }
+
+    #[test]
+    fn markdown_report_should_handle_empty_projects() {
+        let db = ProjectsDatabase { database: Box::new(MockDb::new()) };
+
+        assert_eq!(markdown_report(&db.read_db().unwrap()), "# Project report\n\n_No epics._\n");
+    }
+}
@@ -0,0 +1,155 @@
+use anyhow::Result;
+use itertools::Itertools;
+use crate::models::{DBState, IssueType, ItemId, Status, Story};
+
+/// Story filter built from a query such as `type:bug status:open labels:ui login`.
+/// `type` takes any of the project's issue types. Any other `key:value` word filters
+/// on the custom field named `key`; words without a prefix are matched against the
+/// story name and description.
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct StoryFilter {
+    pub issue_type: Option<IssueType>,
+    pub status: Option<Status>,
+    pub fields: Vec<(String, String)>,
+    pub text: Option<String>
+}
+
+impl StoryFilter {
+    pub fn parse(query: &str, db_state: &DBState) -> Result<Self> {
+        let mut filter = StoryFilter::default();
+        let mut words = vec![];
+
+        for word in query.split_whitespace() {
+            match word.split_once(':') {
+                Some(("type", value)) => filter.issue_type = Some(db_state.issue_type(value)?),
+                Some(("status", value)) => filter.status = Some(value.parse()?),
+                Some((field, value)) if !field.is_empty() && !value.is_empty() => {
+                    filter.fields.push((field.to_owned(), value.to_owned()))
+                }
+                _ => words.push(word)
+            }
+        }
+
+        if !words.is_empty() {
+            filter.text = Some(words.join(" ").to_lowercase());
+        }
+
+        Ok(filter)
+    }
+
+    pub fn matches(&self, story: &Story) -> bool {
+        if let Some(issue_type) = &self.issue_type {
+            if &story.issue_type != issue_type {
+                return false;
+            }
+        }
+        if let Some(status) = &self.status {
+            if &story.status != status {
+                return false;
+            }
+        }
+        for (field, value) in &self.fields {
+            let matches_field = story.fields
+                .iter()
+                .any(|(name, field_value)| name.eq_ignore_ascii_case(field) && field_value.matches(value));
+            if !matches_field {
+                return false;
+            }
+        }
+        if let Some(text) = &self.text {
+            return story.name.to_lowercase().contains(text)
+                || story.description.to_lowercase().contains(text);
+        }
+        true
+    }
+}
+
+pub struct SearchHit<'a> {
+    pub epic_id: ItemId,
+    pub story_id: ItemId,
+    pub story: &'a Story
+}
+
+/// Returns every story matching the filter, ordered by story id.
+pub fn search_stories<'a>(db_state: &'a DBState, filter: &StoryFilter) -> Vec<SearchHit<'a>> {
+    db_state.epics
+        .iter()
+        .flat_map(|(epic_id, epic)| epic.stories.iter().map(move |story_id| (*epic_id, *story_id)))
+        .filter_map(|(epic_id, story_id)| {
+            db_state.stories
+                .get(&story_id)
+                .filter(|story| filter.matches(story))
+                .map(|story| SearchHit { epic_id, story_id, story })
+        })
+        .sorted_by_key(|hit| hit.story_id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use super::*;
+    use crate::models::{Epic, FieldValue, IssueTypeConfig};
+
+    fn db_state() -> DBState {
+        let mut epic = Epic::new("epic".to_owned(), "".to_owned());
+        epic.stories = vec![2, 3, 4];
+
+        let mut stories = BTreeMap::new();
+        let mut login_page = Story::new("Login page".to_owned(), "".to_owned());
+        login_page.fields.insert("Labels".to_owned(), FieldValue::MultiSelect(vec!["ui".to_owned(), "auth".to_owned()]));
+        stories.insert(2, login_page);
+        stories.insert(3, Story::new("Login crash".to_owned(), "".to_owned()).with_issue_type(IssueType::new("Bug")));
+        stories.insert(4, Story::new("Spike on caching".to_owned(), "".to_owned()).with_issue_type(IssueType::new("Spike")));
+
+        let mut epics = BTreeMap::new();
+        epics.insert(1, epic);
+
+        DBState { last_item_id: 4, epics, stories, ..DBState::default() }
+    }
+
+    #[test]
+    fn parse_should_extract_type_status_and_text() {
+        let filter = StoryFilter::parse("type:bug status:in-progress Login  crash", &DBState::default()).unwrap();
+
+        assert_eq!(filter.issue_type, Some(IssueType::new("Bug")));
+        assert_eq!(filter.status, Some(Status::InProgress));
+        assert_eq!(filter.text, Some("login crash".to_owned()));
+
+        let filter = StoryFilter::parse("labels:ui", &DBState::default()).unwrap();
+        assert_eq!(filter.fields, vec![("labels".to_owned(), "ui".to_owned())]);
+        assert_eq!(filter.text, None);
+    }
+
+    #[test]
+    fn parse_should_only_accept_the_project_issue_types() {
+        let mut state = DBState::default();
+        assert_eq!(StoryFilter::parse("type:feature", &state).is_err(), true);
+
+        state.issue_types.push(IssueTypeConfig::new("Feature", "F"));
+        let filter = StoryFilter::parse("type:FEATURE", &state).unwrap();
+        assert_eq!(filter.issue_type, Some(IssueType::new("Feature")));
+    }
+
+    #[test]
+    fn search_stories_should_filter_by_type() {
+        let state = db_state();
+
+        let filter = StoryFilter::parse("type:bug", &state).unwrap();
+        let ids = search_stories(&state, &filter).iter().map(|hit| hit.story_id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![3]);
+
+        let filter = StoryFilter::parse("login", &state).unwrap();
+        let ids = search_stories(&state, &filter).iter().map(|hit| hit.story_id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![2, 3]);
+
+        let filter = StoryFilter::parse("labels:auth", &state).unwrap();
+        let ids = search_stories(&state, &filter).iter().map(|hit| hit.story_id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![2]);
+
+        let filter = StoryFilter::default();
+        let hits = search_stories(&state, &filter);
+        assert_eq!(hits.len(), 3);
+        assert_eq!(hits[0].epic_id, 1);
+    }
+}
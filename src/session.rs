@@ -0,0 +1,82 @@
+use std::fs;
+use std::io::ErrorKind;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use crate::models::ItemId;
+use crate::ui::ListState;
+
+/// What a page needs to be reopened as it was left.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(tag = "page", rename_all = "snake_case")]
+pub enum PageState {
+    Home { list: ListState },
+    EpicDetail { epic_id: ItemId, list: ListState },
+    StoryDetail { epic_id: ItemId, story_id: ItemId },
+    SearchResults { query: String },
+    Sprints,
+    SprintPlanning { sprint_id: u32 },
+    Board { epic_id: Option<ItemId>, selected: (usize, usize) },
+    Help { offset: usize },
+}
+
+/// The page stack saved on exit, from the home page to the page that was open.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct Session {
+    pub pages: Vec<PageState>
+}
+
+impl Session {
+    pub fn load(file_path: &str) -> Result<Session> {
+        let contents = match fs::read_to_string(file_path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Session::default()),
+            Err(error) => return Err(error).with_context(|| anyhow!("couldn't read session file '{}'", file_path))
+        };
+
+        serde_json::from_str(&contents).with_context(|| anyhow!("invalid session file '{}'", file_path))
+    }
+
+    pub fn save(&self, file_path: &str) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(file_path, contents).with_context(|| anyhow!("couldn't write session file '{}'", file_path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::SortColumn;
+
+    #[test]
+    fn load_should_return_empty_session_for_missing_file() {
+        assert_eq!(Session::load("INVALID_PATH").unwrap(), Session::default());
+    }
+
+    #[test]
+    fn save_and_load_should_round_trip() {
+        let tmpfile = tempfile::NamedTempFile::new().unwrap();
+        let path = tmpfile.path().to_str().unwrap();
+        let mut list = ListState { selected: 4, ..ListState::default() };
+        list.sort.column = SortColumn::Updated;
+        list.sort.descending = true;
+        let session = Session {
+            pages: vec![
+                PageState::Home { list },
+                PageState::EpicDetail { epic_id: 1, list },
+                PageState::Board { epic_id: Some(1), selected: (2, 0) },
+            ]
+        };
+
+        assert_eq!(session.save(path).is_ok(), true);
+        assert_eq!(Session::load(path).unwrap(), session);
+    }
+
+    #[test]
+    fn load_should_fail_with_invalid_json() {
+        let tmpfile = tempfile::NamedTempFile::new().unwrap();
+        fs::write(tmpfile.path(), r#"{ "pages": [{ "page": "unknown" }] }"#).unwrap();
+
+        assert_eq!(Session::load(tmpfile.path().to_str().unwrap()).is_err(), true);
+    }
+}
@@ -0,0 +1,239 @@
+use crate::ui::fuzzy::fuzzy_filter;
+use crate::ui::terminal::{Color, Key, Line, SharedTerminal, Style};
+
+const PALETTE_ROWS: usize = 10;
+
+/// Single-line text input. Returns `None` when the dialog is cancelled with Esc.
+pub fn text_input(terminal: &SharedTerminal, title: &str, label: &str) -> Option<String> {
+    input_with_error(terminal, title, label, None)
+}
+
+/// Text input that asks again until `parse` accepts the trimmed value, showing the
+/// error under the label, so a typo doesn't cancel the action the input is for.
+pub fn parsed_input<T>(terminal: &SharedTerminal, title: &str, label: &str, parse: impl Fn(&str) -> Result<T, String>) -> Option<T> {
+    let mut error = None;
+    loop {
+        let value = input_with_error(terminal, title, label, error.as_deref())?;
+        match parse(value.trim()) {
+            Ok(value) => return Some(value),
+            Err(message) => error = Some(message)
+        }
+    }
+}
+
+fn input_with_error(terminal: &SharedTerminal, title: &str, label: &str, error: Option<&str>) -> Option<String> {
+    let mut value = String::new();
+
+    loop {
+        let mut body = vec![Line::from(label)];
+        if let Some(error) = error {
+            body.push(Line::styled(error, Style::fg(Color::Red)));
+        }
+        body.extend([
+            Line::styled(format!("> {}_", value), Style::bold()),
+            Line::default(),
+            Line::from("[enter] confirm | [esc] cancel"),
+        ]);
+        terminal.borrow_mut().draw_dialog(title, &body).ok()?;
+
+        match terminal.borrow_mut().read_key().ok()? {
+            Key::Enter => return Some(value),
+            Key::Esc | Key::Ctrl('c') => return None,
+            Key::Backspace => {
+                value.pop();
+            }
+            Key::Char(c) => value.push(c),
+            _ => {}
+        }
+    }
+}
+
+/// Yes/no question; anything but `y` counts as no.
+pub fn confirm(terminal: &SharedTerminal, title: &str, message: &str) -> bool {
+    let body = [
+        Line::from(message),
+        Line::default(),
+        Line::from("[y] yes | [n] no"),
+    ];
+    if terminal.borrow_mut().draw_dialog(title, &body).is_err() {
+        return false;
+    }
+
+    matches!(terminal.borrow_mut().read_key(), Ok(Key::Char('y')) | Ok(Key::Char('Y')))
+}
+
+/// Arrow-key selection from a list of options. Returns the index of the chosen option.
+/// When there are more options than rows, the shown ones scroll with the selection.
+pub fn select(terminal: &SharedTerminal, title: &str, options: &[String]) -> Option<usize> {
+    if options.is_empty() {
+        return None;
+    }
+
+    let mut selected: usize = 0;
+    let mut first = 0;
+
+    loop {
+        // The frame, a blank line and the key hints take four rows.
+        let rows = terminal.borrow().size().ok()?.1.saturating_sub(4).max(1);
+        first = first.min(selected).max((selected + 1).saturating_sub(rows));
+        let mut body = options
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(rows)
+            .map(|(idx, option)| {
+                if idx == selected {
+                    Line::styled(format!("> {}", option), Style::reversed())
+                } else {
+                    Line::from(format!("  {}", option))
+                }
+            })
+            .collect::<Vec<_>>();
+        body.push(Line::default());
+        body.push(Line::from("[up/down] choose | [enter] confirm | [esc] cancel"));
+        terminal.borrow_mut().draw_dialog(title, &body).ok()?;
+
+        match terminal.borrow_mut().read_key().ok()? {
+            Key::Up | Key::Char('k') => selected = selected.saturating_sub(1),
+            Key::Down | Key::Char('j') => selected = (selected + 1).min(options.len() - 1),
+            Key::Char(c) if c.is_ascii_digit() => {
+                let idx = (c as usize - '0' as usize).wrapping_sub(1);
+                if idx < options.len() {
+                    return Some(idx);
+                }
+            }
+            Key::Enter => return Some(selected),
+            Key::Esc | Key::Ctrl('c') => return None,
+            _ => {}
+        }
+    }
+}
+
+/// Command palette: typing filters `options` by fuzzy match, best matches first.
+/// Returns the index of the chosen option in `options`.
+pub fn palette(terminal: &SharedTerminal, title: &str, options: &[String]) -> Option<usize> {
+    let mut query = String::new();
+    let mut selected = 0;
+
+    loop {
+        let matches = fuzzy_filter(&query, options);
+        let shown = matches.len().min(PALETTE_ROWS);
+        selected = selected.min(shown.saturating_sub(1));
+
+        let mut body = vec![Line::styled(format!("> {}_", query), Style::bold()), Line::default()];
+        body.extend(matches.iter().take(shown).enumerate().map(|(idx, option)| {
+            if idx == selected {
+                Line::styled(format!("> {}", options[*option]), Style::reversed())
+            } else {
+                Line::from(format!("  {}", options[*option]))
+            }
+        }));
+        if matches.is_empty() {
+            body.push(Line::from("  no matches"));
+        }
+        body.push(Line::default());
+        body.push(Line::from("[up/down] choose | [enter] run | [esc] cancel"));
+        terminal.borrow_mut().draw_dialog(title, &body).ok()?;
+
+        match terminal.borrow_mut().read_key().ok()? {
+            Key::Up => selected = selected.saturating_sub(1),
+            Key::Down => selected += 1,
+            Key::Enter => {
+                if let Some(option) = matches.get(selected) {
+                    return Some(*option);
+                }
+            }
+            Key::Esc | Key::Ctrl('c') => return None,
+            Key::Backspace => {
+                query.pop();
+                selected = 0;
+            }
+            Key::Char(c) => {
+                query.push(c);
+                selected = 0;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use super::*;
+    use crate::ui::terminal::{HeadlessBackend, Terminal};
+
+    #[test]
+    fn text_input_should_support_editing_and_cancel() {
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24)
+            .with_text("nane")
+            .with_keys(vec![Key::Backspace, Key::Backspace])
+            .with_text("me")
+            .with_keys(vec![Key::Enter])
+            .with_text("abc")
+            .with_keys(vec![Key::Esc]));
+
+        assert_eq!(text_input(&terminal, "Title", "Name:"), Some("name".to_owned()));
+        assert_eq!(text_input(&terminal, "Title", "Name:"), None);
+        assert_eq!(text_input(&terminal, "Title", "Name:"), None);
+    }
+
+    #[test]
+    fn select_should_return_chosen_index() {
+        let options = vec!["one".to_owned(), "two".to_owned(), "three".to_owned()];
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24)
+            .with_keys(vec![Key::Down, Key::Down, Key::Down, Key::Up, Key::Enter])
+            .with_text("3")
+            .with_keys(vec![Key::Esc]));
+
+        assert_eq!(select(&terminal, "Title", &options), Some(1));
+        assert_eq!(select(&terminal, "Title", &options), Some(2));
+        assert_eq!(select(&terminal, "Title", &options), None);
+        assert_eq!(select(&terminal, "Title", &[]), None);
+    }
+
+    #[test]
+    fn palette_should_filter_as_you_type() {
+        let options = vec!["create epic".to_owned(), "search stories".to_owned(), "sprints".to_owned()];
+        let backend = HeadlessBackend::new(80, 24)
+            .with_text("sx")
+            .with_keys(vec![Key::Enter, Key::Backspace, Key::Down, Key::Enter])
+            .with_text("zz")
+            .with_keys(vec![Key::Esc]);
+        let frames = Rc::clone(&backend.frames);
+        let terminal = Terminal::shared(backend);
+
+        assert_eq!(palette(&terminal, "Title", &options), Some(2));
+        assert_eq!(palette(&terminal, "Title", &options), None);
+
+        let frames = frames.borrow();
+        assert_eq!(frames[0].text().join("\n").contains("> create epic"), true);
+        assert_eq!(frames.last().unwrap().text().join("\n").contains("no matches"), true);
+    }
+
+    #[test]
+    fn select_should_scroll_to_the_selected_option() {
+        let options = (1..=10).map(|n| format!("sprint {}", n)).collect::<Vec<_>>();
+        let backend = HeadlessBackend::new(80, 8)
+            .with_keys(vec![Key::Down; 6])
+            .with_keys(vec![Key::Enter]);
+        let frames = Rc::clone(&backend.frames);
+        let terminal = Terminal::shared(backend);
+
+        assert_eq!(select(&terminal, "Title", &options), Some(6));
+
+        let screen = frames.borrow().last().unwrap().text().join("\n");
+        assert_eq!(screen.contains("> sprint 7"), true);
+        assert_eq!(screen.contains("sprint 4"), true);
+        assert_eq!(screen.contains("sprint 3"), false);
+    }
+
+    #[test]
+    fn confirm_should_only_accept_y() {
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24).with_text("yn"));
+
+        assert_eq!(confirm(&terminal, "Title", "Sure?"), true);
+        assert_eq!(confirm(&terminal, "Title", "Sure?"), false);
+        assert_eq!(confirm(&terminal, "Title", "Sure?"), false);
+    }
+}
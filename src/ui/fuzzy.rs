@@ -0,0 +1,83 @@
+use itertools::Itertools;
+
+/// Scores how well `query` matches `text` as a case-insensitive subsequence, ignoring
+/// spaces in the query. `None` when some query character is missing; higher scores
+/// for consecutive characters and matches at the start of a word.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text = text.to_lowercase().chars().collect::<Vec<_>>();
+    let query = query.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect::<Vec<_>>();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+
+    for (query_idx, c) in query.iter().enumerate() {
+        let mut candidates = (next..text.len()).filter(|idx| text[*idx] == *c);
+        let first = candidates.next()?;
+        // A later occurrence that continues a run or starts a word scores better, as
+        // long as the rest of the query still matches after it.
+        let idx = std::iter::once(first)
+            .chain(candidates)
+            .find(|idx| {
+                let preferred = previous.is_some_and(|previous| previous + 1 == *idx) || *idx == 0 || !text[idx - 1].is_alphanumeric();
+                preferred && is_subsequence(&query[query_idx + 1..], &text[idx + 1..])
+            })
+            .unwrap_or(first);
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == idx) {
+            score += 4;
+        }
+        if idx == 0 || !text[idx - 1].is_alphanumeric() {
+            score += 2;
+        }
+        previous = Some(idx);
+        next = idx + 1;
+    }
+    Some(score)
+}
+
+fn is_subsequence(query: &[char], text: &[char]) -> bool {
+    let mut text = text.iter();
+    query.iter().all(|c| text.any(|t| t == c))
+}
+
+/// Indices of the `options` matching `query`, best match first. Options with equal
+/// scores keep their order, so an empty query lists everything as given.
+pub fn fuzzy_filter(query: &str, options: &[String]) -> Vec<usize> {
+    options
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, option)| fuzzy_score(query, option).map(|score| (idx, score)))
+        .sorted_by_key(|(_, score)| -score)
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_score_should_match_subsequences() {
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        assert_eq!(fuzzy_score("ce", "create epic").is_some(), true);
+        assert_eq!(fuzzy_score("CE", "create epic").is_some(), true);
+        assert_eq!(fuzzy_score("ec", "create"), None);
+        assert_eq!(fuzzy_score("ab", "a_b a").is_some(), true);
+        assert_eq!(fuzzy_score("x", "create epic"), None);
+    }
+
+    #[test]
+    fn fuzzy_score_should_prefer_word_starts_and_runs() {
+        assert_eq!(fuzzy_score("ep", "epic") > fuzzy_score("ep", "deep"), true);
+        assert_eq!(fuzzy_score("ce", "create epic") > fuzzy_score("ce", "close sprint"), true);
+    }
+
+    #[test]
+    fn fuzzy_filter_should_sort_by_score() {
+        let options = vec!["delete story".to_owned(), "set due date".to_owned(), "update story".to_owned()];
+
+        assert_eq!(fuzzy_filter("", &options), vec![0, 1, 2]);
+        assert_eq!(fuzzy_filter("ds", &options), vec![0, 2]);
+        assert_eq!(fuzzy_filter("ups", &options), vec![2]);
+    }
+}
@@ -1,4 +1,11 @@
 mod pages;
+pub mod dialogs;
+pub mod fuzzy;
 pub mod prompts;
+pub mod table;
+pub mod terminal;
+pub mod text;
+pub mod theme;
+pub mod tui;
 
 pub use pages::*;
\ No newline at end of file
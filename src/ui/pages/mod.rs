@@ -1,58 +1,169 @@
 use std::any::Any;
 use std::rc::Rc;
 use anyhow::{anyhow, Result};
+use chrono::Local;
 use itertools::Itertools;
 use crate::db::ProjectsDatabase;
-use crate::models::Action;
+use crate::keymap::{ActionHelp, Command, Keymap, PageKind};
+use crate::models::{Action, DBState, ItemId, SprintState, Status};
+use crate::search::{search_stories, StoryFilter};
+use crate::session::PageState;
+use crate::ui::terminal::{Key, Line, Screen, Span, Style};
+use crate::ui::table::{Column, Table};
+use crate::ui::text::get_column_string;
 
 mod page_helpers;
 use page_helpers::*;
+pub use page_helpers::{ListState, SortColumn};
+
+/// What a page did with a key press.
+#[derive(Debug, PartialEq, Eq)]
+pub enum KeyOutcome {
+    /// The key isn't bound on this page and should be treated as command input.
+    Ignored,
+    /// The key only changed the page's view state, e.g. the selected row.
+    Handled,
+    Action(Action),
+}
+
+/// Parses the commands that work on every page: `g 42`, `g PROJ-42` or `#42` to open
+/// an item by key and `^2` to jump to a breadcrumb level, counting from 1 for the
+/// home page.
+pub fn parse_global_command(input: &str) -> Option<Action> {
+    if let Some(level) = input.strip_prefix('^') {
+        return match level.trim().parse::<usize>() {
+            Ok(level) if level > 0 => Some(Action::NavigateToBreadcrumb { level: level - 1 }),
+            _ => None
+        };
+    }
+    let key = input
+        .strip_prefix('#')
+        .or_else(|| input.strip_prefix('g'))?
+        .trim();
+    let looks_like_key = key.ends_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+    looks_like_key.then(|| Action::GoToItem { key: key.to_owned() })
+}
 
 pub trait Page {
-    fn draw_page(&self) -> Result<()>;
+    fn draw_page(&self, screen: &mut Screen) -> Result<()>;
+    /// Typed input the keymap doesn't cover, like an id to open.
     fn handle_input(&self, input: &str) -> Result<Option<Action>>;
+    fn kind(&self) -> PageKind;
+    /// The action a bound command stands for on this page, `None` when it doesn't
+    /// apply, e.g. starting a sprint that already runs.
+    fn command_action(&self, command: Command) -> Result<Option<Action>>;
+    fn handle_key(&mut self, _key: Key) -> Result<KeyOutcome> {
+        Ok(KeyOutcome::Ignored)
+    }
+    /// Short label of the page in the breadcrumb line, e.g. `Epic 3 "Auth"`.
+    fn breadcrumb(&self) -> String;
+    /// View state saved with the session, see `Navigator::restore`.
+    fn state(&self) -> PageState;
     fn as_any(&self) -> &dyn Any;
 }
 
+const EPIC_SORT_COLUMNS: &[SortColumn] = &[SortColumn::Id, SortColumn::Name, SortColumn::Status, SortColumn::Updated];
+const STORY_SORT_COLUMNS: &[SortColumn] = &[SortColumn::Id, SortColumn::Name, SortColumn::Status, SortColumn::Priority, SortColumn::Updated];
+const HELP_PAGE_ROWS: usize = 10;
+
 pub struct HomePage {
-    pub db: Rc<ProjectsDatabase>
+    pub db: Rc<ProjectsDatabase>,
+    list: ListView
 }
-impl Page for HomePage {
-    fn draw_page(&self) -> Result<()> {
-        println!("--------------------------- EPICS ---------------------------");
-        println!("      id       |            name              |   status     ");
+
+impl HomePage {
+    pub fn new(db: Rc<ProjectsDatabase>) -> Self {
+        HomePage { db, list: ListView::new(EPIC_SORT_COLUMNS) }
+    }
+
+    pub fn with_list_state(mut self, state: ListState) -> Self {
+        self.list = self.list.with_state(state);
+        self
+    }
+
+    fn epic_ids(&self) -> Result<Vec<ItemId>> {
         let epics = self.db.read_db()?.epics;
+        Ok(sorted_ids(&epics, epics.keys().copied(), self.list.sort))
+    }
+}
 
-        for id in epics.keys().sorted() {
-            let epic = &epics[id];
-            let id_col = get_column_string(&id.to_string(), 14);
-            let name_col = get_column_string(&epic.name, 28);
-            let status_col = get_column_string(&epic.status.to_string(), 13);
-            println!("{} | {} | {}", id_col, name_col, status_col);
+impl Page for HomePage {
+    fn draw_page(&self, screen: &mut Screen) -> Result<()> {
+        screen.print("--------------------------- EPICS ---------------------------");
+        let db_state = self.db.read_db()?;
+        let epics = &db_state.epics;
+
+        let mut table = Table::new(vec![
+            Column::new("id", 4),
+            Column::new("name", 12).priority(1),
+            Column::new("status", 11).priority(2),
+            Column::new("updated", 16).priority(3),
+        ]);
+        for id in sorted_ids(epics, epics.keys().copied(), self.list.sort) {
+            let epic = &epics[&id];
+            table.add_row(vec![db_state.item_key(id), epic.name.clone(), epic.status.to_string(), format_updated_at(epic.updated_at)])
+                .style(screen.theme.row(&epic.status, false))
+                .cell_style(2, screen.theme.status(&epic.status));
         }
-        println!();
-        println!();
 
-        println!("[q] quit | [c] create epic | [:id:] navigate to epic");
+        self.list.fit_page(screen.page_size, screen.height.saturating_sub(6));
+        table.print(screen, self.list.visible_range(table.len()), Some(self.list.clamp(table.len())));
+        screen.blank();
+        screen.print(self.list.status_line(table.len()));
+        screen.blank();
+
+        screen.print(footer(self, screen, &["[[/]] page", "[o/O] sort", "[:id:] navigate to epic"])?);
 
         Ok(())
     }
 
     fn handle_input(&self, input: &str) -> Result<Option<Action>> {
-        let epics = self.db.read_db()?.epics;
+        let db_state = self.db.read_db()?;
 
-        match input {
-            "q" => Ok(Some(Action::Exit)),
-            "c" => Ok(Some(Action::CreateEpic)),
-            input => {
-                if let Ok(epic_id) = input.parse::<u32>() {
-                    if epics.contains_key(&epic_id) {
-                        return Ok(Some(Action::NavigateToEpicDetail { epic_id }))
-                    }
-                }
-                Ok(None)
+        if let Ok(epic_id) = db_state.find_item(input) {
+            if db_state.epics.contains_key(&epic_id) {
+                return Ok(Some(Action::NavigateToEpicDetail { epic_id }))
             }
         }
+        Ok(None)
+    }
+
+    fn kind(&self) -> PageKind {
+        PageKind::Home
+    }
+
+    fn command_action(&self, command: Command) -> Result<Option<Action>> {
+        Ok(match command {
+            Command::Quit => Some(Action::Exit),
+            Command::CreateEpic => Some(Action::CreateEpic),
+            Command::SearchStories => Some(Action::SearchStories),
+            Command::DefineField => Some(Action::CreateFieldDefinition),
+            Command::Sprints => Some(Action::NavigateToSprints),
+            Command::Board => Some(Action::NavigateToBoard { epic_id: None }),
+            Command::Help => Some(Action::ShowHelp),
+            _ => None
+        })
+    }
+
+    fn handle_key(&mut self, key: Key) -> Result<KeyOutcome> {
+        let ids = self.epic_ids()?;
+
+        if key == Key::Enter {
+            return Ok(ids
+                .get(self.list.clamp(ids.len()))
+                .map(|epic_id| KeyOutcome::Action(Action::NavigateToEpicDetail { epic_id: *epic_id }))
+                .unwrap_or(KeyOutcome::Handled));
+        }
+        Ok(self.list.handle_key(key, ids.len()))
+    }
+
+    fn state(&self) -> PageState {
+        PageState::Home { list: self.list.state() }
+    }
+
+    fn breadcrumb(&self) -> String {
+        "Home".to_owned()
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -61,64 +172,298 @@ impl Page for HomePage {
 }
 
 pub struct EpicDetail {
-    pub epic_id: u32,
-    pub db: Rc<ProjectsDatabase>
+    pub epic_id: ItemId,
+    pub db: Rc<ProjectsDatabase>,
+    list: ListView
+}
+
+impl EpicDetail {
+    pub fn new(epic_id: ItemId, db: Rc<ProjectsDatabase>) -> Self {
+        EpicDetail { epic_id, db, list: ListView::new(STORY_SORT_COLUMNS) }
+    }
+
+    pub fn with_list_state(mut self, state: ListState) -> Self {
+        self.list = self.list.with_state(state);
+        self
+    }
+
+    fn story_ids(&self) -> Result<Vec<ItemId>> {
+        let db_state = self.db.read_db()?;
+        let epic = db_state.epics.get(&self.epic_id).ok_or_else(|| anyhow!("Epic not found!"))?;
+        Ok(sorted_ids(&db_state.stories, epic.stories.iter().copied(), self.list.sort))
+    }
 }
 
 impl Page for EpicDetail {
-    fn draw_page(&self) -> Result<()> {
+    fn draw_page(&self, screen: &mut Screen) -> Result<()> {
         let db_state = self.db.read_db()?;
         let epic = db_state.epics.get(&self.epic_id).ok_or_else(|| anyhow!("Epic not found!"))?;
         let stories = &db_state.stories;
 
-        println!("------------------------------ EPIC ------------------------------");
-        println!("  id  |     name     |         description         |    status    ");
+        screen.print("------------------------------ EPIC ------------------------------");
+        let mut epic_table = detail_table();
+        epic_table.add_row(vec![db_state.item_key(self.epic_id), epic.name.clone(), epic.description.clone(), epic.status.to_string()])
+            .cell_style(3, screen.theme.status(&epic.status));
+        epic_table.print_all(screen);
+
+        print_custom_fields(screen, &db_state.epic_fields(), &epic.fields);
+
+        screen.blank();
+
+        screen.print("---------------------------- STORIES ----------------------------");
+        let today = Local::now().date_naive();
+        let mut table = Table::new(vec![
+            Column::new("id", 4),
+            Column::new("type", 4).priority(3),
+            Column::new("name", 12).priority(1),
+            Column::new("priority", 8).priority(4),
+            Column::new("status", 11).priority(2),
+            Column::new("updated", 16).priority(5),
+        ]);
+        for id in sorted_ids(stories, epic.stories.iter().copied(), self.list.sort) {
+            let story = &stories[&id];
+            table.add_row(vec![
+                db_state.item_key(id),
+                db_state.issue_type_icon(&story.issue_type),
+                story.name.clone(),
+                story.priority.to_string(),
+                story.status.to_string(),
+                format_updated_at(story.updated_at)
+            ])
+                .style(screen.theme.row(&story.status, story.is_overdue(today)))
+                .cell_style(3, screen.theme.priority(story.priority))
+                .cell_style(4, screen.theme.status(&story.status));
+        }
+
+        self.list.fit_page(screen.page_size, screen.height.saturating_sub(screen.lines().len() + 5));
+        table.print(screen, self.list.visible_range(table.len()), Some(self.list.clamp(table.len())));
+
+        screen.blank();
+        screen.print(self.list.status_line(table.len()));
+        screen.blank();
+
+        screen.print(footer(self, screen, &["[[/]] page", "[o/O] sort", "[:id:] navigate to story"])?);
+
+        Ok(())
+    }
+
+    fn handle_input(&self, input: &str) -> Result<Option<Action>> {
+        let db_state = self.db.read_db()?;
+
+        if let Ok(story_id) = db_state.find_item(input) {
+            if db_state.stories.contains_key(&story_id) {
+                return Ok(Some(Action::NavigateToStoryDetail { epic_id: self.epic_id, story_id }))
+            }
+        }
+        Ok(None)
+    }
+
+    fn kind(&self) -> PageKind {
+        PageKind::EpicDetail
+    }
+
+    fn command_action(&self, command: Command) -> Result<Option<Action>> {
+        let epic_id = self.epic_id;
+        Ok(match command {
+            Command::Previous => Some(Action::NavigateToPreviousPage),
+            Command::UpdateEpicStatus => Some(Action::UpdateEpicStatus { epic_id }),
+            Command::DeleteEpic => Some(Action::DeleteEpic { epic_id }),
+            Command::SetEpicField => Some(Action::UpdateEpicField { epic_id }),
+            Command::Board => Some(Action::NavigateToBoard { epic_id: Some(epic_id) }),
+            Command::CreateStory => Some(Action::CreateStory { epic_id }),
+            Command::Help => Some(Action::ShowHelp),
+            _ => None
+        })
+    }
+
+    fn handle_key(&mut self, key: Key) -> Result<KeyOutcome> {
+        let ids = self.story_ids()?;
+
+        if key == Key::Enter {
+            return Ok(ids
+                .get(self.list.clamp(ids.len()))
+                .map(|story_id| KeyOutcome::Action(Action::NavigateToStoryDetail { epic_id: self.epic_id, story_id: *story_id }))
+                .unwrap_or(KeyOutcome::Handled));
+        }
+        Ok(self.list.handle_key(key, ids.len()))
+    }
+
+    fn state(&self) -> PageState {
+        PageState::EpicDetail { epic_id: self.epic_id, list: self.list.state() }
+    }
 
-        let id_col = get_column_string(&self.epic_id.to_string(), 5);
-        let name_col = get_column_string(&epic.name, 12);
-        let desc_col = get_column_string(&epic.description, 27);
-        let status_col = get_column_string(&epic.status.to_string(), 13);
-        println!("{} | {} | {} | {}", id_col, name_col, desc_col, status_col);
+    fn breadcrumb(&self) -> String {
+        let db_state = self.db.read_db().ok();
+        let name = db_state.as_ref().and_then(|db_state| db_state.epics.get(&self.epic_id).map(|epic| epic.name.clone()));
+        let key = db_state.map_or_else(|| self.epic_id.to_string(), |db_state| db_state.item_key(self.epic_id));
+        item_breadcrumb("Epic", &key, name)
+    }
 
-        println!();
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub struct StoryDetail {
+    pub epic_id: ItemId,
+    pub story_id: ItemId,
+    pub db: Rc<ProjectsDatabase>
+}
 
-        println!("---------------------------- STORIES ----------------------------");
-        println!("     id     |               name               |      status      ");
+impl Page for StoryDetail {
+    fn draw_page(&self, screen: &mut Screen) -> Result<()> {
+        let db_state = self.db.read_db()?;
+        let story = db_state.stories.get(&self.story_id).ok_or_else(|| anyhow!("could not find story!"))?;
 
-        for id in epic.stories.iter().sorted() {
-            let story = &stories[id];
-            let id_col = get_column_string(&id.to_string(), 11);
-            let name_col = get_column_string(&story.name, 32);
-            let status_col = get_column_string(&story.status.to_string(), 17);
-            println!("{} | {} | {}", id_col, name_col, status_col);
+        screen.print("------------------------------ STORY ------------------------------");
+        let mut table = detail_table();
+        table.add_row(vec![db_state.item_key(self.story_id), story.name.clone(), story.description.clone(), story.status.to_string()])
+            .cell_style(3, screen.theme.status(&story.status));
+        table.print_all(screen);
+
+        screen.blank();
+        screen.print(format!("Type: [{}] {}", db_state.issue_type_icon(&story.issue_type), story.issue_type));
+        screen.print(Line { spans: vec![
+            Span { text: "Priority: ".to_owned(), style: Style::default() },
+            Span { text: story.priority.to_string(), style: screen.theme.priority(story.priority) },
+        ] });
+        screen.print(format!("Points: {}", story.points.map(|points| points.to_string()).unwrap_or_else(|| "-".to_owned())));
+        match story.due_date {
+            Some(due_date) if story.is_overdue(Local::now().date_naive()) => {
+                screen.print(Line::styled(format!("Due: {} (overdue)", due_date), screen.theme.overdue))
+            }
+            Some(due_date) => screen.print(format!("Due: {}", due_date)),
+            None => screen.print("Due: -")
         }
 
-        println!();
-        println!();
+        let sprint = story.sprint_id.and_then(|sprint_id| db_state.sprints.get(&sprint_id));
+        screen.print(format!("Sprint: {}", sprint.map(|sprint| sprint.name.as_str()).unwrap_or("backlog")));
+
+        print_custom_fields(screen, &db_state.fields_of_story(story), &story.fields);
 
-        println!("[p] previous | [u] update epic | [d] delete epic | [c] create story | [:id:] navigate to story");
+        screen.blank();
+        screen.blank();
+
+        screen.print(footer(self, screen, &[])?);
+
+        Ok(())
+    }
+
+    fn handle_input(&self, _input: &str) -> Result<Option<Action>> {
+        Ok(None)
+    }
+
+    fn kind(&self) -> PageKind {
+        PageKind::StoryDetail
+    }
+
+    fn command_action(&self, command: Command) -> Result<Option<Action>> {
+        let story_id = self.story_id;
+        Ok(match command {
+            Command::Previous => Some(Action::NavigateToPreviousPage),
+            Command::UpdateStoryStatus => Some(Action::UpdateStoryStatus { story_id }),
+            Command::DeleteStory => Some(Action::DeleteStory { epic_id: self.epic_id, story_id }),
+            Command::EstimatePoints => Some(Action::UpdateStoryPoints { story_id }),
+            Command::SetPriority => Some(Action::UpdateStoryPriority { story_id }),
+            Command::SetDueDate => Some(Action::UpdateStoryDueDate { story_id }),
+            Command::SetStoryField => Some(Action::UpdateStoryField { story_id }),
+            Command::Help => Some(Action::ShowHelp),
+            _ => None
+        })
+    }
+
+    fn state(&self) -> PageState {
+        PageState::StoryDetail { epic_id: self.epic_id, story_id: self.story_id }
+    }
+
+    fn breadcrumb(&self) -> String {
+        let db_state = self.db.read_db().ok();
+        let name = db_state.as_ref().and_then(|db_state| db_state.stories.get(&self.story_id).map(|story| story.name.clone()));
+        let key = db_state.map_or_else(|| self.story_id.to_string(), |db_state| db_state.item_key(self.story_id));
+        item_breadcrumb("Story", &key, name)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub struct SearchResults {
+    pub query: String,
+    pub db: Rc<ProjectsDatabase>
+}
+
+impl Page for SearchResults {
+    fn draw_page(&self, screen: &mut Screen) -> Result<()> {
+        let db_state = self.db.read_db()?;
+        let filter = StoryFilter::parse(&self.query, &db_state)?;
+
+        screen.print("---------------------------- SEARCH ----------------------------");
+        screen.print(format!("Query: {}", self.query));
+        screen.blank();
+
+        let mut table = Table::new(vec![
+            Column::new("id", 4),
+            Column::new("type", 4).priority(3),
+            Column::new("name", 12).priority(1),
+            Column::new("epic", 4).priority(5),
+            Column::new("priority", 8).priority(4),
+            Column::new("status", 11).priority(2),
+        ]);
+        let today = Local::now().date_naive();
+        for hit in search_stories(&db_state, &filter) {
+            table.add_row(vec![
+                db_state.item_key(hit.story_id),
+                db_state.issue_type_icon(&hit.story.issue_type),
+                hit.story.name.clone(),
+                db_state.item_key(hit.epic_id),
+                hit.story.priority.to_string(),
+                hit.story.status.to_string()
+            ])
+                .style(screen.theme.row(&hit.story.status, hit.story.is_overdue(today)))
+                .cell_style(4, screen.theme.priority(hit.story.priority))
+                .cell_style(5, screen.theme.status(&hit.story.status));
+        }
+        table.print_all(screen);
+
+        screen.blank();
+        screen.blank();
+
+        screen.print(footer(self, screen, &["[:id:] navigate to story"])?);
 
         Ok(())
     }
 
     fn handle_input(&self, input: &str) -> Result<Option<Action>> {
         let db_state = self.db.read_db()?;
-        let stories = db_state.stories;
-
-        match input {
-            "p" => Ok( Some(Action::NavigateToPreviousPage) ),
-            "u" => Ok( Some(Action::UpdateEpicStatus { epic_id: self.epic_id } )),
-            "d" => Ok( Some(Action::DeleteEpic {epic_id: self.epic_id}) ),
-            "c" => Ok( Some(Action::CreateStory {epic_id: self.epic_id}) ),
-            input => {
-                if let Ok(story_id) = input.parse::<u32>() {
-                    if stories.contains_key(&story_id) {
-                        return Ok(Some(Action::NavigateToStoryDetail { epic_id: self.epic_id, story_id }))
-                    }
+        if let Ok(story_id) = db_state.find_item(input) {
+            if db_state.stories.contains_key(&story_id) {
+                if let Some(epic_id) = db_state.epic_id_of_story(story_id) {
+                    return Ok(Some(Action::NavigateToStoryDetail { epic_id, story_id }))
                 }
-                Ok(None)
             }
         }
+        Ok(None)
+    }
+
+    fn kind(&self) -> PageKind {
+        PageKind::SearchResults
+    }
+
+    fn command_action(&self, command: Command) -> Result<Option<Action>> {
+        Ok(match command {
+            Command::Previous => Some(Action::NavigateToPreviousPage),
+            Command::Help => Some(Action::ShowHelp),
+            _ => None
+        })
+    }
+
+    fn state(&self) -> PageState {
+        PageState::SearchResults { query: self.query.clone() }
+    }
+
+    fn breadcrumb(&self) -> String {
+        format!("Search \"{}\"", self.query)
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -126,42 +471,498 @@ impl Page for EpicDetail {
     }
 }
 
-pub struct StoryDetail {
-    pub epic_id: u32,
-    pub story_id: u32,
+pub struct SprintsPage {
     pub db: Rc<ProjectsDatabase>
 }
 
-impl Page for StoryDetail {
-    fn draw_page(&self) -> Result<()> {
+impl Page for SprintsPage {
+    fn draw_page(&self, screen: &mut Screen) -> Result<()> {
         let db_state = self.db.read_db()?;
-        let story = db_state.stories.get(&self.story_id).ok_or_else(|| anyhow!("could not find story!"))?;
 
-        println!("------------------------------ STORY ------------------------------");
-        println!("  id  |     name     |         description         |    status    ");
-        let id_col = get_column_string(&self.story_id.to_string(), 5);
-        let name_col = get_column_string(&story.name, 12);
-        let desc_col = get_column_string(&story.description, 27);
-        let status_col = get_column_string(&story.status.to_string(), 13);
-        println!("{} | {} | {} | {}", id_col, name_col, desc_col, status_col);
+        screen.print("------------------------------ SPRINTS ------------------------------");
+
+        let mut table = Table::new(vec![
+            Column::new("id", 4),
+            Column::new("name", 12).priority(1),
+            Column::new("dates", 23).priority(3),
+            Column::new("points", 6).priority(4),
+            Column::new("status", 7).priority(2),
+        ]);
+        for id in db_state.sprints.keys().sorted() {
+            let sprint = &db_state.sprints[id];
+            table.add_row(vec![
+                id.to_string(),
+                sprint.name.clone(),
+                format!("{} - {}", sprint.start, sprint.end),
+                db_state.sprint_points(*id).to_string(),
+                sprint.state.to_string()
+            ])
+                .style(if sprint.state == SprintState::Closed { screen.theme.closed } else { Style::default() });
+        }
+        table.print_all(screen);
 
-        println!();
-        println!();
+        screen.blank();
+        screen.blank();
 
-        println!("[p] previous | [u] update story | [d] delete story");
+        screen.print(footer(self, screen, &["[:id:] plan sprint"])?);
 
         Ok(())
     }
 
     fn handle_input(&self, input: &str) -> Result<Option<Action>> {
-        match input {
-            "p" => Ok(Some(Action::NavigateToPreviousPage)),
-            "u" => Ok(Some(Action::UpdateStoryStatus { story_id: self.story_id })),
-            "d" => Ok(Some(Action::DeleteStory { epic_id: self.epic_id, story_id: self.story_id })),
-            _ => {
-                Ok(None)
+        let sprints = self.db.read_db()?.sprints;
+
+        if let Ok(sprint_id) = input.parse::<u32>() {
+            if sprints.contains_key(&sprint_id) {
+                return Ok(Some(Action::NavigateToSprintPlanning { sprint_id }))
+            }
+        }
+        Ok(None)
+    }
+
+    fn kind(&self) -> PageKind {
+        PageKind::Sprints
+    }
+
+    fn command_action(&self, command: Command) -> Result<Option<Action>> {
+        Ok(match command {
+            Command::Previous => Some(Action::NavigateToPreviousPage),
+            Command::CreateSprint => Some(Action::CreateSprint),
+            Command::Help => Some(Action::ShowHelp),
+            _ => None
+        })
+    }
+
+    fn state(&self) -> PageState {
+        PageState::Sprints
+    }
+
+    fn breadcrumb(&self) -> String {
+        "Sprints".to_owned()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub struct SprintPlanning {
+    pub sprint_id: u32,
+    pub db: Rc<ProjectsDatabase>
+}
+
+impl Page for SprintPlanning {
+    fn draw_page(&self, screen: &mut Screen) -> Result<()> {
+        let db_state = self.db.read_db()?;
+        let sprint = db_state.sprints.get(&self.sprint_id).ok_or_else(|| anyhow!("Sprint not found!"))?;
+
+        screen.print("------------------------------ SPRINT ------------------------------");
+        let mut sprint_table = Table::new(vec![
+            Column::new("id", 4),
+            Column::new("name", 12).priority(1),
+            Column::new("goal", 12).priority(3).wrap(),
+            Column::new("committed", 9).priority(4),
+            Column::new("status", 7).priority(2),
+        ]);
+        sprint_table.add_row(vec![
+            self.sprint_id.to_string(),
+            sprint.name.clone(),
+            sprint.goal.clone(),
+            format!("{} points", db_state.sprint_points(self.sprint_id)),
+            sprint.state.to_string()
+        ]);
+        sprint_table.print_all(screen);
+        screen.print(format!("{} - {}", sprint.start, sprint.end));
+
+        let today = Local::now().date_naive();
+        let print_stories = |screen: &mut Screen, in_sprint: bool| {
+            let mut table = Table::new(vec![
+                Column::new("id", 4),
+                Column::new("type", 4).priority(3),
+                Column::new("name", 12).priority(1),
+                Column::new("points", 6).priority(4),
+                Column::new("status", 11).priority(2),
+            ]);
+            for id in db_state.stories.keys().sorted() {
+                let story = &db_state.stories[id];
+                let selected = if in_sprint {
+                    story.sprint_id == Some(self.sprint_id)
+                } else {
+                    story.sprint_id.is_none() && !story.status.is_done()
+                };
+                if !selected {
+                    continue;
+                }
+
+                table.add_row(vec![
+                    db_state.item_key(*id),
+                    db_state.issue_type_icon(&story.issue_type),
+                    story.name.clone(),
+                    story.points.map(|points| points.to_string()).unwrap_or_default(),
+                    story.status.to_string()
+                ])
+                    .style(screen.theme.row(&story.status, story.is_overdue(today)))
+                    .cell_style(4, screen.theme.status(&story.status));
+            }
+            table.print_all(screen);
+        };
+
+        screen.blank();
+        screen.print("--------------------------- SPRINT BACKLOG ---------------------------");
+        print_stories(screen, true);
+
+        screen.blank();
+        screen.print("------------------------------ BACKLOG ------------------------------");
+        print_stories(screen, false);
+
+        screen.blank();
+        screen.blank();
+
+        screen.print(footer(self, screen, &["[a :id:] add story", "[r :id:] remove story", "[:id:] navigate to story"])?);
+
+        Ok(())
+    }
+
+    fn handle_input(&self, input: &str) -> Result<Option<Action>> {
+        let db_state = self.db.read_db()?;
+
+        let parse_story_id = |input: &str| {
+            db_state.find_item(input)
+                .ok()
+                .filter(|story_id| db_state.stories.contains_key(story_id))
+        };
+
+        if let Some(story_id) = input.strip_prefix("a ").and_then(parse_story_id) {
+            return Ok(Some(Action::AddStoryToSprint { sprint_id: self.sprint_id, story_id }))
+        }
+        if let Some(story_id) = input.strip_prefix("r ").and_then(parse_story_id) {
+            return Ok(Some(Action::RemoveStoryFromSprint { story_id }))
+        }
+        if let Some(story_id) = parse_story_id(input) {
+            if let Some(epic_id) = db_state.epic_id_of_story(story_id) {
+                return Ok(Some(Action::NavigateToStoryDetail { epic_id, story_id }))
+            }
+        }
+        Ok(None)
+    }
+
+    fn kind(&self) -> PageKind {
+        PageKind::SprintPlanning
+    }
+
+    fn command_action(&self, command: Command) -> Result<Option<Action>> {
+        let db_state = self.db.read_db()?;
+        let sprint = db_state.sprints.get(&self.sprint_id).ok_or_else(|| anyhow!("Sprint not found!"))?;
+        let sprint_id = self.sprint_id;
+
+        Ok(match command {
+            Command::Previous => Some(Action::NavigateToPreviousPage),
+            Command::StartSprint if sprint.state == SprintState::Planned => Some(Action::StartSprint { sprint_id }),
+            Command::CloseSprint if sprint.state != SprintState::Closed => Some(Action::CloseSprint { sprint_id }),
+            Command::Help => Some(Action::ShowHelp),
+            _ => None
+        })
+    }
+
+    fn state(&self) -> PageState {
+        PageState::SprintPlanning { sprint_id: self.sprint_id }
+    }
+
+    fn breadcrumb(&self) -> String {
+        let name = self.db.read_db().ok().and_then(|db_state| db_state.sprints.get(&self.sprint_id).map(|sprint| sprint.name.clone()));
+        item_breadcrumb("Sprint", &self.sprint_id.to_string(), name)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Kanban board with one column per `Status`. The selected card is tracked as
+/// (column, row) and survives redraws.
+pub struct Board {
+    pub epic_id: Option<ItemId>,
+    pub db: Rc<ProjectsDatabase>,
+    selected: (usize, usize)
+}
+
+impl Board {
+    pub fn new(epic_id: Option<ItemId>, db: Rc<ProjectsDatabase>) -> Self {
+        Board { epic_id, db, selected: (0, 0) }
+    }
+
+    /// Selected (column, row); out of range values are clamped when drawing.
+    pub fn with_selected(mut self, selected: (usize, usize)) -> Self {
+        self.selected = selected;
+        self
+    }
+
+    /// Story ids per status column, each column sorted by id.
+    fn columns(&self, db_state: &DBState) -> Result<Vec<Vec<ItemId>>> {
+        let story_ids = match self.epic_id {
+            Some(epic_id) => db_state.epics
+                .get(&epic_id)
+                .ok_or_else(|| anyhow!("Epic not found!"))?
+                .stories
+                .clone(),
+            None => db_state.stories.keys().copied().collect()
+        };
+
+        Ok(Status::all()
+            .iter()
+            .map(|status| {
+                story_ids
+                    .iter()
+                    .filter(|id| db_state.stories.get(id).map(|story| &story.status) == Some(status))
+                    .copied()
+                    .sorted()
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Keeps the selection inside the board after stories were moved or deleted.
+    fn clamp_selection(&self, columns: &[Vec<ItemId>]) -> (usize, usize) {
+        let (column, row) = self.selected;
+        let column = column.min(columns.len() - 1);
+        let row = row.min(columns[column].len().saturating_sub(1));
+        (column, row)
+    }
+
+    fn move_card(&mut self, columns: &[Vec<ItemId>], story_id: ItemId, target_column: usize) -> KeyOutcome {
+        let row = columns[target_column]
+            .iter()
+            .filter(|id| **id < story_id)
+            .count();
+        self.selected = (target_column, row);
+        KeyOutcome::Action(Action::SetStoryStatus { story_id, status: Status::all()[target_column].clone() })
+    }
+}
+
+impl Page for Board {
+    fn draw_page(&self, screen: &mut Screen) -> Result<()> {
+        let db_state = self.db.read_db()?;
+        let columns = self.columns(&db_state)?;
+        let (selected_column, selected_row) = self.clamp_selection(&columns);
+        let width = get_board_column_width(screen.width, columns.len());
+
+        match self.epic_id.and_then(|epic_id| db_state.epics.get(&epic_id)) {
+            Some(epic) => screen.print(format!("------------------------------ BOARD: {} ------------------------------", epic.name)),
+            None => screen.print("------------------------------ BOARD ------------------------------")
+        }
+
+        let header = Status::all()
+            .iter()
+            .zip(&columns)
+            .map(|(status, stories)| get_column_string(&format!("{} ({})", status, stories.len()), width))
+            .join(" | ");
+        screen.print(Line::styled(header, screen.theme.header));
+
+        let today = Local::now().date_naive();
+        let rows = columns.iter().map(|stories| stories.len()).max().unwrap_or(0);
+        let visible_rows = screen.height.saturating_sub(5);
+        let first_row = (selected_row + 1).saturating_sub(visible_rows);
+        for row in (first_row..rows).take(visible_rows) {
+            let mut line = Line::default();
+            for (column, stories) in columns.iter().enumerate() {
+                if column > 0 {
+                    line.spans.push(Span { text: " | ".to_owned(), style: Style::default() });
+                }
+                let story = stories.get(row).map(|id| (id, &db_state.stories[id]));
+                let card = story
+                    .map(|(id, story)| format!("{} {}", db_state.item_key(*id), story.name))
+                    .unwrap_or_default();
+                let mut style = story
+                    .map(|(_, story)| screen.theme.row(&story.status, story.is_overdue(today)))
+                    .unwrap_or_default();
+                if (column, row) == (selected_column, selected_row) && !card.is_empty() {
+                    style = style.patch(screen.theme.selected);
+                }
+                line.spans.push(Span { text: get_column_string(&card, width), style });
+            }
+            screen.print(line);
+        }
+
+        screen.blank();
+        screen.blank();
+
+        screen.print(footer(self, screen, &["[arrows/hjkl] select card", "[</>] move card", "[enter] open card"])?);
+
+        Ok(())
+    }
+
+    fn handle_input(&self, _input: &str) -> Result<Option<Action>> {
+        Ok(None)
+    }
+
+    fn kind(&self) -> PageKind {
+        PageKind::Board
+    }
+
+    fn command_action(&self, command: Command) -> Result<Option<Action>> {
+        Ok(match command {
+            Command::Previous => Some(Action::NavigateToPreviousPage),
+            Command::Help => Some(Action::ShowHelp),
+            _ => None
+        })
+    }
+
+    fn handle_key(&mut self, key: Key) -> Result<KeyOutcome> {
+        let db_state = self.db.read_db()?;
+        let columns = self.columns(&db_state)?;
+        let (column, row) = self.clamp_selection(&columns);
+        let selected_story = columns[column].get(row).copied();
+
+        let outcome = match key {
+            Key::Left | Key::Char('h') => {
+                self.selected = (column.saturating_sub(1), row);
+                KeyOutcome::Handled
+            }
+            Key::Right | Key::Char('l') => {
+                self.selected = ((column + 1).min(columns.len() - 1), row);
+                KeyOutcome::Handled
+            }
+            Key::Up | Key::Char('k') => {
+                self.selected = (column, row.saturating_sub(1));
+                KeyOutcome::Handled
+            }
+            Key::Down | Key::Char('j') => {
+                self.selected = (column, row + 1);
+                KeyOutcome::Handled
+            }
+            Key::Char('>') => match selected_story {
+                Some(story_id) if column + 1 < columns.len() => self.move_card(&columns, story_id, column + 1),
+                _ => KeyOutcome::Handled
+            },
+            Key::Char('<') => match selected_story {
+                Some(story_id) if column > 0 => self.move_card(&columns, story_id, column - 1),
+                _ => KeyOutcome::Handled
+            },
+            Key::Enter => selected_story
+                .and_then(|story_id| db_state.epic_id_of_story(story_id).map(|epic_id| (epic_id, story_id)))
+                .map(|(epic_id, story_id)| KeyOutcome::Action(Action::NavigateToStoryDetail { epic_id, story_id }))
+                .unwrap_or(KeyOutcome::Handled),
+            _ => KeyOutcome::Ignored
+        };
+
+        Ok(outcome)
+    }
+
+    fn state(&self) -> PageState {
+        PageState::Board { epic_id: self.epic_id, selected: self.selected }
+    }
+
+    fn breadcrumb(&self) -> String {
+        match self.epic_id {
+            Some(epic_id) => {
+                let key = self.db.read_db().map_or_else(|_| epic_id.to_string(), |db_state| db_state.item_key(epic_id));
+                format!("Board (epic {})", key)
+            }
+            None => "Board".to_owned()
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Every action with its keys and the pages it works on, built from `Action::help`
+/// and the keymap in use.
+#[derive(Default)]
+pub struct HelpPage {
+    /// First action row shown.
+    pub offset: usize
+}
+
+impl HelpPage {
+    fn help_row(help: &ActionHelp, keymap: &Keymap) -> Vec<String> {
+        let mut keys = vec![];
+        let mut pages = vec![];
+        if let Some(command) = help.command {
+            for kind in PageKind::all() {
+                if let Some(binding) = keymap.bindings(kind).iter().find(|binding| binding.command == command) {
+                    keys.push(binding.keys.clone());
+                    pages.push(kind);
+                }
+            }
+        }
+        if let Some(input) = help.input {
+            keys.push(input.to_owned());
+            if help.pages.is_empty() {
+                pages.extend(PageKind::all());
+            } else {
+                pages.extend(help.pages);
             }
         }
+
+        let pages = pages.into_iter().unique().collect::<Vec<_>>();
+        let pages = if pages.len() == PageKind::all().len() {
+            "every page".to_owned()
+        } else {
+            pages.iter().map(|kind| kind.name()).join(", ")
+        };
+        vec![help.name.to_owned(), keys.into_iter().unique().join(", "), pages, help.description.to_owned()]
+    }
+}
+
+impl Page for HelpPage {
+    fn draw_page(&self, screen: &mut Screen) -> Result<()> {
+        screen.print("------------------------------ HELP ------------------------------");
+        let workflow = std::iter::successors(Some(Status::Open), Status::next).join(" -> ");
+        screen.print(format!("status workflow: {} (cards move both ways on the board)", workflow));
+        screen.print(footer(self, screen, &["[up/down] scroll"])?);
+        screen.blank();
+
+        let mut table = Table::new(vec![
+            Column::new("action", 12),
+            Column::new("keys", 8).priority(1),
+            Column::new("pages", 12).priority(2).wrap(),
+            Column::new("description", 12).priority(3).wrap(),
+        ]);
+        for action in Action::all() {
+            table.add_row(HelpPage::help_row(&action.help(), &screen.keymap));
+        }
+        table.print(screen, self.offset.min(table.len().saturating_sub(1))..table.len(), None);
+
+        Ok(())
+    }
+
+    fn handle_input(&self, _input: &str) -> Result<Option<Action>> {
+        Ok(None)
+    }
+
+    fn kind(&self) -> PageKind {
+        PageKind::Help
+    }
+
+    fn command_action(&self, command: Command) -> Result<Option<Action>> {
+        Ok(match command {
+            Command::Previous => Some(Action::NavigateToPreviousPage),
+            _ => None
+        })
+    }
+
+    fn handle_key(&mut self, key: Key) -> Result<KeyOutcome> {
+        let last = Action::all().len() - 1;
+        self.offset = match key {
+            Key::Up => self.offset.saturating_sub(1),
+            Key::Down => (self.offset + 1).min(last),
+            Key::PageUp => self.offset.saturating_sub(HELP_PAGE_ROWS),
+            Key::PageDown => (self.offset + HELP_PAGE_ROWS).min(last),
+            Key::Home => 0,
+            Key::End => last,
+            _ => return Ok(KeyOutcome::Ignored)
+        };
+        Ok(KeyOutcome::Handled)
+    }
+
+    fn state(&self) -> PageState {
+        PageState::Help { offset: self.offset }
+    }
+
+    fn breadcrumb(&self) -> String {
+        "Help".to_owned()
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -173,8 +974,95 @@ impl Page for StoryDetail {
 mod tests {
     use super::*;
     use crate::{db::test_utils::MockDb};
+    use std::collections::HashMap;
     use crate::models::{Story, Epic};
 
+    #[test]
+    fn parse_global_command_should_accept_go_to_forms() {
+        assert_eq!(parse_global_command("g 42"), Some(Action::GoToItem { key: "42".to_owned() }));
+        assert_eq!(parse_global_command("g42"), Some(Action::GoToItem { key: "42".to_owned() }));
+        assert_eq!(parse_global_command("#7"), Some(Action::GoToItem { key: "7".to_owned() }));
+        assert_eq!(parse_global_command("g PROJ-43"), Some(Action::GoToItem { key: "PROJ-43".to_owned() }));
+        assert_eq!(parse_global_command("g"), None);
+        assert_eq!(parse_global_command("#abc"), None);
+        assert_eq!(parse_global_command("42"), None);
+    }
+
+    #[test]
+    fn parse_global_command_should_accept_breadcrumb_levels() {
+        assert_eq!(parse_global_command("^1"), Some(Action::NavigateToBreadcrumb { level: 0 }));
+        assert_eq!(parse_global_command("^3"), Some(Action::NavigateToBreadcrumb { level: 2 }));
+        assert_eq!(parse_global_command("^0"), None);
+        assert_eq!(parse_global_command("^"), None);
+    }
+
+    #[test]
+    fn footer_should_follow_the_keymap() {
+        let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+        let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+        let story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
+        let overrides = HashMap::from([(PageKind::StoryDetail, HashMap::from([(Command::SetPriority, "P".to_owned())]))]);
+        let keymap = Keymap::with_overrides(overrides).unwrap();
+
+        let page = StoryDetail { epic_id, story_id, db };
+        let mut screen = Screen::new(200, 24).with_keymap(Rc::new(keymap));
+        assert_eq!(page.draw_page(&mut screen).is_ok(), true);
+
+        assert_eq!(
+            screen.text().last().unwrap(),
+            "[p] previous | [u] update story | [d] delete story | [e] estimate points | [P] set priority | [t] set due date | [f] set field | [?] help"
+        );
+    }
+
+    #[test]
+    fn breadcrumbs_should_name_items() {
+        let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+        let epic_id = db.create_epic(Epic::new("Auth".to_owned(), "".to_owned())).unwrap();
+
+        assert_eq!(HomePage::new(Rc::clone(&db)).breadcrumb(), "Home".to_owned());
+        assert_eq!(EpicDetail::new(epic_id, Rc::clone(&db)).breadcrumb(), "Epic 1 \"Auth\"".to_owned());
+        assert_eq!(StoryDetail { epic_id, story_id: 9, db }.breadcrumb(), "Story 9".to_owned());
+    }
+
+    mod help_page {
+        use super::*;
+
+        #[test]
+        fn draw_page_should_list_actions_from_metadata() {
+            let overrides = HashMap::from([(PageKind::Home, HashMap::from([(Command::CreateEpic, "n".to_owned())]))]);
+            let keymap = Keymap::with_overrides(overrides).unwrap();
+
+            let page = HelpPage::default();
+            let mut screen = Screen::new(200, 60).with_keymap(Rc::new(keymap));
+            assert_eq!(page.draw_page(&mut screen).is_ok(), true);
+
+            let text = screen.text();
+            assert_eq!(text[1], "status workflow: OPEN -> IN PROGRESS -> RESOLVED -> CLOSED (cards move both ways on the board)");
+            assert_eq!(text[2], "[p] previous | [up/down] scroll");
+            let row = |name: &str| text.iter().find(|line| line.starts_with(name)).unwrap().split(" | ").map(str::trim).map(str::to_owned).collect::<Vec<_>>();
+            assert_eq!(row("Create epic"), vec!["Create epic", "n", "home", "Add an epic"]);
+            assert_eq!(row("Back"), vec!["Back", "p, ctrl+b", "every page", "Return to the previous page"]);
+            assert_eq!(row("Help"), vec!["Help", "?", "every page", "Show this page"]);
+            assert_eq!(row("Add to sprint"), vec!["Add to sprint", "a :id:", "sprint_planning", "Add a backlog story to the sprint"]);
+            assert_eq!(text.len(), 5 + Action::all().len());
+        }
+
+        #[test]
+        fn handle_key_should_scroll() {
+            let mut page = HelpPage::default();
+
+            assert_eq!(page.handle_key(Key::Down).unwrap(), KeyOutcome::Handled);
+            assert_eq!(page.offset, 1);
+            assert_eq!(page.handle_key(Key::End).unwrap(), KeyOutcome::Handled);
+            assert_eq!(page.offset, Action::all().len() - 1);
+            assert_eq!(page.handle_key(Key::PageDown).unwrap(), KeyOutcome::Handled);
+            assert_eq!(page.offset, Action::all().len() - 1);
+            assert_eq!(page.handle_key(Key::Char('q')).unwrap(), KeyOutcome::Ignored);
+            assert_eq!(Keymap::default().action(&page, "p").unwrap(), Some(Action::NavigateToPreviousPage));
+            assert_eq!(Keymap::default().action(&page, "?").unwrap(), None);
+        }
+    }
+
     mod home_page {
         use super::*;
 
@@ -182,15 +1070,15 @@ mod tests {
         fn draw_page_should_not_throw_error() {
             let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
 
-            let page = HomePage { db };
-            assert_eq!(page.draw_page().is_ok(), true);
+            let page = HomePage::new(db);
+            assert_eq!(page.draw_page(&mut Screen::new(80, 24)).is_ok(), true);
         }
 
         #[test]
         fn handle_input_should_not_throw_error() {
             let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
 
-            let page = HomePage { db };
+            let page = HomePage::new(db);
             assert_eq!(page.handle_input("").is_ok(), true);
         }
 
@@ -202,7 +1090,9 @@ mod tests {
 
             let epic_id = db.create_epic(epic).unwrap();
 
-            let page = HomePage { db };
+            let page = HomePage::new(db);
+
+            let keymap = Keymap::default();
 
             let q = "q";
             let c = "c";
@@ -212,13 +1102,51 @@ mod tests {
             let junk_input_with_valid_prefix = "q983f2j";
             let input_with_trailing_white_spaces = "q\n";
 
-            assert_eq!(page.handle_input(q).unwrap(), Some(Action::Exit));
-            assert_eq!(page.handle_input(c).unwrap(), Some(Action::CreateEpic));
-            assert_eq!(page.handle_input(&valid_epic_id).unwrap(), Some(Action::NavigateToEpicDetail { epic_id: 1 }));
-            assert_eq!(page.handle_input(invalid_epic_id).unwrap(), None);
-            assert_eq!(page.handle_input(junk_input).unwrap(), None);
-            assert_eq!(page.handle_input(junk_input_with_valid_prefix).unwrap(), None);
-            assert_eq!(page.handle_input(input_with_trailing_white_spaces).unwrap(), None);
+            assert_eq!(keymap.action(&page, q).unwrap(), Some(Action::Exit));
+            assert_eq!(keymap.action(&page, c).unwrap(), Some(Action::CreateEpic));
+            assert_eq!(keymap.action(&page, "s").unwrap(), Some(Action::SearchStories));
+            assert_eq!(keymap.action(&page, "f").unwrap(), Some(Action::CreateFieldDefinition));
+            assert_eq!(keymap.action(&page, "r").unwrap(), Some(Action::NavigateToSprints));
+            assert_eq!(keymap.action(&page, "b").unwrap(), Some(Action::NavigateToBoard { epic_id: None }));
+            assert_eq!(keymap.action(&page, &valid_epic_id).unwrap(), Some(Action::NavigateToEpicDetail { epic_id: 1 }));
+            assert_eq!(keymap.action(&page, invalid_epic_id).unwrap(), None);
+            assert_eq!(keymap.action(&page, junk_input).unwrap(), None);
+            assert_eq!(keymap.action(&page, junk_input_with_valid_prefix).unwrap(), None);
+            assert_eq!(keymap.action(&page, input_with_trailing_white_spaces).unwrap(), None);
+        }
+
+        #[test]
+        fn draw_page_should_fit_columns_to_screen_width() {
+            let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+
+            let name = "an epic name that is much longer than the default column";
+            db.create_epic(Epic::new(name.to_owned(), "".to_owned())).unwrap();
+
+            let page = HomePage::new(db);
+
+            let mut wide = Screen::new(200, 24);
+            page.draw_page(&mut wide).unwrap();
+            assert_eq!(wide.text()[2].contains(name), true);
+
+            let mut narrow = Screen::new(40, 24);
+            page.draw_page(&mut narrow).unwrap();
+            assert_eq!(narrow.text()[2], "1    | an epic name tha... | OPEN       ".to_owned());
+        }
+
+        #[test]
+        fn handle_key_should_select_epics() {
+            let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+
+            db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+            db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+
+            let mut page = HomePage::new(db);
+
+            assert_eq!(page.handle_key(Key::Enter).unwrap(), KeyOutcome::Action(Action::NavigateToEpicDetail { epic_id: 1 }));
+            assert_eq!(page.handle_key(Key::Down).unwrap(), KeyOutcome::Handled);
+            assert_eq!(page.handle_key(Key::Down).unwrap(), KeyOutcome::Handled);
+            assert_eq!(page.handle_key(Key::Enter).unwrap(), KeyOutcome::Action(Action::NavigateToEpicDetail { epic_id: 2 }));
+            assert_eq!(page.handle_key(Key::Char('c')).unwrap(), KeyOutcome::Ignored);
         }
     }
 
@@ -231,8 +1159,8 @@ mod tests {
             let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
             let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
 
-            let page = EpicDetail { epic_id, db };
-            assert_eq!(page.draw_page().is_ok(), true);
+            let page = EpicDetail::new(epic_id, db);
+            assert_eq!(page.draw_page(&mut Screen::new(80, 24)).is_ok(), true);
         }
 
         #[test]
@@ -240,7 +1168,7 @@ mod tests {
             let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
             let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
 
-            let page = EpicDetail { epic_id, db };
+            let page = EpicDetail::new(epic_id, db);
             assert_eq!(page.handle_input("").is_ok(), true);
         }
 
@@ -248,8 +1176,8 @@ mod tests {
         fn draw_page_should_throw_error_for_invalid_epic_id() {
             let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
 
-            let page = EpicDetail { epic_id: 999, db };
-            assert_eq!(page.draw_page().is_err(), true);
+            let page = EpicDetail::new(999, db);
+            assert_eq!(page.draw_page(&mut Screen::new(80, 24)).is_err(), true);
         }
 
         #[test]
@@ -259,7 +1187,9 @@ mod tests {
             let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
             let story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
 
-            let page = EpicDetail { epic_id, db };
+            let page = EpicDetail::new(epic_id, db);
+
+            let keymap = Keymap::default();
 
             let p = "p";
             let u = "u";
@@ -270,15 +1200,17 @@ mod tests {
             let junk_input_with_valid_prefix = "p983f2j";
             let input_with_trailing_white_spaces = "p\n";
 
-            assert_eq!(page.handle_input(p).unwrap(), Some(Action::NavigateToPreviousPage));
-            assert_eq!(page.handle_input(u).unwrap(), Some(Action::UpdateEpicStatus { epic_id: 1 }));
-            assert_eq!(page.handle_input(d).unwrap(), Some(Action::DeleteEpic { epic_id: 1 }));
-            assert_eq!(page.handle_input(c).unwrap(), Some(Action::CreateStory { epic_id: 1 }));
-            assert_eq!(page.handle_input(&story_id.to_string()).unwrap(), Some(Action::NavigateToStoryDetail { epic_id: 1, story_id: 2 }));
-            assert_eq!(page.handle_input(invalid_story_id).unwrap(), None);
-            assert_eq!(page.handle_input(junk_input).unwrap(), None);
-            assert_eq!(page.handle_input(junk_input_with_valid_prefix).unwrap(), None);
-            assert_eq!(page.handle_input(input_with_trailing_white_spaces).unwrap(), None);
+            assert_eq!(keymap.action(&page, p).unwrap(), Some(Action::NavigateToPreviousPage));
+            assert_eq!(keymap.action(&page, u).unwrap(), Some(Action::UpdateEpicStatus { epic_id: 1 }));
+            assert_eq!(keymap.action(&page, d).unwrap(), Some(Action::DeleteEpic { epic_id: 1 }));
+            assert_eq!(keymap.action(&page, c).unwrap(), Some(Action::CreateStory { epic_id: 1 }));
+            assert_eq!(keymap.action(&page, "f").unwrap(), Some(Action::UpdateEpicField { epic_id: 1 }));
+            assert_eq!(keymap.action(&page, "b").unwrap(), Some(Action::NavigateToBoard { epic_id: Some(1) }));
+            assert_eq!(keymap.action(&page, &story_id.to_string()).unwrap(), Some(Action::NavigateToStoryDetail { epic_id: 1, story_id: 2 }));
+            assert_eq!(keymap.action(&page, invalid_story_id).unwrap(), None);
+            assert_eq!(keymap.action(&page, junk_input).unwrap(), None);
+            assert_eq!(keymap.action(&page, junk_input_with_valid_prefix).unwrap(), None);
+            assert_eq!(keymap.action(&page, input_with_trailing_white_spaces).unwrap(), None);
         }
     }
 
@@ -294,7 +1226,7 @@ mod tests {
             let story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
 
             let page = StoryDetail { epic_id, story_id, db };
-            assert_eq!(page.draw_page().is_ok(), true);
+            assert_eq!(page.draw_page(&mut Screen::new(80, 24)).is_ok(), true);
         }
 
         #[test]
@@ -316,7 +1248,7 @@ mod tests {
             let _ = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
 
             let page = StoryDetail { epic_id, story_id: 999, db };
-            assert_eq!(page.draw_page().is_err(), true);
+            assert_eq!(page.draw_page(&mut Screen::new(80, 24)).is_err(), true);
         }
 
         #[test]
@@ -328,6 +1260,8 @@ mod tests {
 
             let page = StoryDetail { epic_id, story_id, db };
 
+            let keymap = Keymap::default();
+
             let p = "p";
             let u = "u";
             let d = "d";
@@ -336,13 +1270,144 @@ mod tests {
             let junk_input_with_valid_prefix = "p983f2j";
             let input_with_trailing_white_spaces = "p\n";
 
-            assert_eq!(page.handle_input(p).unwrap(), Some(Action::NavigateToPreviousPage));
-            assert_eq!(page.handle_input(u).unwrap(), Some(Action::UpdateStoryStatus { story_id }));
-            assert_eq!(page.handle_input(d).unwrap(), Some(Action::DeleteStory { epic_id, story_id }));
-            assert_eq!(page.handle_input(some_number).unwrap(), None);
-            assert_eq!(page.handle_input(junk_input).unwrap(), None);
-            assert_eq!(page.handle_input(junk_input_with_valid_prefix).unwrap(), None);
-            assert_eq!(page.handle_input(input_with_trailing_white_spaces).unwrap(), None);
+            assert_eq!(keymap.action(&page, p).unwrap(), Some(Action::NavigateToPreviousPage));
+            assert_eq!(keymap.action(&page, u).unwrap(), Some(Action::UpdateStoryStatus { story_id }));
+            assert_eq!(keymap.action(&page, d).unwrap(), Some(Action::DeleteStory { epic_id, story_id }));
+            assert_eq!(keymap.action(&page, "e").unwrap(), Some(Action::UpdateStoryPoints { story_id }));
+            assert_eq!(keymap.action(&page, "r").unwrap(), Some(Action::UpdateStoryPriority { story_id }));
+            assert_eq!(keymap.action(&page, "t").unwrap(), Some(Action::UpdateStoryDueDate { story_id }));
+            assert_eq!(keymap.action(&page, "f").unwrap(), Some(Action::UpdateStoryField { story_id }));
+            assert_eq!(keymap.action(&page, some_number).unwrap(), None);
+            assert_eq!(keymap.action(&page, junk_input).unwrap(), None);
+            assert_eq!(keymap.action(&page, junk_input_with_valid_prefix).unwrap(), None);
+            assert_eq!(keymap.action(&page, input_with_trailing_white_spaces).unwrap(), None);
+        }
+    }
+
+    mod search_results_page {
+        use crate::models::{Epic, Story};
+        use super::*;
+
+        #[test]
+        fn draw_page_should_throw_error_for_invalid_query() {
+            let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+
+            let page = SearchResults { query: "type:feature".to_owned(), db };
+            assert_eq!(page.draw_page(&mut Screen::new(80, 24)).is_err(), true);
+        }
+
+        #[test]
+        fn handle_input_should_return_the_correct_actions() {
+            let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+
+            let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+            let story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
+
+            let page = SearchResults { query: "".to_owned(), db };
+
+            let keymap = Keymap::default();
+
+            assert_eq!(page.draw_page(&mut Screen::new(80, 24)).is_ok(), true);
+            assert_eq!(keymap.action(&page, "p").unwrap(), Some(Action::NavigateToPreviousPage));
+            assert_eq!(keymap.action(&page, &story_id.to_string()).unwrap(), Some(Action::NavigateToStoryDetail { epic_id, story_id }));
+            assert_eq!(keymap.action(&page, "999").unwrap(), None);
+        }
+    }
+
+    mod sprint_planning_page {
+        use chrono::NaiveDate;
+        use crate::models::{Epic, Sprint, Story};
+        use super::*;
+
+        fn create_sprint(db: &ProjectsDatabase) -> u32 {
+            db.create_sprint(Sprint::new(
+                "".to_owned(),
+                "".to_owned(),
+                NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 1, 14).unwrap()
+            )).unwrap()
+        }
+
+        #[test]
+        fn draw_page_should_throw_error_for_invalid_sprint_id() {
+            let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+
+            let page = SprintPlanning { sprint_id: 999, db };
+            assert_eq!(page.draw_page(&mut Screen::new(80, 24)).is_err(), true);
+        }
+
+        #[test]
+        fn handle_input_should_return_the_correct_actions() {
+            let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+
+            let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+            let story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
+            let sprint_id = create_sprint(&db);
+
+            let page = SprintPlanning { sprint_id, db: Rc::clone(&db) };
+
+            let keymap = Keymap::default();
+
+            assert_eq!(page.draw_page(&mut Screen::new(80, 24)).is_ok(), true);
+            assert_eq!(keymap.action(&page, "p").unwrap(), Some(Action::NavigateToPreviousPage));
+            assert_eq!(keymap.action(&page, "s").unwrap(), Some(Action::StartSprint { sprint_id }));
+            assert_eq!(keymap.action(&page, "x").unwrap(), Some(Action::CloseSprint { sprint_id }));
+            assert_eq!(keymap.action(&page, &format!("a {}", story_id)).unwrap(), Some(Action::AddStoryToSprint { sprint_id, story_id }));
+            assert_eq!(keymap.action(&page, &format!("r {}", story_id)).unwrap(), Some(Action::RemoveStoryFromSprint { story_id }));
+            assert_eq!(keymap.action(&page, &story_id.to_string()).unwrap(), Some(Action::NavigateToStoryDetail { epic_id, story_id }));
+            assert_eq!(keymap.action(&page, "a 999").unwrap(), None);
+            assert_eq!(keymap.action(&page, "a").unwrap(), None);
+
+            db.close_sprint(sprint_id, None).unwrap();
+
+            assert_eq!(keymap.action(&page, "s").unwrap(), None);
+            assert_eq!(keymap.action(&page, "x").unwrap(), None);
+        }
+    }
+
+    mod board_page {
+        use crate::models::{Epic, Story};
+        use super::*;
+
+        #[test]
+        fn draw_page_should_throw_error_for_invalid_epic_id() {
+            let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+
+            let page = Board::new(Some(999), db);
+            assert_eq!(page.draw_page(&mut Screen::new(80, 24)).is_err(), true);
+        }
+
+        #[test]
+        fn handle_key_should_navigate_and_move_cards() {
+            let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+
+            let epic_id = db.create_epic(Epic::new("".to_owned(), "".to_owned())).unwrap();
+            let first_story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
+            let second_story_id = db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id).unwrap();
+            db.update_story_status(second_story_id, Status::InProgress).unwrap();
+
+            let mut page = Board::new(Some(epic_id), Rc::clone(&db));
+
+            let keymap = Keymap::default();
+            assert_eq!(page.draw_page(&mut Screen::new(80, 24)).is_ok(), true);
+
+            assert_eq!(keymap.action(&page, "p").unwrap(), Some(Action::NavigateToPreviousPage));
+            assert_eq!(keymap.action(&page, "h").unwrap(), None);
+            assert_eq!(page.handle_key(Key::Char('<')).unwrap(), KeyOutcome::Handled);
+            assert_eq!(page.handle_key(Key::Enter).unwrap(), KeyOutcome::Action(Action::NavigateToStoryDetail { epic_id, story_id: first_story_id }));
+            assert_eq!(page.handle_key(Key::Char('>')).unwrap(), KeyOutcome::Action(Action::SetStoryStatus { story_id: first_story_id, status: Status::InProgress }));
+            assert_eq!(page.selected, (1, 0));
+
+            db.update_story_status(first_story_id, Status::InProgress).unwrap();
+
+            assert_eq!(page.handle_key(Key::Down).unwrap(), KeyOutcome::Handled);
+            assert_eq!(page.handle_key(Key::Enter).unwrap(), KeyOutcome::Action(Action::NavigateToStoryDetail { epic_id, story_id: second_story_id }));
+            assert_eq!(page.handle_key(Key::Char('l')).unwrap(), KeyOutcome::Handled);
+            assert_eq!(page.handle_key(Key::Enter).unwrap(), KeyOutcome::Handled);
+            assert_eq!(page.handle_key(Key::Left).unwrap(), KeyOutcome::Handled);
+            assert_eq!(page.handle_key(Key::Char('j')).unwrap(), KeyOutcome::Handled);
+            assert_eq!(page.handle_key(Key::Char('<')).unwrap(), KeyOutcome::Action(Action::SetStoryStatus { story_id: second_story_id, status: Status::Open }));
+            assert_eq!(page.handle_key(Key::Char('q')).unwrap(), KeyOutcome::Ignored);
         }
     }
 }
\ No newline at end of file
@@ -1,69 +1,377 @@
-use std::cmp::Ordering;
-use ellipse::Ellipse;
-pub fn get_column_string(text: &str, width: usize) -> String {
-    let len = text.len();
-
-    match len.cmp(&width) {
-        std::cmp::Ordering::Equal => text.to_owned(),
-        Ordering::Less => {
-            let left_over = width - len;
-            let mut column_string = text.to_owned();
-
-            for _ in 0..left_over {
-                column_string.push(' ');
-            }
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use crate::models::{Epic, FieldDefinition, FieldValue, ItemId, Priority, Status, Story};
+use crate::ui::pages::{KeyOutcome, Page};
+use crate::ui::table::{Column, Table};
+use crate::ui::terminal::{Key, Screen};
 
-            column_string
+/// Footer listing the keys bound on the page that apply right now, followed by
+/// `hints` for input the keymap doesn't cover.
+pub fn footer(page: &dyn Page, screen: &Screen, hints: &[&str]) -> Result<String> {
+    let mut items = vec![];
+    for binding in screen.keymap.bindings(page.kind()) {
+        if page.command_action(binding.command)?.is_some() {
+            items.push(format!("[{}] {}", binding.keys, binding.command.label()));
         }
-        Ordering::Greater => {
-            if width <= 3 {
-                let mut result = "".to_string();
-                for _ in 0..width {
-                    result = format!("{}.", result)
-                }
-                return result
-            }
-            let result = text.truncate_ellipse(width - 3);
-            result.to_string()
+    }
+    items.extend(hints.iter().map(|hint| hint.to_string()));
+    Ok(items.join(" | "))
+}
+
+/// Breadcrumb of a single item: its kind, key and, when it still exists, its name.
+pub fn item_breadcrumb(kind: &str, key: &str, name: Option<String>) -> String {
+    match name {
+        Some(name) => format!("{} {} \"{}\"", kind, key, name),
+        None => format!("{} {}", kind, key)
+    }
+}
+
+/// Splits the terminal width evenly between board columns separated by " | ".
+pub fn get_board_column_width(total_width: usize, columns: usize) -> usize {
+    if columns == 0 {
+        return 0;
+    }
+    let separators = (columns - 1) * 3;
+    (total_width.saturating_sub(separators) / columns).max(10)
+}
+
+pub fn print_custom_fields(screen: &mut Screen, definitions: &[FieldDefinition], values: &BTreeMap<String, FieldValue>) {
+    if definitions.is_empty() {
+        return;
+    }
+
+    screen.blank();
+    for definition in definitions {
+        let value = values
+            .get(&definition.name)
+            .map(|value| value.to_string())
+            .unwrap_or_else(|| "-".to_owned());
+        screen.print(format!("{}: {}", definition.name, value));
+    }
+}
+
+/// Columns shared by the epic and story detail pages; long descriptions wrap.
+pub fn detail_table() -> Table {
+    Table::new(vec![
+        Column::new("id", 4),
+        Column::new("name", 12).priority(1),
+        Column::new("description", 12).priority(3).wrap(),
+        Column::new("status", 11).priority(2),
+    ])
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortColumn {
+    #[default]
+    Id,
+    Name,
+    Status,
+    Priority,
+    Updated,
+}
+
+impl Display for SortColumn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortColumn::Id => { write!(f, "id") }
+            SortColumn::Name => { write!(f, "name") }
+            SortColumn::Status => { write!(f, "status") }
+            SortColumn::Priority => { write!(f, "priority") }
+            SortColumn::Updated => { write!(f, "updated") }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Sort {
+    pub column: SortColumn,
+    pub descending: bool
+}
+
+/// What list pages can sort epics and stories by.
+pub trait Sortable {
+    fn name(&self) -> &str;
+    fn status(&self) -> &Status;
+    fn priority(&self) -> Option<Priority>;
+    fn updated_at(&self) -> Option<DateTime<Utc>>;
+}
+
+impl Sortable for Epic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn status(&self) -> &Status {
+        &self.status
+    }
+
+    fn priority(&self) -> Option<Priority> {
+        None
+    }
+
+    fn updated_at(&self) -> Option<DateTime<Utc>> {
+        self.updated_at
+    }
+}
+
+impl Sortable for Story {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn status(&self) -> &Status {
+        &self.status
+    }
+
+    fn priority(&self) -> Option<Priority> {
+        Some(self.priority)
+    }
+
+    fn updated_at(&self) -> Option<DateTime<Utc>> {
+        self.updated_at
+    }
+}
+
+/// `ids` ordered by `sort`. Rows that compare equal stay in id order.
+pub fn sorted_ids<T: Sortable>(items: &BTreeMap<ItemId, T>, ids: impl IntoIterator<Item = ItemId>, sort: Sort) -> Vec<ItemId> {
+    let status_rank = |status: &Status| Status::all().iter().position(|other| other == status);
+
+    let mut ids = ids.into_iter().sorted().collect::<Vec<_>>();
+    ids.sort_by(|a, b| {
+        let (a_item, b_item) = (&items[a], &items[b]);
+        let ordering = match sort.column {
+            SortColumn::Id => a.cmp(b),
+            SortColumn::Name => a_item.name().to_lowercase().cmp(&b_item.name().to_lowercase()),
+            SortColumn::Status => status_rank(a_item.status()).cmp(&status_rank(b_item.status())),
+            SortColumn::Priority => a_item.priority().cmp(&b_item.priority()),
+            SortColumn::Updated => a_item.updated_at().cmp(&b_item.updated_at())
+        };
+        if sort.descending { ordering.reverse() } else { ordering }
+    });
+    ids
+}
+
+pub fn format_updated_at(updated_at: Option<DateTime<Utc>>) -> String {
+    updated_at
+        .map(|updated_at| updated_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| "-".to_owned())
+}
+
+const DEFAULT_PAGE_SIZE: usize = 10;
+
+/// The part of a `ListView` kept between sessions. The page follows from the
+/// selected row, so it isn't stored.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ListState {
+    pub selected: usize,
+    pub sort: Sort
+}
+
+/// Selection, current page and sort order of a list page.
+///
+/// The selected index is clamped on every use, so it stays valid when rows are
+/// added or deleted between redraws. The current page is the one holding the
+/// selected row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListView {
+    selected: usize,
+    /// Rows per page at the last redraw, used by the paging keys.
+    rows_per_page: Cell<usize>,
+    pub sort: Sort,
+    sort_columns: &'static [SortColumn]
+}
+
+impl ListView {
+    /// A list sorted by the first of `sort_columns`; `o` cycles through the rest.
+    pub fn new(sort_columns: &'static [SortColumn]) -> Self {
+        ListView {
+            selected: 0,
+            rows_per_page: Cell::new(DEFAULT_PAGE_SIZE),
+            sort: Sort { column: sort_columns[0], descending: false },
+            sort_columns
+        }
+    }
+
+    /// Restores a saved view; a sort column this list doesn't offer is ignored.
+    pub fn with_state(mut self, state: ListState) -> Self {
+        self.selected = state.selected;
+        if self.sort_columns.contains(&state.sort.column) {
+            self.sort = state.sort;
         }
+        self
+    }
+
+    pub fn state(&self) -> ListState {
+        ListState { selected: self.selected, sort: self.sort }
+    }
+
+    pub fn clamp(&self, len: usize) -> usize {
+        self.selected.min(len.saturating_sub(1))
+    }
+
+    /// Sets the page size for this redraw: the configured size, or as many rows as
+    /// fit on the screen. Pages never grow past the screen so the header stays visible.
+    pub fn fit_page(&self, page_size: Option<usize>, available_rows: usize) {
+        let rows = page_size.unwrap_or(available_rows).min(available_rows).max(1);
+        self.rows_per_page.set(rows);
+    }
+
+    pub fn page(&self, len: usize) -> usize {
+        self.clamp(len) / self.rows_per_page.get()
+    }
+
+    pub fn page_count(&self, len: usize) -> usize {
+        len.div_ceil(self.rows_per_page.get()).max(1)
+    }
+
+    /// Rows on the current page.
+    pub fn visible_range(&self, len: usize) -> Range<usize> {
+        let start = self.page(len) * self.rows_per_page.get();
+        start..len.min(start + self.rows_per_page.get())
+    }
+
+    pub fn status_line(&self, len: usize) -> String {
+        let direction = if self.sort.descending { "descending" } else { "ascending" };
+        format!("page {}/{} | sorted by {} ({})", self.page(len) + 1, self.page_count(len), self.sort.column, direction)
+    }
+
+    pub fn handle_key(&mut self, key: Key, len: usize) -> KeyOutcome {
+        let selected = self.clamp(len);
+        let rows_per_page = self.rows_per_page.get();
+        let page_start = selected - selected % rows_per_page;
+
+        self.selected = match key {
+            Key::Up => selected.saturating_sub(1),
+            Key::Down => selected + 1,
+            Key::PageUp | Key::Char('[') => page_start.saturating_sub(rows_per_page),
+            Key::PageDown | Key::Char(']') => page_start + rows_per_page,
+            Key::Home => 0,
+            Key::End => len,
+            Key::Char('o') => {
+                let current = self.sort_columns.iter().position(|column| *column == self.sort.column).unwrap_or(0);
+                self.sort.column = self.sort_columns[(current + 1) % self.sort_columns.len()];
+                0
+            }
+            Key::Char('O') => {
+                self.sort.descending = !self.sort.descending;
+                0
+            }
+            _ => return KeyOutcome::Ignored
+        };
+        self.selected = self.clamp(len);
+        KeyOutcome::Handled
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn test_get_board_column_width() {
+        assert_eq!(get_board_column_width(80, 4), 17);
+        assert_eq!(get_board_column_width(200, 4), 47);
+        assert_eq!(get_board_column_width(20, 4), 10);
+        assert_eq!(get_board_column_width(80, 0), 0);
+    }
+
+    const COLUMNS: &[SortColumn] = &[SortColumn::Id, SortColumn::Name, SortColumn::Updated];
+
     #[test]
-    fn test_get_column_string() {
-        let text1 = "";
-        let text2 = "test";
-        let text3 = "testme";
-        let text4 = "testmetest";
+    fn list_view_should_page_through_rows() {
+        let mut list = ListView::new(COLUMNS);
+        list.fit_page(Some(10), 20);
+
+        assert_eq!(list.handle_key(Key::Up, 25), KeyOutcome::Handled);
+        assert_eq!(list.clamp(25), 0);
+        assert_eq!(list.visible_range(25), 0..10);
+        assert_eq!(list.status_line(25), "page 1/3 | sorted by id (ascending)".to_owned());
 
-        let width = 0;
+        list.handle_key(Key::Char(']'), 25);
+        assert_eq!(list.clamp(25), 10);
+        assert_eq!(list.visible_range(25), 10..20);
 
-        assert_eq!(get_column_string(text4, width), "".to_owned());
+        list.handle_key(Key::Down, 25);
+        list.handle_key(Key::PageDown, 25);
+        assert_eq!(list.clamp(25), 20);
+        assert_eq!(list.visible_range(25), 20..25);
 
-        let width = 1;
+        list.handle_key(Key::PageDown, 25);
+        assert_eq!(list.clamp(25), 24);
+        assert_eq!(list.page(25), 2);
 
-        assert_eq!(get_column_string(text4, width), ".".to_owned());
+        list.handle_key(Key::Char('['), 25);
+        assert_eq!(list.clamp(25), 10);
 
-        let width = 2;
+        list.handle_key(Key::End, 25);
+        assert_eq!(list.clamp(25), 24);
+        assert_eq!(list.clamp(3), 2);
 
-        assert_eq!(get_column_string(text4, width), "..".to_owned());
+        assert_eq!(list.handle_key(Key::Char('x'), 25), KeyOutcome::Ignored);
+        assert_eq!(list.visible_range(0), 0..0);
+        assert_eq!(list.page_count(0), 1);
+    }
+
+    #[test]
+    fn list_view_should_fit_page_to_screen() {
+        let list = ListView::new(COLUMNS);
+
+        list.fit_page(None, 7);
+        assert_eq!(list.visible_range(30), 0..7);
+
+        list.fit_page(Some(50), 7);
+        assert_eq!(list.visible_range(30), 0..7);
+
+        list.fit_page(Some(5), 0);
+        assert_eq!(list.visible_range(30), 0..1);
+    }
+
+    #[test]
+    fn list_view_should_cycle_sort_column_and_direction() {
+        let mut list = ListView::new(COLUMNS);
+        list.handle_key(Key::Down, 25);
 
-        let width = 3;
+        assert_eq!(list.handle_key(Key::Char('o'), 25), KeyOutcome::Handled);
+        assert_eq!(list.sort, Sort { column: SortColumn::Name, descending: false });
+        assert_eq!(list.clamp(25), 0);
 
-        assert_eq!(get_column_string(text4, width), "...".to_owned());
+        list.handle_key(Key::Char('O'), 25);
+        assert_eq!(list.sort, Sort { column: SortColumn::Name, descending: true });
 
-        let width = 4;
+        list.handle_key(Key::Char('o'), 25);
+        list.handle_key(Key::Char('o'), 25);
+        assert_eq!(list.sort.column, SortColumn::Id);
+    }
 
-        assert_eq!(get_column_string(text4, width), "t...".to_owned());
+    #[test]
+    fn sorted_ids_should_order_by_column() {
+        let mut stories = BTreeMap::new();
+        let mut story = Story::new("b".to_owned(), "".to_owned());
+        story.priority = Priority::High;
+        story.status = Status::Closed;
+        stories.insert(1, story);
+        let mut story = Story::new("C".to_owned(), "".to_owned());
+        story.priority = Priority::Low;
+        story.updated_at = Some(Utc::now());
+        stories.insert(2, story);
+        let mut story = Story::new("a".to_owned(), "".to_owned());
+        story.status = Status::InProgress;
+        stories.insert(3, story);
 
-        let width = 6;
+        let sort = |column, descending| sorted_ids(&stories, stories.keys().copied(), Sort { column, descending });
 
-        assert_eq!(get_column_string(text1, width), "      ".to_owned());
-        assert_eq!(get_column_string(text2, width), "test  ".to_owned());
-        assert_eq!(get_column_string(text3, width), "testme".to_owned());
-        assert_eq!(get_column_string(text4, width), "tes...".to_owned());
+        assert_eq!(sort(SortColumn::Id, false), vec![1, 2, 3]);
+        assert_eq!(sort(SortColumn::Id, true), vec![3, 2, 1]);
+        assert_eq!(sort(SortColumn::Name, false), vec![3, 1, 2]);
+        assert_eq!(sort(SortColumn::Status, false), vec![2, 3, 1]);
+        assert_eq!(sort(SortColumn::Priority, true), vec![1, 3, 2]);
+        assert_eq!(sort(SortColumn::Updated, true), vec![2, 1, 3]);
     }
-}
\ No newline at end of file
+}
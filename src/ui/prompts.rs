@@ -1,92 +1,379 @@
-use crate::io_utils::{get_user_input, print_line};
-use crate::models::{Epic, Status, Story};
+use std::rc::Rc;
+use chrono::{Duration, Local, NaiveDate};
+use crate::models::{Epic, FieldDefinition, FieldKind, FieldTarget, FieldValue, IssueTypeConfig, Priority, Sprint, SprintRollover, Status, Story};
+use crate::ui::dialogs::{confirm, parsed_input, select, text_input};
+use crate::ui::terminal::SharedTerminal;
 
+type CreateStoryPrompt = Box<dyn Fn(&[IssueTypeConfig]) -> Option<Story>>;
+type UpdateFieldPrompt = Box<dyn Fn(&[FieldDefinition]) -> Option<(String, String)>>;
+type CloseSprintPrompt = Box<dyn Fn(Option<&Sprint>) -> Option<SprintRollover>>;
+
+/// Modal dialogs used by `Navigator` to ask for input. Each prompt is a closure so
+/// tests can replace it with a canned answer.
 pub struct Prompts {
-    pub create_epic: Box<dyn Fn() -> Epic>,
-    pub create_story: Box<dyn Fn() -> Story>,
+    pub create_epic: Box<dyn Fn() -> Option<Epic>>,
+    pub create_story: CreateStoryPrompt,
     pub delete_epic: Box<dyn Fn() -> bool>,
     pub delete_story: Box<dyn Fn() -> bool>,
-    pub update_status: Box<dyn Fn() -> Option<Status>>
+    pub update_status: Box<dyn Fn() -> Option<Status>>,
+    pub search: Box<dyn Fn() -> Option<String>>,
+    pub create_field_definition: Box<dyn Fn() -> Option<FieldDefinition>>,
+    pub update_field: UpdateFieldPrompt,
+    pub update_points: Box<dyn Fn() -> Option<Option<u32>>>,
+    pub update_priority: Box<dyn Fn() -> Option<Priority>>,
+    pub update_due_date: Box<dyn Fn() -> Option<Option<NaiveDate>>>,
+    pub create_sprint: Box<dyn Fn() -> Option<Sprint>>,
+    pub close_sprint: CloseSprintPrompt
 }
 
 impl Prompts {
-    pub fn new() -> Self {
+    pub fn new(terminal: SharedTerminal) -> Self {
         Self {
-            create_epic: Box::new(create_epic_prompt),
-            create_story: Box::new(create_story_prompt),
-            delete_epic: Box::new(delete_epic_prompt),
-            delete_story: Box::new(delete_story_prompt),
-            update_status: Box::new(update_status_prompt),
+            create_epic: {
+                let terminal = Rc::clone(&terminal);
+                Box::new(move || create_epic_prompt(&terminal))
+            },
+            create_story: {
+                let terminal = Rc::clone(&terminal);
+                Box::new(move |issue_types| create_story_prompt(&terminal, issue_types))
+            },
+            delete_epic: {
+                let terminal = Rc::clone(&terminal);
+                Box::new(move || delete_epic_prompt(&terminal))
+            },
+            delete_story: {
+                let terminal = Rc::clone(&terminal);
+                Box::new(move || delete_story_prompt(&terminal))
+            },
+            update_status: {
+                let terminal = Rc::clone(&terminal);
+                Box::new(move || update_status_prompt(&terminal))
+            },
+            search: {
+                let terminal = Rc::clone(&terminal);
+                Box::new(move || search_prompt(&terminal))
+            },
+            create_field_definition: {
+                let terminal = Rc::clone(&terminal);
+                Box::new(move || create_field_definition_prompt(&terminal))
+            },
+            update_field: {
+                let terminal = Rc::clone(&terminal);
+                Box::new(move |fields| update_field_prompt(&terminal, fields))
+            },
+            update_points: {
+                let terminal = Rc::clone(&terminal);
+                Box::new(move || update_points_prompt(&terminal))
+            },
+            update_priority: {
+                let terminal = Rc::clone(&terminal);
+                Box::new(move || update_priority_prompt(&terminal))
+            },
+            update_due_date: {
+                let terminal = Rc::clone(&terminal);
+                Box::new(move || update_due_date_prompt(&terminal))
+            },
+            create_sprint: {
+                let terminal = Rc::clone(&terminal);
+                Box::new(move || create_sprint_prompt(&terminal))
+            },
+            close_sprint: {
+                let terminal = Rc::clone(&terminal);
+                Box::new(move |next_sprint| close_sprint_prompt(&terminal, next_sprint))
+            },
         }
     }
 }
 
-fn create_epic_prompt() -> Epic {
-    print_line();
-    println!("Epic name: ");
-    let epic_name = get_user_input();
-    println!("Epic description: ");
-    let epic_description = get_user_input();
+fn create_epic_prompt(terminal: &SharedTerminal) -> Option<Epic> {
+    let epic_name = text_input(terminal, "Create epic", "Epic name:")?;
+    let epic_description = text_input(terminal, "Create epic", "Epic description:")?;
     let epic = Epic::new(
         epic_name.trim().to_owned(), epic_description.trim().to_owned()
     );
-    epic
+    Some(epic)
 }
 
-fn create_story_prompt() -> Story {
-    print_line();
-    println!("Story name: ");
-    let story_name = get_user_input();
-    println!("Story description: ");
-    let story_description = get_user_input();
-    let story = Story::new(
+fn create_story_prompt(terminal: &SharedTerminal, issue_types: &[IssueTypeConfig]) -> Option<Story> {
+    let story_name = text_input(terminal, "Create story", "Story name:")?;
+    let story_description = text_input(terminal, "Create story", "Story description:")?;
+    let mut story = Story::new(
         story_name.trim().to_owned(),
         story_description.trim().to_owned());
-    story
+
+    if let Some(config) = issue_type_prompt(terminal, issue_types)? {
+        let title = format!("Create {}", config.issue_type.name().to_lowercase());
+        for field in &config.fields {
+            if let Some(value) = type_field_prompt(terminal, &title, field)? {
+                story.fields.insert(field.name.clone(), value);
+            }
+        }
+        story = story.with_issue_type(config.issue_type.clone());
+    }
+    Some(story)
 }
 
-fn delete_epic_prompt() -> bool {
-    print_line();
-    println!("Are you sure you want to delete this epic? All stories in this epic will be deleted too [Y/n]:");
-    let input = get_user_input();
-    if input.trim().eq("Y") {
-        return true;
+/// Only asks when there is a choice; without configured types the story keeps the default.
+fn issue_type_prompt<'a>(terminal: &SharedTerminal, issue_types: &'a [IssueTypeConfig]) -> Option<Option<&'a IssueTypeConfig>> {
+    if issue_types.len() <= 1 {
+        return Some(issue_types.first());
     }
-    false
+
+    let options = issue_types
+        .iter()
+        .map(|config| format!("[{}] {}", config.icon, config.issue_type))
+        .collect::<Vec<_>>();
+
+    select(terminal, "Issue type", &options).map(|idx| Some(&issue_types[idx]))
 }
 
-fn delete_story_prompt() -> bool {
-    print_line();
-    println!("Are you sure you want to delete this story? [Y/n]:");
-    let input = get_user_input();
-    if input.trim().eq("Y") {
-        return true;
+/// Asks for one of the fields of the chosen issue type. The outer `None` means the
+/// dialog was cancelled, the inner one that the field was left empty.
+fn type_field_prompt(terminal: &SharedTerminal, title: &str, field: &FieldDefinition) -> Option<Option<FieldValue>> {
+    if let FieldKind::SingleSelect { options } = &field.kind {
+        return select(terminal, &field.name, options).map(|idx| Some(FieldValue::SingleSelect(options[idx].clone())));
     }
-    false
+
+    parsed_input(terminal, title, &format!("{} (leave empty to skip):", field.name), |value| match value {
+        "" => Ok(None),
+        value => field.parse_value(value).map(Some).map_err(|err| err.to_string())
+    })
+}
+
+fn delete_epic_prompt(terminal: &SharedTerminal) -> bool {
+    confirm(terminal, "Delete epic", "Are you sure you want to delete this epic? All stories in this epic will be deleted too.")
 }
 
-fn update_status_prompt() -> Option<Status> {
-    print_line();
-    println!("New status (1 - OPEN, 2 - IN-PROGRESS, 3 - RESOLVED, 4 - CLOSED): ");
-    let status = get_user_input();
-    let status = status.trim().parse::<u8>();
+fn delete_story_prompt(terminal: &SharedTerminal) -> bool {
+    confirm(terminal, "Delete story", "Are you sure you want to delete this story?")
+}
 
-    if let Ok(status) = status {
-        return match status {
-            1 => {
-                Some(Status::Open)
-            }
-            2 => {
-                Some(Status::InProgress)
-            }
-            3 => {
-                Some(Status::Resolved)
+fn update_status_prompt(terminal: &SharedTerminal) -> Option<Status> {
+    let statuses = Status::all();
+    let options = statuses.iter().map(|status| status.to_string()).collect::<Vec<_>>();
+
+    select(terminal, "New status", &options).map(|idx| statuses[idx].clone())
+}
+
+fn search_prompt(terminal: &SharedTerminal) -> Option<String> {
+    text_input(terminal, "Search stories", "Query (e.g. type:bug status:open login):")
+        .map(|query| query.trim().to_owned())
+}
+
+fn create_field_definition_prompt(terminal: &SharedTerminal) -> Option<FieldDefinition> {
+    let name = text_input(terminal, "Define custom field", "Field name:")?.trim().to_owned();
+
+    let kinds = ["TEXT", "NUMBER", "DATE", "SINGLE-SELECT", "MULTI-SELECT", "USER"].map(String::from);
+    let kind = match select(terminal, "Field type", &kinds)? {
+        0 => FieldKind::Text,
+        1 => FieldKind::Number,
+        2 => FieldKind::Date,
+        3 => FieldKind::SingleSelect { options: options_prompt(terminal)? },
+        4 => FieldKind::MultiSelect { options: options_prompt(terminal)? },
+        _ => FieldKind::User
+    };
+
+    let targets = ["EPICS", "STORIES", "BOTH"].map(String::from);
+    let target = match select(terminal, "Applies to", &targets)? {
+        0 => FieldTarget::Epics,
+        1 => FieldTarget::Stories,
+        _ => FieldTarget::Both
+    };
+
+    Some(FieldDefinition::new(name, kind, target))
+}
+
+fn options_prompt(terminal: &SharedTerminal) -> Option<Vec<String>> {
+    let options = text_input(terminal, "Define custom field", "Options (comma separated):")?;
+    Some(options
+        .split(',')
+        .map(|option| option.trim().to_owned())
+        .filter(|option| !option.is_empty())
+        .collect())
+}
+
+fn update_field_prompt(terminal: &SharedTerminal, fields: &[FieldDefinition]) -> Option<(String, String)> {
+    let options = fields
+        .iter()
+        .map(|field| format!("{} ({})", field.name, field.kind))
+        .collect::<Vec<_>>();
+    let field = &fields[select(terminal, "Set field", &options)?];
+
+    let hint = match field.kind {
+        FieldKind::Date => " (YYYY-MM-DD)",
+        FieldKind::MultiSelect { .. } => " (comma separated)",
+        _ => ""
+    };
+    let value = text_input(terminal, &field.name, &format!("Value{}, leave empty to clear:", hint))?;
+
+    Some((field.name.clone(), value.trim().to_owned()))
+}
+
+/// Asks again until the input is a whole number, so a typo never clears the estimate.
+fn update_points_prompt(terminal: &SharedTerminal) -> Option<Option<u32>> {
+    parsed_input(terminal, "Estimate", "Story points (leave empty to clear):", |points| match points {
+        "" => Ok(None),
+        points => points.parse().map(Some).map_err(|_| format!("'{}' is not a whole number of points", points))
+    })
+}
+
+fn update_priority_prompt(terminal: &SharedTerminal) -> Option<Priority> {
+    let priorities = Priority::all();
+    let options = priorities.iter().map(|priority| priority.to_string()).collect::<Vec<_>>();
+
+    select(terminal, "Priority", &options).map(|idx| priorities[idx])
+}
+
+fn update_due_date_prompt(terminal: &SharedTerminal) -> Option<Option<NaiveDate>> {
+    parsed_input(terminal, "Due date", "Due date (YYYY-MM-DD, leave empty to clear):", |input| match input {
+        "" => Ok(None),
+        input => parse_date(input).map(Some)
+    })
+}
+
+fn create_sprint_prompt(terminal: &SharedTerminal) -> Option<Sprint> {
+    let name = text_input(terminal, "Create sprint", "Sprint name:")?;
+    let goal = text_input(terminal, "Create sprint", "Sprint goal:")?;
+
+    let today = Local::now().date_naive();
+    let start = date_prompt(terminal, "Start date", today)?;
+
+    let two_weeks_later = start + Duration::days(13);
+    let end = date_prompt(terminal, "End date", two_weeks_later)?;
+
+    Some(Sprint::new(name.trim().to_owned(), goal.trim().to_owned(), start, end))
+}
+
+/// Asks again on a mistyped date, so the name and goal typed before aren't lost.
+fn date_prompt(terminal: &SharedTerminal, label: &str, default: NaiveDate) -> Option<NaiveDate> {
+    parsed_input(terminal, "Create sprint", &format!("{} (YYYY-MM-DD, leave empty for {}):", label, default), |input| match input {
+        "" => Ok(default),
+        input => parse_date(input)
+    })
+}
+
+fn parse_date(input: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(input, "%Y-%m-%d").map_err(|_| format!("'{}' is not a date like 2024-05-01", input))
+}
+
+fn close_sprint_prompt(terminal: &SharedTerminal, next_sprint: Option<&Sprint>) -> Option<SprintRollover> {
+    match next_sprint {
+        Some(next_sprint) => {
+            let options = vec![format!("NEXT SPRINT \"{}\"", next_sprint.name), "BACKLOG".to_owned()];
+            match select(terminal, "Move incomplete stories to", &options)? {
+                0 => Some(SprintRollover::NextSprint),
+                _ => Some(SprintRollover::Backlog)
             }
-            4 => {
-                Some(Status::Closed)
+        }
+        None => {
+            if confirm(terminal, "Close sprint", "There is no planned sprint, incomplete stories will be moved to the backlog. Close sprint?") {
+                return Some(SprintRollover::Backlog);
             }
-            _ => None
+            None
         }
     }
-    None
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{default_issue_types, IssueType};
+    use crate::ui::terminal::{HeadlessBackend, Key, Terminal};
+
+    #[test]
+    fn create_story_prompt_should_ask_for_bug_details() {
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24)
+            .with_text("crash")
+            .with_keys(vec![Key::Enter, Key::Enter, Key::Down, Key::Enter])
+            .with_text("click login")
+            .with_keys(vec![Key::Enter])
+            .with_text("4"));
+        let prompts = Prompts::new(terminal);
+
+        let story = (prompts.create_story)(&default_issue_types()).unwrap();
+
+        assert_eq!(story.name, "crash".to_owned());
+        assert_eq!(story.issue_type, IssueType::new("Bug"));
+        assert_eq!(story.fields.get("Steps to reproduce"), Some(&FieldValue::Text("click login".to_owned())));
+        assert_eq!(story.fields.get("Severity"), Some(&FieldValue::SingleSelect("CRITICAL".to_owned())));
+    }
+
+    #[test]
+    fn create_story_prompt_should_ask_for_the_fields_of_a_configured_type() {
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24)
+            .with_text("outage")
+            .with_keys(vec![Key::Enter, Key::Enter, Key::Down, Key::Enter])
+            .with_text("lots")
+            .with_keys(vec![Key::Enter])
+            .with_text("30")
+            .with_keys(vec![Key::Enter]));
+        let prompts = Prompts::new(terminal);
+        let issue_types = vec![
+            IssueTypeConfig::new("Story", "S"),
+            IssueTypeConfig::new("Incident", "!").with_field("Minutes down", FieldKind::Number),
+        ];
+
+        let story = (prompts.create_story)(&issue_types).unwrap();
+
+        assert_eq!(story.issue_type, IssueType::new("Incident"));
+        assert_eq!(story.fields.get("Minutes down"), Some(&FieldValue::Number(30.0)));
+    }
+
+    #[test]
+    fn update_points_prompt_should_ask_again_on_invalid_input() {
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24)
+            .with_text("3.5")
+            .with_keys(vec![Key::Enter])
+            .with_text("five")
+            .with_keys(vec![Key::Enter])
+            .with_text("5")
+            .with_keys(vec![Key::Enter, Key::Enter, Key::Esc]));
+        let prompts = Prompts::new(terminal);
+
+        assert_eq!((prompts.update_points)(), Some(Some(5)));
+        assert_eq!((prompts.update_points)(), Some(None));
+        assert_eq!((prompts.update_points)(), None);
+    }
+
+    #[test]
+    fn update_due_date_prompt_should_show_the_error_and_ask_again() {
+        let backend = HeadlessBackend::new(80, 24)
+            .with_text("2024-13-01")
+            .with_keys(vec![Key::Enter])
+            .with_text("2024-05-01")
+            .with_keys(vec![Key::Enter]);
+        let frames = Rc::clone(&backend.frames);
+        let prompts = Prompts::new(Terminal::shared(backend));
+
+        assert_eq!((prompts.update_due_date)(), Some(NaiveDate::from_ymd_opt(2024, 5, 1)));
+        assert_eq!(frames.borrow().last().unwrap().text().join("\n").contains("'2024-13-01' is not a date like 2024-05-01"), true);
+    }
+
+    #[test]
+    fn create_sprint_prompt_should_keep_the_name_after_a_mistyped_date() {
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24)
+            .with_text("Sprint 1")
+            .with_keys(vec![Key::Enter, Key::Enter])
+            .with_text("tomorrow")
+            .with_keys(vec![Key::Enter])
+            .with_text("2024-01-01")
+            .with_keys(vec![Key::Enter, Key::Enter]));
+        let prompts = Prompts::new(terminal);
+
+        let sprint = (prompts.create_sprint)().unwrap();
+
+        assert_eq!(sprint.name, "Sprint 1");
+        assert_eq!((sprint.start, sprint.end), (NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 1, 14).unwrap()));
+    }
+
+    #[test]
+    fn prompts_should_return_none_when_cancelled() {
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24)
+            .with_text("name")
+            .with_keys(vec![Key::Enter, Key::Esc, Key::Esc]));
+        let prompts = Prompts::new(terminal);
+
+        assert_eq!((prompts.create_epic)(), None);
+        assert_eq!((prompts.update_status)(), None);
+    }
+}
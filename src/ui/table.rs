@@ -0,0 +1,266 @@
+use std::ops::Range;
+use itertools::Itertools;
+use crate::ui::terminal::{Line, Screen, Span, Style};
+use crate::ui::text::{display_width, get_column_string, wrap_text};
+
+const SEPARATOR: &str = " | ";
+
+pub struct Column {
+    pub header: String,
+    pub min_width: usize,
+    /// Lower values are more important: they get spare width first and are the
+    /// last to be hidden on narrow terminals.
+    pub priority: usize,
+    /// Wrap long cells onto several lines instead of eliding them.
+    pub wrap: bool
+}
+
+impl Column {
+    pub fn new(header: &str, min_width: usize) -> Self {
+        Column { header: header.to_owned(), min_width, priority: 0, wrap: false }
+    }
+
+    pub fn priority(mut self, priority: usize) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn wrap(mut self) -> Self {
+        self.wrap = true;
+        self
+    }
+}
+
+pub struct Row {
+    cells: Vec<String>,
+    style: Style,
+    cell_styles: Vec<Style>
+}
+
+impl Row {
+    /// Style of the whole row, e.g. dimmed for closed items.
+    pub fn style(&mut self, style: Style) -> &mut Self {
+        self.style = style;
+        self
+    }
+
+    /// Style of a single cell, layered on top of the row style.
+    pub fn cell_style(&mut self, column: usize, style: Style) -> &mut Self {
+        if self.cell_styles.len() <= column {
+            self.cell_styles.resize(column + 1, Style::default());
+        }
+        self.cell_styles[column] = style;
+        self
+    }
+}
+
+/// Table whose column widths follow the width of the screen it is printed on.
+///
+/// Every visible column gets at least its minimum width; the remaining space goes
+/// to columns in priority order until their content fits. When even the minimum
+/// widths don't fit, the least important columns are hidden.
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Row>
+}
+
+impl Table {
+    pub fn new(columns: Vec<Column>) -> Self {
+        Table { columns, rows: vec![] }
+    }
+
+    pub fn add_row(&mut self, cells: Vec<String>) -> &mut Row {
+        self.rows.push(Row { cells, style: Style::default(), cell_styles: vec![] });
+        self.rows.last_mut().unwrap()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Width of every column, `None` for columns hidden to make the table fit.
+    pub fn layout(&self, total_width: usize) -> Vec<Option<usize>> {
+        let mut visible = vec![true; self.columns.len()];
+        loop {
+            let shown = (0..self.columns.len()).filter(|idx| visible[*idx]).collect::<Vec<_>>();
+            let needed = shown.iter().map(|idx| self.columns[*idx].min_width).sum::<usize>()
+                + shown.len().saturating_sub(1) * SEPARATOR.len();
+            if needed <= total_width || shown.len() <= 1 {
+                break;
+            }
+            let least_important = shown
+                .into_iter()
+                .max_by_key(|idx| (self.columns[*idx].priority, *idx))
+                .unwrap();
+            visible[least_important] = false;
+        }
+
+        let shown = (0..self.columns.len()).filter(|idx| visible[*idx]).collect::<Vec<_>>();
+        let mut widths = self.columns
+            .iter()
+            .zip(&visible)
+            .map(|(column, visible)| visible.then_some(column.min_width))
+            .collect::<Vec<_>>();
+
+        let used = shown.iter().map(|idx| self.columns[*idx].min_width).sum::<usize>()
+            + shown.len().saturating_sub(1) * SEPARATOR.len();
+        let mut spare = total_width.saturating_sub(used);
+
+        for idx in shown.into_iter().sorted_by_key(|idx| (self.columns[*idx].priority, *idx)) {
+            let width = widths[idx].unwrap_or_default();
+            let grow = self.content_width(idx).saturating_sub(width).min(spare);
+            widths[idx] = Some(width + grow);
+            spare -= grow;
+        }
+
+        widths
+    }
+
+    /// Prints the header and the rows in `rows`, highlighting the `selected` row.
+    pub fn print(&self, screen: &mut Screen, rows: Range<usize>, selected: Option<usize>) {
+        let widths = self.layout(screen.width);
+
+        let header = self.columns
+            .iter()
+            .zip(&widths)
+            .filter_map(|(column, width)| width.map(|width| get_column_string(&column.header, width)))
+            .join(SEPARATOR);
+        screen.print(Line::styled(header, screen.theme.header));
+
+        for idx in rows {
+            let selected_style = if selected == Some(idx) { screen.theme.selected } else { Style::default() };
+            for line in self.row_lines(&self.rows[idx], &widths, selected_style) {
+                screen.print(line);
+            }
+        }
+    }
+
+    /// Prints the header and every row.
+    pub fn print_all(&self, screen: &mut Screen) {
+        self.print(screen, 0..self.len(), None)
+    }
+
+    fn content_width(&self, idx: usize) -> usize {
+        self.rows
+            .iter()
+            .filter_map(|row| row.cells.get(idx))
+            .map(|cell| display_width(cell))
+            .chain([display_width(&self.columns[idx].header)])
+            .max()
+            .unwrap_or_default()
+    }
+
+    fn row_lines(&self, row: &Row, widths: &[Option<usize>], selected_style: Style) -> Vec<Line> {
+        let row_style = row.style.patch(selected_style);
+        let cells = self.columns
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .filter_map(|(idx, (column, width))| {
+                let width = (*width)?;
+                let cell = row.cells.get(idx).map(String::as_str).unwrap_or_default();
+                let lines = if column.wrap {
+                    wrap_text(cell, width)
+                } else {
+                    vec![cell.to_owned()]
+                };
+                let style = row.style
+                    .patch(row.cell_styles.get(idx).copied().unwrap_or_default())
+                    .patch(selected_style);
+                Some((width, lines, style))
+            })
+            .collect::<Vec<_>>();
+
+        let height = cells.iter().map(|(_, lines, _)| lines.len()).max().unwrap_or(1);
+        (0..height)
+            .map(|line| {
+                let mut spans = vec![];
+                for (idx, (width, lines, style)) in cells.iter().enumerate() {
+                    if idx > 0 {
+                        spans.push(Span { text: SEPARATOR.to_owned(), style: row_style });
+                    }
+                    let text = get_column_string(lines.get(line).map(String::as_str).unwrap_or_default(), *width);
+                    spans.push(Span { text, style: *style });
+                }
+                Line { spans }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table {
+        let mut table = Table::new(vec![
+            Column::new("id", 4),
+            Column::new("name", 8).priority(1),
+            Column::new("description", 11).priority(3).wrap(),
+            Column::new("status", 11).priority(2),
+        ]);
+        table.add_row(vec!["1".to_owned(), "a rather long epic name".to_owned(), "fix the login page".to_owned(), "OPEN".to_owned()]);
+        table
+    }
+
+    #[test]
+    fn layout_should_grow_columns_up_to_their_content() {
+        assert_eq!(table().layout(200), vec![Some(4), Some(23), Some(18), Some(11)]);
+    }
+
+    #[test]
+    fn layout_should_give_spare_width_by_priority() {
+        assert_eq!(table().layout(50), vec![Some(4), Some(15), Some(11), Some(11)]);
+    }
+
+    #[test]
+    fn layout_should_hide_least_important_columns() {
+        assert_eq!(table().layout(30), vec![Some(4), Some(9), None, Some(11)]);
+        assert_eq!(table().layout(10), vec![Some(4), None, None, None]);
+    }
+
+    #[test]
+    fn print_should_wrap_and_elide_cells() {
+        let table = table();
+        let mut screen = Screen::new(50, 10);
+        table.print_all(&mut screen);
+
+        assert_eq!(screen.text(), vec![
+            "id   | name            | description | status     ".to_owned(),
+            "1    | a rather lon... | fix the     | OPEN       ".to_owned(),
+            "     |                 | login page  |            ".to_owned(),
+        ]);
+    }
+
+    #[test]
+    fn print_should_apply_row_and_cell_styles() {
+        let mut table = table();
+        table.add_row(vec!["2".to_owned(), "".to_owned(), "".to_owned(), "CLOSED".to_owned()])
+            .style(Style::dim())
+            .cell_style(3, Style::bold());
+        let mut screen = Screen::new(200, 10);
+        table.print(&mut screen, 1..2, None);
+
+        let spans = &screen.lines()[1].spans;
+        assert_eq!(spans.len(), 7);
+        assert_eq!(spans[0].style, Style::dim());
+        assert_eq!(spans[5].style, Style::dim());
+        assert_eq!(spans[6].style, Style { dim: true, bold: true, ..Style::default() });
+    }
+
+    #[test]
+    fn print_should_highlight_the_selected_row() {
+        let mut table = table();
+        table.add_row(vec!["2".to_owned(), "".to_owned(), "".to_owned(), "DONE".to_owned()]);
+        let mut screen = Screen::new(200, 10);
+        table.print(&mut screen, 1..2, Some(1));
+
+        assert_eq!(screen.lines().len(), 2);
+        assert_eq!(screen.lines()[1].spans[0].style, Style::reversed());
+        assert_eq!(screen.lines()[1].text().starts_with("2    | "), true);
+    }
+}
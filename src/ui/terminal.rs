@@ -0,0 +1,508 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{stdout, Stdout, Write};
+use std::rc::Rc;
+use anyhow::{anyhow, Result};
+use crossterm::{cursor, execute, queue, style, terminal};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crate::keymap::Keymap;
+use crate::ui::text::{display_width, get_column_string};
+use crate::ui::theme::Theme;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    Enter,
+    Esc,
+    Backspace,
+    Tab,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    /// The terminal was resized; nothing to handle, but the next frame uses the new size.
+    Resize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    Grey,
+    DarkRed,
+    DarkGreen,
+    DarkYellow,
+    DarkBlue,
+    DarkMagenta,
+    DarkCyan,
+    DarkGrey,
+}
+
+impl From<Color> for style::Color {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Black => style::Color::Black,
+            Color::Red => style::Color::Red,
+            Color::Green => style::Color::Green,
+            Color::Yellow => style::Color::Yellow,
+            Color::Blue => style::Color::Blue,
+            Color::Magenta => style::Color::Magenta,
+            Color::Cyan => style::Color::Cyan,
+            Color::White => style::Color::White,
+            Color::Grey => style::Color::Grey,
+            Color::DarkRed => style::Color::DarkRed,
+            Color::DarkGreen => style::Color::DarkGreen,
+            Color::DarkYellow => style::Color::DarkYellow,
+            Color::DarkBlue => style::Color::DarkBlue,
+            Color::DarkMagenta => style::Color::DarkMagenta,
+            Color::DarkCyan => style::Color::DarkCyan,
+            Color::DarkGrey => style::Color::DarkGrey,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Style {
+    pub bold: bool,
+    pub dim: bool,
+    pub reversed: bool,
+    pub fg: Option<Color>,
+    pub bg: Option<Color>
+}
+
+impl Style {
+    pub fn bold() -> Self {
+        Style { bold: true, ..Style::default() }
+    }
+
+    pub fn dim() -> Self {
+        Style { dim: true, ..Style::default() }
+    }
+
+    pub fn reversed() -> Self {
+        Style { reversed: true, ..Style::default() }
+    }
+
+    pub fn fg(color: Color) -> Self {
+        Style { fg: Some(color), ..Style::default() }
+    }
+
+    pub fn with_bg(mut self, color: Color) -> Self {
+        self.bg = Some(color);
+        self
+    }
+
+    pub fn with_bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    /// Layers `other` on top of this style: attributes add up and its colors win.
+    pub fn patch(self, other: Style) -> Style {
+        Style {
+            bold: self.bold || other.bold,
+            dim: self.dim || other.dim,
+            reversed: self.reversed || other.reversed,
+            fg: other.fg.or(self.fg),
+            bg: other.bg.or(self.bg)
+        }
+    }
+
+    fn content_style(&self) -> style::ContentStyle {
+        let mut content_style = style::ContentStyle::new();
+        content_style.foreground_color = self.fg.map(Into::into);
+        content_style.background_color = self.bg.map(Into::into);
+        if self.bold {
+            content_style.attributes.set(style::Attribute::Bold);
+        }
+        if self.dim {
+            content_style.attributes.set(style::Attribute::Dim);
+        }
+        if self.reversed {
+            content_style.attributes.set(style::Attribute::Reverse);
+        }
+        content_style
+    }
+
+    /// `text` wrapped in the ANSI escape codes for this style, for line based output.
+    pub fn paint(&self, text: &str) -> String {
+        if *self == Style::default() {
+            return text.to_owned();
+        }
+        self.content_style().apply(text).to_string()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Span {
+    pub text: String,
+    pub style: Style
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Line {
+    pub spans: Vec<Span>
+}
+
+impl Line {
+    pub fn styled(text: impl Into<String>, style: Style) -> Self {
+        Line { spans: vec![Span { text: text.into(), style }] }
+    }
+
+    /// The line without any styling, as a headless backend would show it.
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+}
+
+impl From<String> for Line {
+    fn from(text: String) -> Self {
+        Line::styled(text, Style::default())
+    }
+}
+
+impl From<&str> for Line {
+    fn from(text: &str) -> Self {
+        Line::styled(text, Style::default())
+    }
+}
+
+/// The area a page draws into. Pages use `height` to decide how many list rows fit,
+/// `theme` to pick styles, `page_size` to limit the rows of one list page and
+/// `keymap` to list their key bindings.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Screen {
+    pub width: usize,
+    pub height: usize,
+    pub theme: Theme,
+    pub page_size: Option<usize>,
+    pub keymap: Rc<Keymap>,
+    lines: Vec<Line>
+}
+
+impl Screen {
+    pub fn new(width: usize, height: usize) -> Self {
+        Screen {
+            width,
+            height,
+            theme: Theme::default(),
+            page_size: None,
+            keymap: Rc::new(Keymap::default()),
+            lines: vec![]
+        }
+    }
+
+    pub fn with_keymap(mut self, keymap: Rc<Keymap>) -> Self {
+        self.keymap = keymap;
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn with_page_size(mut self, page_size: Option<usize>) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    pub fn print(&mut self, line: impl Into<Line>) {
+        self.lines.push(line.into());
+    }
+
+    pub fn blank(&mut self) {
+        self.lines.push(Line::default());
+    }
+
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    pub fn text(&self) -> Vec<String> {
+        self.lines.iter().map(|line| line.text()).collect()
+    }
+
+    fn truncate(&mut self, height: usize) {
+        self.lines.truncate(height);
+    }
+}
+
+pub trait Backend {
+    fn size(&self) -> Result<(usize, usize)>;
+    fn draw(&mut self, screen: &Screen) -> Result<()>;
+    fn read_key(&mut self) -> Result<Key>;
+}
+
+pub type SharedTerminal = Rc<RefCell<Terminal>>;
+
+/// Wraps a backend and remembers the last drawn screen so modal dialogs can be
+/// drawn on top of the page they were opened from.
+pub struct Terminal {
+    backend: Box<dyn Backend>,
+    last_screen: Screen
+}
+
+impl Terminal {
+    pub fn new(backend: Box<dyn Backend>) -> Self {
+        Terminal { backend, last_screen: Screen::new(0, 0) }
+    }
+
+    pub fn shared(backend: impl Backend + 'static) -> SharedTerminal {
+        Rc::new(RefCell::new(Terminal::new(Box::new(backend))))
+    }
+
+    pub fn size(&self) -> Result<(usize, usize)> {
+        self.backend.size()
+    }
+
+    pub fn read_key(&mut self) -> Result<Key> {
+        self.backend.read_key()
+    }
+
+    pub fn draw(&mut self, mut screen: Screen) -> Result<()> {
+        let (_, height) = self.size()?;
+        screen.truncate(height);
+        self.backend.draw(&screen)?;
+        self.last_screen = screen;
+        Ok(())
+    }
+
+    /// Draws a framed box in the middle of the last screen without replacing it.
+    /// Lines too wide for the box are cut short, and lines below the screen dropped.
+    pub fn draw_dialog(&mut self, title: &str, body: &[Line]) -> Result<()> {
+        let (width, height) = self.size()?;
+        let box_width = width.saturating_sub(4).clamp(20, 72);
+        let inner_width = box_width - 4;
+        let left = " ".repeat(width.saturating_sub(box_width) / 2);
+
+        let mut dialog = vec![];
+        let title = format!("─ {} ", title);
+        dialog.push(Line::styled(format!("{}┌{}{}┐", left, title, "─".repeat(box_width.saturating_sub(display_width(&title) + 2))), Style::bold()));
+        for line in body.iter().take(height.saturating_sub(2)) {
+            let text = line.text();
+            let padding = inner_width.saturating_sub(display_width(&text));
+            let mut spans = vec![Span { text: format!("{}│ ", left), style: Style::bold() }];
+            if display_width(&text) > inner_width {
+                let style = line.spans.first().map_or_else(Style::default, |span| span.style);
+                spans.push(Span { text: get_column_string(&text, inner_width), style });
+            } else {
+                spans.extend(line.spans.iter().cloned());
+            }
+            spans.push(Span { text: format!("{} │", " ".repeat(padding)), style: Style::bold() });
+            dialog.push(Line { spans });
+        }
+        dialog.push(Line::styled(format!("{}└{}┘", left, "─".repeat(box_width - 2)), Style::bold()));
+
+        let mut screen = self.last_screen.clone();
+        while screen.lines.len() < height {
+            screen.blank();
+        }
+        let top = height.saturating_sub(dialog.len()) / 2;
+        for (idx, line) in dialog.into_iter().enumerate() {
+            if let Some(row) = screen.lines.get_mut(top + idx) {
+                *row = line;
+            }
+        }
+
+        self.backend.draw(&screen)
+    }
+
+    pub fn last_screen(&self) -> &Screen {
+        &self.last_screen
+    }
+}
+
+/// Backend for tests: replays scripted keys and keeps the drawn screens in memory.
+pub struct HeadlessBackend {
+    width: usize,
+    height: usize,
+    keys: VecDeque<Key>,
+    pub frames: Rc<RefCell<Vec<Screen>>>
+}
+
+impl HeadlessBackend {
+    pub fn new(width: usize, height: usize) -> Self {
+        HeadlessBackend { width, height, keys: VecDeque::new(), frames: Rc::new(RefCell::new(vec![])) }
+    }
+
+    pub fn with_keys(mut self, keys: Vec<Key>) -> Self {
+        self.keys.extend(keys);
+        self
+    }
+
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.keys.extend(text.chars().map(Key::Char));
+        self
+    }
+}
+
+impl Backend for HeadlessBackend {
+    fn size(&self) -> Result<(usize, usize)> {
+        Ok((self.width, self.height))
+    }
+
+    fn draw(&mut self, screen: &Screen) -> Result<()> {
+        self.frames.borrow_mut().push(screen.clone());
+        Ok(())
+    }
+
+    fn read_key(&mut self) -> Result<Key> {
+        self.keys.pop_front().ok_or_else(|| anyhow!("headless backend ran out of keys"))
+    }
+}
+
+/// Full-screen backend: switches the terminal to raw mode and the alternate
+/// screen for as long as it lives.
+pub struct CrosstermBackend {
+    stdout: Stdout
+}
+
+impl CrosstermBackend {
+    pub fn new() -> Result<Self> {
+        let mut stdout = stdout();
+        terminal::enable_raw_mode()?;
+        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(CrosstermBackend { stdout })
+    }
+}
+
+impl Drop for CrosstermBackend {
+    fn drop(&mut self) {
+        let _ = execute!(self.stdout, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl Backend for CrosstermBackend {
+    fn size(&self) -> Result<(usize, usize)> {
+        let (columns, rows) = terminal::size()?;
+        Ok((columns as usize, rows as usize))
+    }
+
+    fn draw(&mut self, screen: &Screen) -> Result<()> {
+        queue!(self.stdout, cursor::MoveTo(0, 0), terminal::Clear(terminal::ClearType::All))?;
+
+        for (row, line) in screen.lines().iter().enumerate() {
+            queue!(self.stdout, cursor::MoveTo(0, row as u16))?;
+            for span in &line.spans {
+                queue!(self.stdout, style::PrintStyledContent(span.style.content_style().apply(span.text.as_str())))?;
+            }
+        }
+
+        self.stdout.flush()?;
+        Ok(())
+    }
+
+    fn read_key(&mut self) -> Result<Key> {
+        loop {
+            let event = event::read()?;
+            if let Event::Resize(_, _) = event {
+                return Ok(Key::Resize);
+            }
+            if let Event::Key(key) = event {
+                if key.kind == KeyEventKind::Release {
+                    continue;
+                }
+
+                let key = match key.code {
+                    KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => Key::Ctrl(c),
+                    KeyCode::Char(c) => Key::Char(c),
+                    KeyCode::Enter => Key::Enter,
+                    KeyCode::Esc => Key::Esc,
+                    KeyCode::Backspace => Key::Backspace,
+                    KeyCode::Tab => Key::Tab,
+                    KeyCode::Up => Key::Up,
+                    KeyCode::Down => Key::Down,
+                    KeyCode::Left => Key::Left,
+                    KeyCode::Right => Key::Right,
+                    KeyCode::PageUp => Key::PageUp,
+                    KeyCode::PageDown => Key::PageDown,
+                    KeyCode::Home => Key::Home,
+                    KeyCode::End => Key::End,
+                    _ => continue
+                };
+                return Ok(key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draw_should_clip_screen_to_terminal_height() {
+        let backend = HeadlessBackend::new(20, 2);
+        let frames = Rc::clone(&backend.frames);
+        let terminal = Terminal::shared(backend);
+
+        let mut screen = Screen::new(20, 2);
+        screen.print("one");
+        screen.print("two");
+        screen.print("three");
+        terminal.borrow_mut().draw(screen).unwrap();
+
+        assert_eq!(terminal.borrow().last_screen().text(), vec!["one".to_owned(), "two".to_owned()]);
+        assert_eq!(frames.borrow().len(), 1);
+    }
+
+    #[test]
+    fn draw_dialog_should_overlay_last_screen() {
+        let backend = HeadlessBackend::new(40, 6);
+        let frames = Rc::clone(&backend.frames);
+        let terminal = Terminal::shared(backend);
+
+        let mut screen = Screen::new(40, 6);
+        screen.print("page header");
+        terminal.borrow_mut().draw(screen).unwrap();
+        terminal.borrow_mut().draw_dialog("Title", &[Line::from("body")]).unwrap();
+
+        let frames = frames.borrow();
+        let dialog = frames.last().unwrap().text();
+        assert_eq!(dialog.len(), 6);
+        assert_eq!(dialog[0], "page header".to_owned());
+        assert_eq!(dialog[1].trim().starts_with("┌─ Title"), true);
+        assert_eq!(dialog[2].contains("│ body"), true);
+        assert_eq!(dialog[3].trim().starts_with("└"), true);
+        assert_eq!(terminal.borrow().last_screen().text(), vec!["page header".to_owned()]);
+    }
+
+    #[test]
+    fn draw_dialog_should_keep_long_bodies_inside_the_frame() {
+        let backend = HeadlessBackend::new(30, 5);
+        let frames = Rc::clone(&backend.frames);
+        let terminal = Terminal::shared(backend);
+
+        let body = [Line::from("a line much wider than the dialog"), Line::from("two"), Line::from("three"), Line::from("four")];
+        terminal.borrow_mut().draw_dialog("Title", &body).unwrap();
+
+        let frames = frames.borrow();
+        let dialog = frames.last().unwrap().text();
+        assert_eq!(dialog.len(), 5);
+        assert_eq!(dialog[1], "  │ a line much wider t... │".to_owned());
+        assert_eq!(dialog[4].trim().starts_with("└"), true);
+        assert_eq!(dialog.iter().all(|line| display_width(line) <= 30), true);
+    }
+
+    #[test]
+    fn headless_backend_should_replay_keys() {
+        let mut backend = HeadlessBackend::new(80, 24).with_keys(vec![Key::Down]).with_text("q");
+
+        assert_eq!(backend.read_key().unwrap(), Key::Down);
+        assert_eq!(backend.read_key().unwrap(), Key::Char('q'));
+        assert_eq!(backend.read_key().is_err(), true);
+    }
+}
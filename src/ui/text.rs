@@ -0,0 +1,186 @@
+use std::cmp::Ordering;
+use std::mem;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Number of terminal cells a grapheme cluster occupies. Emoji sequences (ZWJ
+/// families, flags, skin tones) are drawn as a single wide glyph, so a cluster is
+/// never wider than two cells.
+pub fn grapheme_width(grapheme: &str) -> usize {
+    grapheme.width().min(2)
+}
+
+/// Number of terminal cells `text` occupies, e.g. 2 for "日" and 1 for "é" even
+/// when it is written with a combining accent.
+pub fn display_width(text: &str) -> usize {
+    text.graphemes(true).map(grapheme_width).sum()
+}
+
+/// Pads or truncates `text` to exactly `width` terminal cells. Truncated text ends
+/// with "..." and is never cut inside a grapheme cluster; when a wide character
+/// doesn't fit in front of the ellipsis the gap is filled with a space.
+pub fn get_column_string(text: &str, width: usize) -> String {
+    let text_width = display_width(text);
+
+    match text_width.cmp(&width) {
+        Ordering::Equal => text.to_owned(),
+        Ordering::Less => format!("{}{}", text, " ".repeat(width - text_width)),
+        Ordering::Greater => {
+            if width <= 3 {
+                return ".".repeat(width);
+            }
+
+            let available = width - 3;
+            let mut result = String::new();
+            let mut used = 0;
+            for grapheme in text.graphemes(true) {
+                let grapheme_width = grapheme_width(grapheme);
+                if used + grapheme_width > available {
+                    break;
+                }
+                result.push_str(grapheme);
+                used += grapheme_width;
+            }
+
+            format!("{}...{}", result, " ".repeat(available - used))
+        }
+    }
+}
+
+/// Splits `text` into lines of at most `width` cells. Lines break at whitespace;
+/// words longer than a line are broken between grapheme clusters.
+pub fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    let mut line_width = 0;
+
+    for word in text.split_whitespace() {
+        let word_width = display_width(word);
+        if line_width > 0 && line_width + 1 + word_width <= width {
+            line.push(' ');
+            line.push_str(word);
+            line_width += 1 + word_width;
+            continue;
+        }
+
+        if line_width > 0 {
+            lines.push(mem::take(&mut line));
+            line_width = 0;
+        }
+
+        if word_width <= width {
+            line.push_str(word);
+            line_width = word_width;
+            continue;
+        }
+
+        for grapheme in word.graphemes(true) {
+            let grapheme_width = grapheme_width(grapheme);
+            if line_width > 0 && line_width + grapheme_width > width {
+                lines.push(mem::take(&mut line));
+                line_width = 0;
+            }
+            line.push_str(grapheme);
+            line_width += grapheme_width;
+        }
+    }
+
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_column_string() {
+        let text1 = "";
+        let text2 = "test";
+        let text3 = "testme";
+        let text4 = "testmetest";
+
+        let width = 0;
+
+        assert_eq!(get_column_string(text4, width), "".to_owned());
+
+        let width = 1;
+
+        assert_eq!(get_column_string(text4, width), ".".to_owned());
+
+        let width = 2;
+
+        assert_eq!(get_column_string(text4, width), "..".to_owned());
+
+        let width = 3;
+
+        assert_eq!(get_column_string(text4, width), "...".to_owned());
+
+        let width = 4;
+
+        assert_eq!(get_column_string(text4, width), "t...".to_owned());
+
+        let width = 6;
+
+        assert_eq!(get_column_string(text1, width), "      ".to_owned());
+        assert_eq!(get_column_string(text2, width), "test  ".to_owned());
+        assert_eq!(get_column_string(text3, width), "testme".to_owned());
+        assert_eq!(get_column_string(text4, width), "tes...".to_owned());
+    }
+
+    #[test]
+    fn test_get_column_string_cyrillic() {
+        let text = "Оновлення профілю";
+
+        assert_eq!(display_width(text), 17);
+        assert_eq!(get_column_string(text, 20), "Оновлення профілю   ".to_owned());
+        assert_eq!(get_column_string(text, 12), "Оновлення...".to_owned());
+        assert_eq!(get_column_string("Київ", 4), "Київ".to_owned());
+    }
+
+    #[test]
+    fn test_get_column_string_cjk() {
+        let text = "日本語のテキスト";
+
+        assert_eq!(display_width(text), 16);
+        assert_eq!(get_column_string(text, 18), "日本語のテキスト  ".to_owned());
+        assert_eq!(get_column_string(text, 9), "日本語...".to_owned());
+        assert_eq!(get_column_string(text, 10), "日本語... ".to_owned());
+        assert_eq!(display_width(&get_column_string(text, 10)), 10);
+    }
+
+    #[test]
+    fn test_get_column_string_combining_marks() {
+        let decomposed = "cafe\u{301} au lait";
+
+        assert_eq!(display_width(decomposed), 12);
+        assert_eq!(get_column_string(decomposed, 14), "cafe\u{301} au lait  ".to_owned());
+        assert_eq!(get_column_string(decomposed, 7), "cafe\u{301}...".to_owned());
+        assert_eq!(get_column_string("й", 1), "й".to_owned());
+    }
+
+    #[test]
+    fn test_get_column_string_emoji() {
+        let family = "👨\u{200d}👩\u{200d}👧";
+        let flag = "🇺🇦";
+
+        assert_eq!(display_width(family), 2);
+        assert_eq!(display_width(flag), 2);
+        assert_eq!(display_width("🚀 launch"), 9);
+        assert_eq!(get_column_string("🚀 launch", 11), "🚀 launch  ".to_owned());
+        assert_eq!(get_column_string(&format!("{}{} release", flag, family), 7), format!("{}{}...", flag, family));
+        assert_eq!(get_column_string(&format!("{}{} release", flag, family), 6), format!("{}... ", flag));
+    }
+
+    #[test]
+    fn test_wrap_text() {
+        assert_eq!(wrap_text("", 10), vec!["".to_owned()]);
+        assert_eq!(wrap_text("fix the login page", 10), vec!["fix the".to_owned(), "login page".to_owned()]);
+        assert_eq!(wrap_text("  spaces   collapse  ", 20), vec!["spaces collapse".to_owned()]);
+        assert_eq!(wrap_text("abcdefghij", 4), vec!["abcd".to_owned(), "efgh".to_owned(), "ij".to_owned()]);
+        assert_eq!(wrap_text("日本語のテキスト", 5), vec!["日本".to_owned(), "語の".to_owned(), "テキ".to_owned(), "スト".to_owned()]);
+        assert_eq!(wrap_text("Оновлення профілю", 12), vec!["Оновлення".to_owned(), "профілю".to_owned()]);
+    }
+}
@@ -0,0 +1,194 @@
+use std::env;
+use std::ffi::OsString;
+use std::io::{self, IsTerminal};
+use serde::{Deserialize, Serialize};
+use crate::models::{Priority, Status};
+use crate::ui::terminal::{Color, Style};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+}
+
+/// Styles pages use for status badges, priorities and whole rows.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Theme {
+    pub header: Style,
+    pub selected: Style,
+    pub todo: Style,
+    pub in_progress: Style,
+    pub done: Style,
+    /// Indexed by `Priority`, from lowest to highest.
+    pub priorities: [Style; 5],
+    /// Rows of resolved and closed items.
+    pub closed: Style,
+    /// Rows of items past their due date.
+    pub overdue: Style
+}
+
+impl Theme {
+    /// No colors at all, only attributes every terminal understands.
+    pub fn monochrome() -> Self {
+        Theme {
+            header: Style::bold(),
+            selected: Style::reversed(),
+            todo: Style::default(),
+            in_progress: Style::bold(),
+            done: Style::default(),
+            priorities: [Style::default(), Style::default(), Style::default(), Style::bold(), Style::bold()],
+            closed: Style::dim(),
+            overdue: Style::bold()
+        }
+    }
+
+    pub fn dark() -> Self {
+        Theme {
+            header: Style::fg(Color::Cyan).with_bold(),
+            selected: Style::reversed(),
+            todo: Style::fg(Color::Blue),
+            in_progress: Style::fg(Color::Yellow),
+            done: Style::fg(Color::Green),
+            priorities: [
+                Style::fg(Color::DarkGrey),
+                Style::fg(Color::Grey),
+                Style::fg(Color::White),
+                Style::fg(Color::Magenta),
+                Style::fg(Color::Red).with_bold(),
+            ],
+            closed: Style::dim(),
+            overdue: Style::fg(Color::Red)
+        }
+    }
+
+    pub fn light() -> Self {
+        Theme {
+            header: Style::fg(Color::DarkBlue).with_bold(),
+            selected: Style::reversed(),
+            todo: Style::fg(Color::DarkBlue),
+            in_progress: Style::fg(Color::DarkYellow),
+            done: Style::fg(Color::DarkGreen),
+            priorities: [
+                Style::fg(Color::Grey),
+                Style::fg(Color::DarkGrey),
+                Style::fg(Color::Black),
+                Style::fg(Color::DarkMagenta),
+                Style::fg(Color::DarkRed).with_bold(),
+            ],
+            closed: Style::dim(),
+            overdue: Style::fg(Color::DarkRed)
+        }
+    }
+
+    /// Bold, saturated colors and no dimming, for low vision and bright screens.
+    pub fn high_contrast() -> Self {
+        Theme {
+            header: Style::fg(Color::White).with_bold(),
+            selected: Style::fg(Color::Black).with_bg(Color::Yellow).with_bold(),
+            todo: Style::fg(Color::Cyan).with_bold(),
+            in_progress: Style::fg(Color::Yellow).with_bold(),
+            done: Style::fg(Color::Green).with_bold(),
+            priorities: [
+                Style::fg(Color::White),
+                Style::fg(Color::White),
+                Style::fg(Color::White).with_bold(),
+                Style::fg(Color::Yellow).with_bold(),
+                Style::fg(Color::Red).with_bold(),
+            ],
+            closed: Style::fg(Color::Grey),
+            overdue: Style::fg(Color::White).with_bg(Color::Red).with_bold()
+        }
+    }
+
+    pub fn named(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Dark => Theme::dark(),
+            ThemeName::Light => Theme::light(),
+            ThemeName::HighContrast => Theme::high_contrast()
+        }
+    }
+
+    /// The configured theme, or `Theme::monochrome` when colors are turned off.
+    pub fn detect(name: ThemeName) -> Self {
+        if colors_enabled() {
+            Theme::named(name)
+        } else {
+            Theme::monochrome()
+        }
+    }
+
+    /// Badge style for a status, by category: to do, in progress or done.
+    pub fn status(&self, status: &Status) -> Style {
+        match status {
+            Status::Open => self.todo,
+            Status::InProgress => self.in_progress,
+            Status::Resolved | Status::Closed => self.done
+        }
+    }
+
+    pub fn priority(&self, priority: Priority) -> Style {
+        self.priorities[priority as usize]
+    }
+
+    /// Style of a whole row; overdue items stand out, finished ones fade.
+    pub fn row(&self, status: &Status, overdue: bool) -> Style {
+        if overdue {
+            self.overdue
+        } else if status.is_done() {
+            self.closed
+        } else {
+            Style::default()
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::monochrome()
+    }
+}
+
+/// Colors are off when `NO_COLOR` is set to a non-empty value (https://no-color.org)
+/// or when stdout isn't a terminal.
+pub fn colors_enabled() -> bool {
+    colors_allowed(env::var_os("NO_COLOR"), io::stdout().is_terminal())
+}
+
+fn colors_allowed(no_color: Option<OsString>, is_terminal: bool) -> bool {
+    let no_color = no_color.is_some_and(|value| !value.is_empty());
+    !no_color && is_terminal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_allowed_should_respect_no_color_and_tty() {
+        assert_eq!(colors_allowed(None, true), true);
+        assert_eq!(colors_allowed(Some(OsString::from("1")), true), false);
+        assert_eq!(colors_allowed(Some(OsString::from("")), true), true);
+        assert_eq!(colors_allowed(None, false), false);
+    }
+
+    #[test]
+    fn theme_should_style_statuses_by_category() {
+        let theme = Theme::dark();
+
+        assert_eq!(theme.status(&Status::Open), Style::fg(Color::Blue));
+        assert_eq!(theme.status(&Status::Resolved), theme.status(&Status::Closed));
+        assert_eq!(theme.priority(Priority::Highest), Style::fg(Color::Red).with_bold());
+        assert_eq!(theme.row(&Status::Closed, false), Style::dim());
+        assert_eq!(theme.row(&Status::Open, true), Style::fg(Color::Red));
+        assert_eq!(theme.row(&Status::Open, false), Style::default());
+    }
+
+    #[test]
+    fn theme_names_should_deserialize_from_kebab_case() {
+        assert_eq!(serde_json::from_str::<ThemeName>(r#""high-contrast""#).unwrap(), ThemeName::HighContrast);
+        assert_eq!(serde_json::from_str::<ThemeName>(r#""light""#).unwrap(), ThemeName::Light);
+    }
+}
@@ -0,0 +1,439 @@
+use std::mem;
+use std::rc::Rc;
+use anyhow::{anyhow, Result};
+use crate::db::ProjectsDatabase;
+use crate::keymap::Keymap;
+use crate::models::Action;
+use crate::navigator::Navigator;
+use crate::session::Session;
+use crate::ui::dialogs;
+use crate::ui::pages::{parse_global_command, KeyOutcome};
+use crate::ui::prompts::Prompts;
+use crate::ui::terminal::{Key, Line, Screen, SharedTerminal, Style};
+use crate::ui::text::{display_width, get_column_string};
+use crate::ui::theme::Theme;
+
+/// Full-screen event loop: draws the current page between a breadcrumb line and a
+/// status bar and routes key presses to the page, its command line or the `Navigator`.
+///
+/// Keys a page doesn't bind itself are typed into the command line: a key sequence
+/// from the keymap runs as soon as it is complete, anything else (e.g. an id) is
+/// submitted with Enter.
+pub struct Tui {
+    terminal: SharedTerminal,
+    navigator: Navigator,
+    theme: Theme,
+    keymap: Rc<Keymap>,
+    page_size: Option<usize>,
+    session_file: Option<String>,
+    command: String,
+    status: Option<String>
+}
+
+impl Tui {
+    pub fn new(terminal: SharedTerminal, db: Rc<ProjectsDatabase>) -> Self {
+        let prompts = Prompts::new(Rc::clone(&terminal));
+        Tui {
+            navigator: Navigator::new(db, prompts),
+            terminal,
+            theme: Theme::default(),
+            keymap: Rc::new(Keymap::default()),
+            page_size: None,
+            session_file: None,
+            command: String::new(),
+            status: None
+        }
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn with_keymap(mut self, keymap: Keymap) -> Self {
+        self.keymap = Rc::new(keymap);
+        self
+    }
+
+    /// Rows per list page; `None` fits as many rows as the terminal has room for.
+    pub fn with_page_size(mut self, page_size: Option<usize>) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Restores the pages open when the tool was last closed from `file_path` and
+    /// saves them there again on exit.
+    pub fn with_session_file(mut self, file_path: &str) -> Self {
+        self.session_file = Some(file_path.to_owned());
+        self
+    }
+
+    pub fn run(&mut self) -> Result<()> {
+        self.restore_session();
+
+        // Exiting clears the page stack, so the session saved is the one from
+        // before the last key press.
+        let mut session = self.navigator.session();
+        while self.navigator.get_current_page().is_some() {
+            self.render()?;
+            let key = self.terminal.borrow_mut().read_key()?;
+            session = self.navigator.session();
+            if let Err(error) = self.handle_key(key) {
+                self.status = Some(format!("{:#}", error));
+            }
+        }
+
+        match &self.session_file {
+            Some(file_path) => session.save(file_path),
+            None => Ok(())
+        }
+    }
+
+    fn restore_session(&mut self) {
+        if let Some(file_path) = &self.session_file {
+            let restored = Session::load(file_path).and_then(|session| self.navigator.restore(session));
+            if let Err(error) = restored {
+                self.status = Some(format!("couldn't restore the last session: {:#}", error));
+            }
+        }
+    }
+
+    fn render(&self) -> Result<()> {
+        let (width, height) = self.terminal.borrow().size()?;
+        let page_height = height.saturating_sub(2);
+
+        let mut page_screen = Screen::new(width, page_height)
+            .with_theme(self.theme.clone())
+            .with_page_size(self.page_size)
+            .with_keymap(Rc::clone(&self.keymap));
+        if let Some(page) = self.navigator.get_current_page() {
+            if let Err(error) = page.draw_page(&mut page_screen) {
+                page_screen.print(format!("Error: {:#}", error));
+            }
+        }
+
+        let mut screen = Screen::new(width, height);
+        screen.print(self.breadcrumb_line(width));
+        for line in page_screen.lines().iter().take(page_height) {
+            screen.print(line.clone());
+        }
+        while screen.lines().len() < page_height + 1 {
+            screen.blank();
+        }
+        screen.print(self.status_bar(width));
+
+        self.terminal.borrow_mut().draw(screen)
+    }
+
+    fn breadcrumb_line(&self, width: usize) -> Line {
+        let text = self.navigator.breadcrumbs().join(" \u{203a} ");
+        Line::styled(get_column_string(&text, width), Style::dim())
+    }
+
+    fn status_bar(&self, width: usize) -> Line {
+        let text = if !self.command.is_empty() {
+            format!(" :{}_", self.command)
+        } else if let Some(status) = &self.status {
+            format!(" {}", status)
+        } else {
+            " type a command or an id and press [enter] | [g <id>] go to item | [^n] breadcrumb | [ctrl+b/f] back/forward | [ctrl+p] commands | [ctrl+c] quit".to_owned()
+        };
+        let padding = width.saturating_sub(display_width(&text));
+        Line::styled(format!("{}{}", text, " ".repeat(padding)), Style::reversed())
+    }
+
+    fn handle_key(&mut self, key: Key) -> Result<()> {
+        if key == Key::Ctrl('c') {
+            return self.dispatch(Action::Exit);
+        }
+
+        if !self.command.is_empty() {
+            return self.handle_command_key(key);
+        }
+        match key {
+            Key::Ctrl('b') => return self.dispatch(Action::NavigateToPreviousPage),
+            Key::Ctrl('f') => return self.dispatch(Action::NavigateForward),
+            Key::Ctrl('p') => return self.open_palette(),
+            _ => {}
+        }
+
+        self.status = None;
+        let page = self.navigator
+            .get_current_page_mut()
+            .ok_or_else(|| anyhow!("no page to handle input"))?;
+
+        match page.handle_key(key)? {
+            KeyOutcome::Action(action) => self.dispatch(action),
+            KeyOutcome::Handled => Ok(()),
+            KeyOutcome::Ignored => match key {
+                Key::Char(c) => self.type_command_char(c),
+                _ => Ok(())
+            }
+        }
+    }
+
+    /// Adds a character to the command line and runs the bound command once the
+    /// typed keys match a binding of the current page.
+    fn type_command_char(&mut self, c: char) -> Result<()> {
+        self.command.push(c);
+
+        let page = self.navigator
+            .get_current_page()
+            .ok_or_else(|| anyhow!("no page to handle input"))?;
+        if let Some(command) = self.keymap.command(page.kind(), &self.command) {
+            if let Some(action) = page.command_action(command)? {
+                self.command.clear();
+                return self.dispatch(action);
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_command_key(&mut self, key: Key) -> Result<()> {
+        match key {
+            Key::Enter => {
+                let command = mem::take(&mut self.command);
+                let page = self.navigator
+                    .get_current_page()
+                    .ok_or_else(|| anyhow!("no page to handle input"))?;
+
+                let action = match parse_global_command(command.trim()) {
+                    Some(action) => Some(action),
+                    None => self.keymap.action(page.as_ref(), command.trim())?
+                };
+                match action {
+                    Some(action) => self.dispatch(action)?,
+                    None => self.status = Some(format!("Unknown command '{}'", command.trim()))
+                }
+            }
+            Key::Esc => self.command.clear(),
+            Key::Backspace => {
+                self.command.pop();
+            }
+            Key::Char(c) => return self.type_command_char(c),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn open_palette(&mut self) -> Result<()> {
+        let entries = self.navigator.palette_entries(&self.keymap)?;
+        let labels = entries.iter().map(|entry| entry.label.clone()).collect::<Vec<_>>();
+
+        match dialogs::palette(&self.terminal, "Commands", &labels) {
+            Some(idx) => self.dispatch(entries[idx].action.clone()),
+            None => Ok(())
+        }
+    }
+
+    fn dispatch(&mut self, action: Action) -> Result<()> {
+        self.navigator.handle_action(action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_utils::MockDb;
+    use std::collections::HashMap;
+    use crate::keymap::{Command, PageKind};
+    use crate::models::{Epic, Story};
+    use crate::ui::terminal::{HeadlessBackend, Terminal};
+    use crate::ui::{EpicDetail, HomePage};
+
+    fn db_with_epics(count: usize) -> Rc<ProjectsDatabase> {
+        let db = Rc::new(ProjectsDatabase { database: Box::new(MockDb::new()) });
+        for idx in 0..count {
+            db.create_epic(Epic::new(format!("epic {}", idx + 1), "".to_owned())).unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn run_should_exit_on_q() {
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24).with_text("q"));
+        let mut tui = Tui::new(Rc::clone(&terminal), db_with_epics(1));
+
+        assert_eq!(tui.run().is_ok(), true);
+
+        let screen = terminal.borrow().last_screen().text();
+        assert_eq!(screen.len(), 24);
+        assert_eq!(screen[0].trim_end(), "Home");
+        assert_eq!(screen[1].contains("EPICS"), true);
+        assert_eq!(screen[3].contains("epic 1"), true);
+        assert_eq!(screen[23].starts_with(" type a command"), true);
+    }
+
+    #[test]
+    fn arrow_keys_should_select_rows_and_enter_should_open_them() {
+        let db = db_with_epics(3);
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24)
+            .with_keys(vec![Key::Down, Key::Down, Key::Up, Key::Enter]));
+        let mut tui = Tui::new(terminal, Rc::clone(&db));
+
+        assert_eq!(tui.run().is_err(), true);
+
+        let page = tui.navigator.get_current_page().unwrap();
+        let epic_detail = page.as_any().downcast_ref::<EpicDetail>().unwrap();
+        assert_eq!(epic_detail.epic_id, 2);
+    }
+
+    #[test]
+    fn typed_ids_should_be_submitted_with_enter() {
+        let db = db_with_epics(12);
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24)
+            .with_text("12")
+            .with_keys(vec![Key::Enter])
+            .with_text("p99")
+            .with_keys(vec![Key::Enter]));
+        let mut tui = Tui::new(Rc::clone(&terminal), db);
+
+        assert_eq!(tui.run().is_err(), true);
+        assert_eq!(tui.navigator.get_current_page().unwrap().as_any().downcast_ref::<HomePage>().is_some(), true);
+
+        let screen = terminal.borrow().last_screen().text();
+        assert_eq!(screen[23].starts_with(" Unknown command '99'"), true);
+    }
+
+    #[test]
+    fn lists_should_page_to_the_selected_row() {
+        let db = db_with_epics(30);
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 10)
+            .with_keys(vec![Key::End])
+            .with_text("q"));
+        let mut tui = Tui::new(Rc::clone(&terminal), db);
+
+        assert_eq!(tui.run().is_ok(), true);
+
+        let screen = terminal.borrow().last_screen().text();
+        assert_eq!(screen[3].contains("epic 29"), true);
+        assert_eq!(screen[4].contains("epic 30"), true);
+        assert_eq!(screen[6], "page 15/15 | sorted by id (ascending)".to_owned());
+        assert_eq!(screen[8].starts_with("[q] quit"), true);
+    }
+
+    #[test]
+    fn configured_page_size_should_limit_list_pages() {
+        let db = db_with_epics(12);
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24)
+            .with_keys(vec![Key::PageDown])
+            .with_text("q"));
+        let mut tui = Tui::new(Rc::clone(&terminal), db).with_page_size(Some(5));
+
+        assert_eq!(tui.run().is_ok(), true);
+
+        let screen = terminal.borrow().last_screen().text();
+        assert_eq!(screen[3].contains("epic 6"), true);
+        assert_eq!(screen[7].contains("epic 10"), true);
+        assert_eq!(screen[9], "page 2/3 | sorted by id (ascending)".to_owned());
+    }
+
+    #[test]
+    fn go_to_command_should_open_items_from_any_page() {
+        let db = db_with_epics(2);
+        db.create_story(Story::new("a story".to_owned(), "".to_owned()), 2).unwrap();
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24)
+            .with_text("1")
+            .with_keys(vec![Key::Enter])
+            .with_text("#3")
+            .with_keys(vec![Key::Enter])
+            .with_text("g 99")
+            .with_keys(vec![Key::Enter, Key::Ctrl('c')]));
+        let mut tui = Tui::new(Rc::clone(&terminal), db);
+
+        assert_eq!(tui.run().is_ok(), true);
+
+        let screen = terminal.borrow().last_screen().text();
+        assert_eq!(screen[0].trim_end(), "Home \u{203a} Epic 1 \"epic 1\" \u{203a} Epic 2 \"epic 2\" \u{203a} Story 3 \"a story\"");
+        assert_eq!(screen[3].contains("a story"), true);
+        assert_eq!(screen[23].starts_with(" no epic or story with key 99"), true);
+    }
+
+    #[test]
+    fn breadcrumb_jumps_and_history_keys_should_move_through_pages() {
+        let db = db_with_epics(1);
+        db.create_story(Story::new("a story".to_owned(), "".to_owned()), 1).unwrap();
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24)
+            .with_text("#2")
+            .with_keys(vec![Key::Enter])
+            .with_text("^1")
+            .with_keys(vec![Key::Enter, Key::Ctrl('f'), Key::Ctrl('f'), Key::Ctrl('b')]));
+        let mut tui = Tui::new(Rc::clone(&terminal), db);
+
+        assert_eq!(tui.run().is_err(), true);
+        assert_eq!(tui.navigator.breadcrumbs(), vec!["Home".to_owned(), "Epic 1 \"epic 1\"".to_owned()]);
+
+        let screen = terminal.borrow().last_screen().text();
+        assert_eq!(screen[0].trim_end(), "Home \u{203a} Epic 1 \"epic 1\"");
+    }
+
+    #[test]
+    fn session_should_be_restored_on_the_next_run() {
+        let db = db_with_epics(3);
+        let session_file = tempfile::NamedTempFile::new().unwrap();
+        let path = session_file.path().to_str().unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24)
+            .with_keys(vec![Key::Char('O'), Key::Down, Key::Enter, Key::Ctrl('c')]));
+        let mut tui = Tui::new(terminal, Rc::clone(&db)).with_session_file(path);
+        assert_eq!(tui.run().is_ok(), true);
+
+        db.delete_epic(2).unwrap();
+
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24).with_text("q"));
+        let mut tui = Tui::new(Rc::clone(&terminal), Rc::clone(&db)).with_session_file(path);
+        assert_eq!(tui.run().is_ok(), true);
+
+        let screen = terminal.borrow().last_screen().text();
+        assert_eq!(screen[0].trim_end(), "Home");
+        assert_eq!(screen[3].contains("epic 3"), true);
+        assert_eq!(screen[6].contains("sorted by id (descending)"), true);
+        assert_eq!(terminal.borrow().last_screen().lines()[4].spans[0].style, Style::reversed());
+    }
+
+    #[test]
+    fn key_sequences_from_the_keymap_should_run_when_complete() {
+        let overrides = HashMap::from([(PageKind::Home, HashMap::from([(Command::Board, "vb".to_owned())]))]);
+        let keymap = Keymap::with_overrides(overrides).unwrap();
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24)
+            .with_text("b")
+            .with_keys(vec![Key::Esc])
+            .with_text("vb"));
+        let mut tui = Tui::new(Rc::clone(&terminal), db_with_epics(1)).with_keymap(keymap);
+
+        assert_eq!(tui.run().is_err(), true);
+        assert_eq!(tui.navigator.breadcrumbs(), vec!["Home".to_owned(), "Board".to_owned()]);
+    }
+
+    #[test]
+    fn palette_should_run_the_chosen_action() {
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24)
+            .with_keys(vec![Key::Ctrl('p')])
+            .with_text("sprnt")
+            .with_keys(vec![Key::Enter, Key::Ctrl('p'), Key::Esc]));
+        let mut tui = Tui::new(terminal, db_with_epics(1));
+
+        assert_eq!(tui.run().is_err(), true);
+        assert_eq!(tui.navigator.breadcrumbs(), vec!["Home".to_owned(), "Sprints".to_owned()]);
+    }
+
+    #[test]
+    fn modal_dialogs_should_create_epic() {
+        let db = db_with_epics(0);
+        let terminal = Terminal::shared(HeadlessBackend::new(80, 24)
+            .with_text("cname")
+            .with_keys(vec![Key::Enter])
+            .with_text("description")
+            .with_keys(vec![Key::Enter])
+            .with_text("q"));
+        let mut tui = Tui::new(terminal, Rc::clone(&db));
+
+        assert_eq!(tui.run().is_ok(), true);
+
+        let db_state = db.read_db().unwrap();
+        let epic = db_state.epics.get(&1).unwrap();
+        assert_eq!(epic.name, "name".to_owned());
+        assert_eq!(epic.description, "description".to_owned());
+    }
+}